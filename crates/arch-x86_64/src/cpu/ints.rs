@@ -156,7 +156,7 @@ pub extern "x86-interrupt" fn security_exception(stack: InterruptStackFrame, ec:
 // other ints
 
 pub mod other {
-    use hyperion_interrupts::interrupt_handler;
+    use hyperion_interrupts::{interrupt_handler, Interrupted};
     use x86_64::structures::idt::InterruptStackFrame;
 
     hyperion_macros::gen_int_handlers!("x86-interrupt");
@@ -1,4 +1,8 @@
-use core::{arch::naked_asm, fmt, mem::offset_of};
+use core::{
+    arch::naked_asm,
+    fmt,
+    mem::{self, offset_of},
+};
 
 use crossbeam::atomic::AtomicCell;
 use x86_64::{
@@ -69,6 +73,55 @@ pub struct SyscallRegs {
     pub user_stack_ptr: u64, // rsp
 }
 
+impl SyscallRegs {
+    /// where [`SignalFrame`] gets pushed on the user stack
+    ///
+    /// the frame skips the red zone and is 16 byte aligned
+    pub fn signal_frame_addr(&self) -> Option<u64> {
+        self.user_stack_ptr
+            .checked_sub(RED_ZONE + mem::size_of::<SignalFrame>() as u64)
+            .map(|addr| addr & !0xf)
+    }
+
+    /// return from this syscall into a signal entry point
+    ///
+    /// `rdi` = signal, `rsi` = handler and `rsp` = `frame`,
+    /// the user controlled `entry` has to be a lower half address (the `sysret` bug)
+    pub fn enter_signal(&mut self, entry: u64, frame: u64, signal: u64, handler: u64) -> bool {
+        if !is_lower_half(entry) {
+            return false;
+        }
+
+        self.user_instr_ptr = entry;
+        self.user_stack_ptr = frame;
+        self.arg0 = signal;
+        self.arg1 = handler;
+        true
+    }
+
+    /// restore the registers saved in a [`SignalFrame`]
+    ///
+    /// the saved registers are user controlled, so only the arithmetic and direction flags are
+    /// restored and the instruction pointer has to be a lower half address (the `sysret` bug)
+    pub fn restore_signal(&mut self, saved: &SyscallRegs) -> bool {
+        if !is_lower_half(saved.user_instr_ptr) {
+            return false;
+        }
+
+        let user_flags = RFlags::CARRY_FLAG
+            | RFlags::PARITY_FLAG
+            | RFlags::AUXILIARY_CARRY_FLAG
+            | RFlags::ZERO_FLAG
+            | RFlags::SIGN_FLAG
+            | RFlags::DIRECTION_FLAG
+            | RFlags::OVERFLOW_FLAG;
+
+        *self = *saved;
+        self.rflags = (saved.rflags & user_flags.bits()) | RFlags::INTERRUPT_FLAG.bits();
+        true
+    }
+}
+
 impl fmt::Display for SyscallRegs {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         writeln!(
@@ -80,6 +133,24 @@ impl fmt::Display for SyscallRegs {
     }
 }
 
+/// the state saved on the user stack while a signal handler runs
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SignalFrame {
+    /// registers of the syscall that got interrupted by the signal
+    pub regs: SyscallRegs,
+    /// blocked signal mask before the signal handler
+    pub blocked: u64,
+}
+
+/// the System V ABI red zone below the stack pointer
+const RED_ZONE: u64 = 128;
+
+/// `sysret` into a non canonical address faults in ring 0
+const fn is_lower_half(addr: u64) -> bool {
+    addr < 0x0000_8000_0000_0000
+}
+
 //
 
 /// jump into the instruction pointer with a given stack and arguments
@@ -92,11 +163,13 @@ pub extern "sysv64" fn userland(
     _stack_ptr: VirtAddr,
     _argc: u64,
     _argv: u64,
+    _auxv: u64,
 ) -> ! {
     // rdi = _instr_ptr
     // rsi = _stack_ptr
     // rdx = _argc
     // rcx = _argv
+    // r8 = _auxv
 
     // SAFETY:
     // the processor jumps into user space with user privileges so it can't hurt the kernel
@@ -104,6 +177,7 @@ pub extern "sysv64" fn userland(
     // this call won't return
     unsafe {
         naked_asm!(
+            "mov r9, r8",  // tmp save auxv
             "mov r8, rcx", // tmp save argc
 
             // setup sysretq args
@@ -111,15 +185,16 @@ pub extern "sysv64" fn userland(
             "mov rsp, rsi",
             "mov r11, {rflags}",
 
-            // setup argc,argv
+            // setup argc,argv,auxv
             "mov rsi, r8",
             "mov rdi, rdx",
+            "mov rdx, r9",
 
             // clear some registers
             "xor rax, rax",
             "xor rbx, rbx",
             // no zeroing rcx, sysreq returns to the address in it (`instr_ptr`)
-            "xor rdx, rdx",
+            // no zeroing rdx, it holds auxv
             // "xor rdi, rdi",
             // "xor rsi, rsi",
             "xor rbp, rbp",
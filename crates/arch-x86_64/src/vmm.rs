@@ -23,20 +23,24 @@
 
 #![allow(clippy::comparison_chain)]
 
+use alloc::{boxed::Box, vec::Vec};
 use core::{
     arch::asm,
+    hint::spin_loop,
     mem::ManuallyDrop,
     ops::Range,
-    sync::atomic::{fence, Ordering},
+    sync::atomic::{fence, AtomicU64, Ordering},
 };
 
+use hyperion_cpu_id::{cpu_count, cpu_id};
+use hyperion_driver_acpi::apic::{self, Ipi, IpiTarget};
 use hyperion_mem::{
     from_higher_half, is_higher_half,
     pmm::{self, PageFrame},
     to_higher_half,
     vmm::{Handled, MapTarget, MemoryInfo, NotHandled, PageFaultResult, PageMapImpl, Privilege},
 };
-use spin::{Once, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use spin::{Lazy, Once, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use x86_64::{
     instructions::tlb,
     registers::control::{Cr3, Cr3Flags},
@@ -73,9 +77,15 @@ pub const LAZY_ALLOC: PageTableFlags = PageTableFlags::BIT_52;
 
 // static HHDM_KERNEL_L4E: Once<(PageTableEntry, PageTableEntry)> = Once::new();
 
+/// TLB flushes started and finished by each CPU, odd while one is in progress
+static TLB_FLUSHES: Lazy<Box<[AtomicU64]>> =
+    Lazy::new(|| (0..cpu_count()).map(|_| AtomicU64::new(0)).collect());
+
 //
 
 pub fn init() {
+    Ipi::TlbShootdown.handler().store(|_| flush_tlb());
+
     /* HHDM_KERNEL_L4E.call_once(|| {
         let boot_map = PageMap::current();
         let inner = boot_map.inner.0.write();
@@ -125,6 +135,50 @@ pub fn init() {
     }); */
 }
 
+/// flush the TLB of this CPU for the CPUs waiting in [`tlb_shootdown`]
+fn flush_tlb() {
+    let flushes = &TLB_FLUSHES[cpu_id()];
+    flushes.fetch_add(1, Ordering::SeqCst);
+    MapperFlushAll::new().flush_all();
+    flushes.fetch_add(1, Ordering::SeqCst);
+}
+
+/// flush the TLBs of the CPUs in the mask and wait for them to finish, bit n is CPU n
+fn tlb_shootdown(cpus: u64) {
+    // a flush that is already in progress might have started before the page map was modified,
+    // so wait for the next complete one
+    let wait_for: Vec<(usize, u64)> = (0..TLB_FLUSHES.len())
+        .filter(|cpu| cpus & cpu_bit(*cpu) != 0)
+        .map(|cpu| {
+            let flushes = TLB_FLUSHES[cpu].load(Ordering::SeqCst);
+            (cpu, flushes + 2 + (flushes & 1))
+        })
+        .collect();
+
+    apic::send_ipi(IpiTarget::Cpus(cpus), Ipi::TlbShootdown);
+
+    for (cpu, until) in wait_for {
+        while TLB_FLUSHES[cpu].load(Ordering::SeqCst) < until {
+            // two CPUs shooting each other down with interrupts disabled would deadlock otherwise
+            if !crate::int::are_enabled() {
+                flush_tlb();
+            }
+            spin_loop();
+        }
+    }
+}
+
+/// TLB flushes started and finished by a CPU, odd while one is in progress
+pub fn tlb_flushes(cpu: usize) -> u64 {
+    TLB_FLUSHES
+        .get(cpu)
+        .map_or(0, |flushes| flushes.load(Ordering::SeqCst))
+}
+
+fn cpu_bit(cpu: usize) -> u64 {
+    1u64.checked_shl(cpu as u32).unwrap_or(0)
+}
+
 fn v_addr_from_parts(
     offset: usize,
     p1_index: usize,
@@ -310,6 +364,8 @@ pub struct PageMap {
     inner: ManuallyDrop<SafeRwLock<LockedPageMap>>,
     owned: bool,
     info: MemoryInfo,
+    /// the CPUs that have this page map loaded, bit n is CPU n
+    active: AtomicU64,
 }
 
 impl PageMapImpl for PageMap {
@@ -334,6 +390,7 @@ impl PageMapImpl for PageMap {
             inner: ManuallyDrop::new(SafeRwLock::new(LockedPageMap { l4 })),
             info: MemoryInfo::symmetric(1),
             owned: false,
+            active: AtomicU64::new(cpu_bit(cpu_id())),
         }
     }
 
@@ -355,6 +412,7 @@ impl PageMapImpl for PageMap {
             inner: ManuallyDrop::new(SafeRwLock::new(LockedPageMap { l4 })),
             info,
             owned: true,
+            active: AtomicU64::new(0),
         };
 
         // hyperion_log::debug!("higher half direct map");
@@ -475,6 +533,9 @@ impl PageMapImpl for PageMap {
                         let mut l0f = l1e.flags();
                         let target = if l0f.contains(LAZY_ALLOC) {
                             MapTarget::LazyAlloc
+                        } else if l0f.contains(NO_FREE) {
+                            // shared mappings stay shared and writable in the fork
+                            MapTarget::Borrowed(l1e.addr())
                         } else {
                             if l0f.contains(PageTableFlags::WRITABLE) {
                                 // mark writeable pages as read only + CoW
@@ -495,7 +556,10 @@ impl PageMapImpl for PageMap {
             }
         }
 
+        // the pages are read only now, also for the other threads of this process
+        drop(inner);
         MapperFlushAll::new().flush_all();
+        self.shootdown();
 
         new
     }
@@ -521,10 +585,12 @@ impl PageMapImpl for PageMap {
 
     fn unmap(&self, v_addr: Range<VirtAddr>) {
         self.inner.write().unmap(&self.info, v_addr);
+        self.shootdown();
     }
 
     fn remap(&self, v_addr: Range<VirtAddr>, new_flags: PageTableFlags) {
-        self.inner.write().remap(v_addr, new_flags);
+        self.inner.write().remap(&self.info, v_addr, new_flags);
+        self.shootdown();
     }
 
     fn is_mapped(&self, v_addr: Range<VirtAddr>, has_at_least: PageTableFlags) -> bool {
@@ -694,6 +760,33 @@ impl LockedPageMap {
         Some(unsafe { &mut *addr })
     }
 
+    /// split a huge (or giant) page into a table of smaller pages with the same flags,
+    /// so that only a part of it can be unmapped or remapped
+    fn split_huge<S: PageSize>(info: &MemoryInfo, entry: &mut PageTableEntry) {
+        let flags = entry.flags();
+        if !flags.contains(PageTableFlags::HUGE_PAGE) {
+            return;
+        }
+
+        let addr = entry.addr();
+        let child_size = S::SIZE / 512;
+        let child_flags = if child_size == Size4KiB::SIZE {
+            flags.difference(PageTableFlags::HUGE_PAGE)
+        } else {
+            flags
+        };
+
+        entry.set_unused();
+        let table = Self::create_table(info, entry).unwrap();
+        for (i, child) in table.iter_mut().enumerate() {
+            if flags.contains(LAZY_ALLOC) {
+                child.set_flags(child_flags);
+            } else {
+                child.set_addr(addr + i as u64 * child_size, child_flags);
+            }
+        }
+    }
+
     fn try_map_if_diff<S: PageSize>(
         info: &MemoryInfo,
         entry: &mut PageTableEntry,
@@ -907,7 +1000,7 @@ impl LockedPageMap {
         let from = Self::is_map_valid(start..end)?;
 
         let Some(p3) = Self::read_table(&mut self.l4[from.p4_index()])? else {
-            unreachable!("512GiB maps are not supported");
+            return Ok(());
         };
         let p3e = &mut p3[from.p3_index()];
 
@@ -925,8 +1018,9 @@ impl LockedPageMap {
         let from = Self::is_map_valid(start..end)?;
 
         let Some(p3) = Self::read_table(&mut self.l4[from.p4_index()])? else {
-            unreachable!("512GiB maps are not supported");
+            return Ok(());
         };
+        Self::split_huge::<Size1GiB>(info, &mut p3[from.p3_index()]);
         let Some(p2) = Self::read_table(&mut p3[from.p3_index()])? else {
             return Ok(());
         };
@@ -948,9 +1042,11 @@ impl LockedPageMap {
         let Some(p3) = Self::read_table(&mut self.l4[from.p4_index()])? else {
             return Ok(());
         };
+        Self::split_huge::<Size1GiB>(info, &mut p3[from.p3_index()]);
         let Some(p2) = Self::read_table(&mut p3[from.p3_index()])? else {
             return Ok(());
         };
+        Self::split_huge::<Size2MiB>(info, &mut p2[from.p2_index()]);
         let Some(p1) = Self::read_table(&mut p2[from.p2_index()])? else {
             return Ok(());
         };
@@ -962,7 +1058,12 @@ impl LockedPageMap {
         Ok(())
     }
 
-    fn remap(&mut self, Range { mut start, end }: Range<VirtAddr>, new_flags: PageTableFlags) {
+    fn remap(
+        &mut self,
+        info: &MemoryInfo,
+        Range { mut start, end }: Range<VirtAddr>,
+        new_flags: PageTableFlags,
+    ) {
         if !start.is_aligned(Size4KiB::SIZE) || !end.is_aligned(Size4KiB::SIZE) {
             panic!("Not aligned");
         }
@@ -984,12 +1085,12 @@ impl LockedPageMap {
                 continue;
             };
 
-            let Err(err_2mib) = self.try_remap_2mib(start..end, new_flags) else {
+            let Err(err_2mib) = self.try_remap_2mib(info, start..end, new_flags) else {
                 start += Size2MiB::SIZE;
                 continue;
             };
 
-            let Err(err_4kib) = self.try_remap_4kib(start..end, new_flags) else {
+            let Err(err_4kib) = self.try_remap_4kib(info, start..end, new_flags) else {
                 start += Size4KiB::SIZE;
                 continue;
             };
@@ -1011,14 +1112,22 @@ impl LockedPageMap {
             return Err(TryMapError::NotMapped);
         }
 
-        flags.insert(entry.flags().intersection(
+        let old = entry.flags();
+        flags.insert(old.intersection(
             PageTableFlags::PRESENT
                 | PageTableFlags::ACCESSED
                 | PageTableFlags::DIRTY
-                | COW
-                | LAZY_ALLOC,
+                | LAZY_ALLOC
+                | NO_FREE,
         ));
 
+        // CoW pages stay read-only until the first write copies them,
+        // only the CoW bit remembers that they should become writable
+        if old.contains(COW) && flags.contains(PageTableFlags::WRITABLE) {
+            flags.remove(PageTableFlags::WRITABLE);
+            flags.insert(COW);
+        }
+
         if entry.flags() == flags {
             return Ok(());
         }
@@ -1045,7 +1154,7 @@ impl LockedPageMap {
         };
         let p3e = &mut p3[from.p3_index()];
 
-        if !flags.contains(PageTableFlags::HUGE_PAGE) {
+        if !p3e.flags().contains(PageTableFlags::HUGE_PAGE) {
             return Err(TryMapError::WrongSize);
         }
 
@@ -1056,6 +1165,7 @@ impl LockedPageMap {
 
     fn try_remap_2mib(
         &mut self,
+        info: &MemoryInfo,
         Range { start, end }: Range<VirtAddr>,
         flags: PageTableFlags,
     ) -> Result<(), TryMapError<Size2MiB>> {
@@ -1064,12 +1174,13 @@ impl LockedPageMap {
         let Some(p3) = Self::read_table(&mut self.l4[from.p4_index()])? else {
             return Ok(());
         };
+        Self::split_huge::<Size1GiB>(info, &mut p3[from.p3_index()]);
         let Some(p2) = Self::read_table(&mut p3[from.p3_index()])? else {
             return Ok(());
         };
         let p2e = &mut p2[from.p2_index()];
 
-        if !flags.contains(PageTableFlags::HUGE_PAGE) {
+        if !p2e.flags().contains(PageTableFlags::HUGE_PAGE) {
             return Err(TryMapError::WrongSize);
         }
 
@@ -1080,6 +1191,7 @@ impl LockedPageMap {
 
     fn try_remap_4kib(
         &mut self,
+        info: &MemoryInfo,
         Range { start, end }: Range<VirtAddr>,
         flags: PageTableFlags,
     ) -> Result<(), TryMapError<Size4KiB>> {
@@ -1088,9 +1200,11 @@ impl LockedPageMap {
         let Some(p3) = Self::read_table(&mut self.l4[from.p4_index()])? else {
             return Ok(());
         };
+        Self::split_huge::<Size1GiB>(info, &mut p3[from.p3_index()]);
         let Some(p2) = Self::read_table(&mut p3[from.p3_index()])? else {
             return Ok(());
         };
+        Self::split_huge::<Size2MiB>(info, &mut p2[from.p2_index()]);
         let Some(p1) = Self::read_table(&mut p2[from.p2_index()])? else {
             return Ok(());
         };
@@ -1140,7 +1254,12 @@ impl LockedPageMap {
                         return false;
                     }
 
-                    start += Size1GiB::SIZE;
+                    start = start.align_down(Size1GiB::SIZE) + Size1GiB::SIZE;
+                    continue;
+                }
+                None if l3.flags().contains(LAZY_ALLOC | PageTableFlags::HUGE_PAGE) => {
+                    // lazy giant page
+                    start = start.align_down(Size1GiB::SIZE) + Size1GiB::SIZE;
                     continue;
                 }
                 None => return false,
@@ -1157,7 +1276,12 @@ impl LockedPageMap {
                         return false;
                     }
 
-                    start += Size2MiB::SIZE;
+                    start = start.align_down(Size2MiB::SIZE) + Size2MiB::SIZE;
+                    continue;
+                }
+                None if l2.flags().contains(LAZY_ALLOC | PageTableFlags::HUGE_PAGE) => {
+                    // lazy huge page
+                    start = start.align_down(Size2MiB::SIZE) + Size2MiB::SIZE;
                     continue;
                 }
                 None => return false,
@@ -1183,7 +1307,7 @@ impl LockedPageMap {
                 .union(PageTableFlags::WRITABLE)
                 .contains(flags)
         } else {
-            true
+            lf.contains(PageTableFlags::PRESENT | flags)
         }
     }
 
@@ -1244,6 +1368,12 @@ impl LockedPageMap {
         Self::free_table(info, 4, self.l4);
     }
 
+    fn clear_user(&mut self, info: &MemoryInfo) {
+        for entry in self.l4.iter_mut().take(256) {
+            Self::free_entry(info, 4, entry);
+        }
+    }
+
     fn free_table(info: &MemoryInfo, layer: u8, table: &mut PageTable) {
         for entry in table.iter_mut() {
             Self::free_entry(info, layer, entry);
@@ -1316,6 +1446,39 @@ impl PageMap {
         self.inner.read().cr3()
     }
 
+    /// unmap and free everything in the lower half
+    pub fn clear_user(&self) {
+        self.inner.write().clear_user(&self.info);
+        MapperFlushAll::new().flush_all();
+        self.shootdown();
+    }
+
+    /// mark this page map as loaded on `cpu`, so that changes to it are flushed from its TLB
+    pub fn mark_active(&self, cpu: usize) {
+        self.active.fetch_or(cpu_bit(cpu), Ordering::SeqCst);
+    }
+
+    /// mark this page map as no longer loaded on `cpu`
+    pub fn mark_inactive(&self, cpu: usize) {
+        self.active.fetch_and(!cpu_bit(cpu), Ordering::SeqCst);
+    }
+
+    /// the CPUs that have this page map loaded, bit n is CPU n
+    pub fn active_cpus(&self) -> u64 {
+        self.active.load(Ordering::SeqCst)
+    }
+
+    /// flush the TLBs of the other CPUs that have this page map loaded,
+    /// the local TLB is flushed by the modification itself
+    ///
+    /// the page map lock should not be held, a CPU could be waiting for it with interrupts disabled
+    fn shootdown(&self) {
+        let others = self.active_cpus() & !cpu_bit(cpu_id());
+        if others != 0 {
+            tlb_shootdown(others);
+        }
+    }
+
     /* /// # Safety
     /// TODO: not safe
     pub unsafe fn unsafe_page_fault(
@@ -1,24 +1,27 @@
 use alloc::boxed::Box;
 use core::{
+    hint::spin_loop,
     ops::Deref,
-    sync::atomic::{AtomicBool, Ordering},
+    sync::atomic::{AtomicBool, AtomicU32, Ordering},
 };
 
 use crossbeam::atomic::AtomicCell;
 use hyperion_clock::ClockSource;
-use hyperion_cpu_id::Tls;
-use hyperion_interrupts::{end_of_interrupt, IntController, INT_CONTROLLER, INT_EOI_HANDLER};
+use hyperion_cpu_id::{cpu_count, cpu_id, Tls};
+use hyperion_interrupts::{
+    end_of_interrupt, IntController, Interrupted, INT_CONTROLLER, INT_EOI_HANDLER,
+};
 use hyperion_log::trace;
 use hyperion_mem::to_higher_half;
 use spin::{Lazy, Once, RwLock, RwLockReadGuard, RwLockWriteGuard};
-use x86_64::PhysAddr;
+use x86_64::{instructions::interrupts::without_interrupts, PhysAddr};
 
 use super::{madt::MADT, ReadOnly, ReadWrite, Reserved, WriteOnly};
 use crate::hpet::HPET;
 
 //
 
-pub static APIC_TIMER_HANDLER: AtomicCell<fn()> = AtomicCell::new(|| {});
+pub static APIC_TIMER_HANDLER: AtomicCell<fn(Interrupted)> = AtomicCell::new(|_| {});
 
 pub const IRQ_APIC_SPURIOUS: u8 = 0xFF;
 // APIC timer interval is 10ms
@@ -26,6 +29,93 @@ pub const APIC_PERIOD_MULT: u32 = 10;
 
 //
 
+/// inter-processor interrupts, each kind has its own interrupt vector shared by all CPUs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ipi {
+    /// wake up a CPU waiting for tasks
+    Wakeup,
+    /// the process running on the CPU was killed
+    Kill,
+    /// flush the TLB of the CPU
+    TlbShootdown,
+}
+
+/// the CPUs an [`Ipi`] is sent to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpiTarget {
+    /// one CPU by its CPU id
+    Cpu(usize),
+    /// every CPU in the mask, bit n is CPU n
+    Cpus(u64),
+    /// one CPU by its APIC id
+    Apic(ApicId),
+    /// this CPU
+    Current,
+    /// every CPU including this one
+    All,
+    /// every CPU except this one
+    Others,
+}
+
+impl Ipi {
+    pub const ALL: [Self; 3] = [Self::Wakeup, Self::Kill, Self::TlbShootdown];
+
+    /// the handler that runs on the receiving CPU, after the end of interrupt
+    pub fn handler(self) -> &'static AtomicCell<fn(Interrupted)> {
+        static HANDLERS: [AtomicCell<fn(Interrupted)>; 3] = [
+            AtomicCell::new(|_| {}),
+            AtomicCell::new(|_| {}),
+            AtomicCell::new(|_| {}),
+        ];
+
+        &HANDLERS[self as usize]
+    }
+}
+
+/// send an inter-processor interrupt,
+/// does nothing before the first APIC is enabled or to CPUs without an enabled APIC
+pub fn send_ipi(target: IpiTarget, ipi: Ipi) {
+    let Some(vectors) = IPI_VECTORS.get() else {
+        return;
+    };
+    let cmd = vectors[ipi as usize] as u32 | ICR_ASSERT;
+
+    match target {
+        IpiTarget::Cpu(cpu) => {
+            if let Some(id) = ApicId::of_cpu(cpu) {
+                send_ipi_cmd(id, cmd);
+            }
+        }
+        IpiTarget::Cpus(mask) => (0..u64::BITS as usize)
+            .filter(|cpu| mask & (1 << cpu) != 0)
+            .filter_map(ApicId::of_cpu)
+            .for_each(|id| send_ipi_cmd(id, cmd)),
+        IpiTarget::Apic(id) => send_ipi_cmd(id, cmd),
+        IpiTarget::Current => send_ipi_cmd(ApicId(0), cmd | ICR_DEST_SELF),
+        IpiTarget::All => send_ipi_cmd(ApicId(0), cmd | ICR_DEST_ALL),
+        IpiTarget::Others => send_ipi_cmd(ApicId(0), cmd | ICR_DEST_OTHERS),
+    }
+}
+
+fn send_ipi_cmd(dest: ApicId, cmd: u32) {
+    // the end of interrupt handler locks the LAPIC too
+    without_interrupts(|| Lapic::current_mut().send_ipi(dest, cmd));
+}
+
+fn ipi_handler(irq: u8, from: Interrupted) {
+    let ipi = IPI_VECTORS
+        .get()
+        .and_then(|vectors| vectors.iter().position(|vector| *vector == irq));
+
+    end_of_interrupt(irq);
+
+    if let Some(ipi) = ipi {
+        Ipi::ALL[ipi].handler().load()(from);
+    }
+}
+
+//
+
 pub struct ApicTls<T: 'static> {
     inner: Box<[(ApicId, T)]>,
 }
@@ -69,6 +159,17 @@ pub fn enable() {
     });
     INT_CONTROLLER.store(IntController::Apic);
 
+    // the IPI vectors are the same on every CPU
+    IPI_VECTORS.call_once(|| {
+        Ipi::ALL.map(|_| {
+            hyperion_interrupts::set_any_interrupt_handler(
+                |irq| (0x30..=0xFF).contains(&irq),
+                ipi_handler,
+            )
+            .expect("No avail IPI IRQ")
+        })
+    });
+
     // enable apic only once per cpu
     static ONCE: Lazy<Tls<AtomicBool>> = Lazy::new(|| Tls::new(|| AtomicBool::new(false)));
     if ONCE.swap(true, Ordering::SeqCst) {
@@ -78,6 +179,7 @@ pub fn enable() {
     // SAFETY: the check above
     let regs: &mut ApicRegs = unsafe { get_apic_regs() };
     let apic_id = ApicId(regs.lapic_id.read());
+    CPU_APIC_IDS[cpu_id()].store(apic_id.0, Ordering::SeqCst);
 
     trace!("Initializing {apic_id:?}");
     let mut lapic = LAPICS.call_once(|| RwLock::new(Lapic { regs })).write();
@@ -95,7 +197,7 @@ pub fn enable() {
 pub fn enable_timer(mut lapic: RwLockWriteGuard<Lapic>) {
     let timer_irq = hyperion_interrupts::set_any_interrupt_handler(
         |irq| (0x30..=0xFF).contains(&irq),
-        |irq, _| {
+        |irq, from| {
             // hyperion_log::println!("AT@{ip:#018x}");
 
             /* unsafe {
@@ -112,7 +214,7 @@ pub fn enable_timer(mut lapic: RwLockWriteGuard<Lapic>) {
             } */
 
             end_of_interrupt(irq);
-            APIC_TIMER_HANDLER.load()();
+            APIC_TIMER_HANDLER.load()(from);
 
             // apic timer interrupt
         },
@@ -178,6 +280,12 @@ impl ApicId {
     pub fn current() -> Self {
         Self(Lapic::current().regs.lapic_id.read())
     }
+
+    /// apic id of the processor with this cpu id, if it has enabled its APIC
+    pub fn of_cpu(cpu: usize) -> Option<Self> {
+        let id = CPU_APIC_IDS.get(cpu)?.load(Ordering::SeqCst);
+        (id != NO_APIC_ID).then_some(Self(id))
+    }
 }
 
 impl Lapic {
@@ -200,6 +308,16 @@ impl Lapic {
     pub fn eoi(&mut self) {
         self.regs.eoi.write(0);
     }
+
+    /// send an inter-processor interrupt and wait for it to be delivered
+    pub fn send_ipi(&mut self, dest: ApicId, cmd: u32) {
+        self.regs.interrupt_cmd_high.write(dest.0 << 24);
+        self.regs.interrupt_cmd_low.write(cmd);
+
+        while self.regs.interrupt_cmd_low.read() & ICR_DELIVERY_PENDING != 0 {
+            spin_loop();
+        }
+    }
 }
 
 //
@@ -207,6 +325,15 @@ impl Lapic {
 static LAPICS: Lazy<Tls<Once<RwLock<Lapic>>>> = Lazy::new(|| Tls::new(Once::new));
 static LAPIC_IDS: Lazy<&'static [ApicId]> =
     Lazy::new(|| Box::leak(hyperion_boot::lapics().map(ApicId).collect::<Box<_>>()));
+// APIC ids indexed by the cpu id, recorded when each CPU enables its APIC
+static CPU_APIC_IDS: Lazy<Box<[AtomicU32]>> = Lazy::new(|| {
+    (0..cpu_count())
+        .map(|_| AtomicU32::new(NO_APIC_ID))
+        .collect()
+});
+static IPI_VECTORS: Once<[u8; 3]> = Once::new();
+
+const NO_APIC_ID: u32 = u32::MAX;
 
 const IA32_APIC_BASE: u32 = 0x1B;
 // const IA32_TSC_AUX: u32 = 0xC0000103; // lapic id storage - same as in Theseus
@@ -219,6 +346,12 @@ const APIC_DISABLE: u32 = 1 << 16;
 
 const APIC_NMI: u32 = 4 << 8;
 
+const ICR_DELIVERY_PENDING: u32 = 1 << 12;
+const ICR_ASSERT: u32 = 1 << 14;
+const ICR_DEST_SELF: u32 = 0b01 << 18;
+const ICR_DEST_ALL: u32 = 0b10 << 18;
+const ICR_DEST_OTHERS: u32 = 0b11 << 18;
+
 const _APIC_TIMER_MODE_ONESHOT: u32 = 0b00 << 17;
 const APIC_TIMER_MODE_PERIODIC: u32 = 0b01 << 17;
 const _APIC_TIMER_MODE_TSC_DEADLINE: u32 = 0b10 << 17;
@@ -334,9 +467,10 @@ pub struct ApicRegs {
     pub interrupt_request: ReadOnly<[u32; 8]>,
     pub error_status: ReadOnly,
     _pad2: Skip<6>,
-    pub lvt_corrected_machine_check_interrupt: ReadWrite,
-    pub interrupt_cmd: ReadWrite<[u32; 2]>, */
-    _pad2: Skip<34>,
+    pub lvt_corrected_machine_check_interrupt: ReadWrite, */
+    _pad2: Skip<32>,
+    pub interrupt_cmd_low: ReadWrite,
+    pub interrupt_cmd_high: ReadWrite,
     pub lvt_timer: ReadWrite,
     pub lvt_thermal_sensor: ReadWrite,
     pub lvt_perf_mon_counters: ReadWrite,
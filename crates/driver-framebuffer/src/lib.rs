@@ -50,9 +50,10 @@ impl FileDevice for FboDevice {
         &mut self,
         vmm: &PageMap,
         v_addr: Range<VirtAddr>,
+        offset: usize,
         flags: PageTableFlags,
     ) -> Result<usize> {
-        if !v_addr.start.is_aligned(0x1000u64) {
+        if !v_addr.start.is_aligned(0x1000u64) || offset & 0xfff != 0 {
             // FIXME: use the real abi error
             return Err(Error::PERMISSION_DENIED);
         }
@@ -75,12 +76,16 @@ impl FileDevice for FboDevice {
             (fbo, unsafe { PageFrame::new(start, size >> 12) })
         });
 
-        let end = v_addr.end.min(v_addr.start + frame.byte_len());
-        vmm.map(
-            v_addr.start..end,
-            MapTarget::Borrowed(frame.physical_addr()),
-            flags,
-        );
+        let end = v_addr
+            .end
+            .min(v_addr.start + frame.byte_len().saturating_sub(offset) as u64);
+        if end > v_addr.start {
+            vmm.map(
+                v_addr.start..end,
+                MapTarget::Borrowed(frame.physical_addr() + offset as u64),
+                flags,
+            );
+        }
 
         hyperion_log::debug!("FBO mapped");
 
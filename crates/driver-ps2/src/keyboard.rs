@@ -14,9 +14,9 @@ pub fn init() {
         if let Some(mut io_apic) = IoApic::any() {
             let irq = hyperion_interrupts::set_any_interrupt_handler(
                 |irq| irq >= 0x20,
-                |irq, ip| {
+                |irq, from| {
                     let ps2_byte: u8 = unsafe { Port::new(0x60).read() };
-                    hyperion_events::keyboard::buffer::send_raw(ps2_byte, ip);
+                    hyperion_events::keyboard::buffer::send_raw(ps2_byte, from.ip);
                     // hyperion_log::println!("K@{ip:#018x}");
                     end_of_interrupt(irq);
                 },
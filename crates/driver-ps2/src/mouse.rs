@@ -14,10 +14,10 @@ pub fn init() {
         if let Some(mut io_apic) = IoApic::any() {
             let irq = hyperion_interrupts::set_any_interrupt_handler(
                 |irq| irq >= 0x20,
-                |irq, ip| {
+                |irq, from| {
                     let ps2_byte: u8 = unsafe { Port::new(0x60).read() };
 
-                    hyperion_events::mouse::buffer::send_raw(ps2_byte, ip);
+                    hyperion_events::mouse::buffer::send_raw(ps2_byte, from.ip);
 
                     end_of_interrupt(irq);
                 },
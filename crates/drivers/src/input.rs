@@ -1,8 +1,9 @@
 use core::any::Any;
 
-use hyperion_events::{keyboard, mouse};
-use hyperion_futures::block_on;
-use hyperion_vfs::{device::FileDevice, Result};
+use hyperion_events::{keyboard, mouse, readiness::Listeners};
+use hyperion_futures::block_on_killable;
+use hyperion_syscall::fs::PollEvents;
+use hyperion_vfs::{device::FileDevice, Error, Result};
 
 //
 
@@ -17,16 +18,39 @@ impl FileDevice for KeyboardDevice {
         0
     }
 
+    fn poll(&self) -> PollEvents {
+        if keyboard::buffer::is_raw_empty() {
+            PollEvents::empty()
+        } else {
+            PollEvents::IN
+        }
+    }
+
+    fn listen(&self, listeners: &mut Listeners) {
+        keyboard::buffer::listen_raw(listeners);
+    }
+
     fn read(&self, _: usize, buf: &mut [u8]) -> Result<usize> {
         if buf.is_empty() {
             return Ok(0);
         }
 
-        let s = block_on(async move { keyboard::buffer::recv_raw().await });
+        let s = block_on_killable(async move { keyboard::buffer::recv_raw().await })
+            .map_err(|_| Error::INTERRUPTED)?;
         buf[0] = s;
 
         Ok(1)
     }
+
+    fn try_read(&self, _: usize, buf: &mut [u8]) -> Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        buf[0] = keyboard::buffer::try_recv_raw().ok_or(Error::WOULD_BLOCK)?;
+
+        Ok(1)
+    }
 }
 
 //
@@ -42,12 +66,37 @@ impl FileDevice for MouseDevice {
         0
     }
 
+    fn poll(&self) -> PollEvents {
+        if mouse::buffer::is_raw_empty() {
+            PollEvents::empty()
+        } else {
+            PollEvents::IN
+        }
+    }
+
+    fn listen(&self, listeners: &mut Listeners) {
+        mouse::buffer::listen_raw(listeners);
+    }
+
     fn read(&self, _: usize, buf: &mut [u8]) -> Result<usize> {
         if buf.is_empty() {
             return Ok(0);
         }
 
-        let s = block_on(async move { mouse::buffer::recv_raw().await });
+        let s = block_on_killable(async move { mouse::buffer::recv_raw().await })
+            .map_err(|_| Error::INTERRUPTED)?;
+        let limit = buf.len().min(3);
+        buf[..limit].copy_from_slice(&s[..limit]);
+
+        Ok(limit)
+    }
+
+    fn try_read(&self, _: usize, buf: &mut [u8]) -> Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let s = mouse::buffer::try_recv_raw().ok_or(Error::WOULD_BLOCK)?;
         let limit = buf.len().min(3);
         buf[..limit].copy_from_slice(&s[..limit]);
 
@@ -10,6 +10,7 @@ mod mpmc;
 
 pub mod keyboard;
 pub mod mouse;
+pub mod readiness;
 pub mod timer;
 
 //
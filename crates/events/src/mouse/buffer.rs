@@ -1,5 +1,8 @@
 use super::{decode, event::MouseEvent};
-use crate::mpmc::{EventQueue, Recv};
+use crate::{
+    mpmc::{EventQueue, Recv},
+    readiness::Listeners,
+};
 
 //
 
@@ -25,6 +28,16 @@ pub fn try_recv_raw() -> Option<[u8; 3]> {
     RAW_BUF.try_recv()
 }
 
+/// no raw events are waiting, `recv_raw` would block
+pub fn is_raw_empty() -> bool {
+    RAW_BUF.is_empty()
+}
+
+/// start listening for new raw events, see [`Readiness::listen`](crate::readiness::Readiness::listen)
+pub fn listen_raw(listeners: &mut Listeners) {
+    RAW_BUF.readiness.listen(listeners);
+}
+
 pub fn recv_raw() -> Recv<'static, [u8; 3]> {
     RAW_BUF.recv()
 }
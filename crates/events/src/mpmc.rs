@@ -2,6 +2,7 @@ use core::{
     fmt::Debug,
     future::Future,
     pin::Pin,
+    sync::atomic::{AtomicUsize, Ordering},
     task::{Context, Poll},
 };
 
@@ -10,28 +11,44 @@ use futures_util::ready;
 use heapless::mpmc::MpMcQueue;
 use pin_project::pin_project;
 
+use crate::readiness::Readiness;
+
 //
 
 pub(crate) struct EventQueue<T> {
     queue: MpMcQueue<T, 128>,
+    len: AtomicUsize,
     ops: Event,
+    pub readiness: Readiness,
 }
 
 impl<T: Debug> EventQueue<T> {
     pub const fn new() -> Self {
         Self {
             queue: MpMcQueue::new(),
+            len: AtomicUsize::new(0),
             ops: Event::new(),
+            readiness: Readiness::new(),
         }
     }
 
     pub fn send(&self, event: T) {
-        _ = self.queue.enqueue(event);
+        if self.queue.enqueue(event).is_ok() {
+            self.len.fetch_add(1, Ordering::Release);
+        }
         self.ops.notify(1);
+        self.readiness.notify();
     }
 
     pub fn try_recv(&self) -> Option<T> {
-        self.queue.dequeue()
+        let event = self.queue.dequeue()?;
+        self.len.fetch_sub(1, Ordering::Release);
+        Some(event)
+    }
+
+    /// approximate, but `try_recv` is going to succeed soon if this is false
+    pub fn is_empty(&self) -> bool {
+        self.len.load(Ordering::Acquire) == 0
     }
 
     pub const fn recv(&self) -> Recv<T> {
@@ -0,0 +1,60 @@
+use alloc::{boxed::Box, vec::Vec};
+use core::pin::Pin;
+
+use event_listener::{Event, EventListener};
+use futures_util::future::{select, select_all};
+use hyperion_instant::Instant;
+
+use crate::timer::sleep_until;
+
+//
+
+/// listeners of the files that `poll` is waiting for
+pub type Listeners = Vec<Pin<Box<EventListener>>>;
+
+/// readiness changes of a single file, like one end of a pipe
+///
+/// only the pollers of that file are woken up
+pub struct Readiness {
+    event: Event,
+}
+
+impl Readiness {
+    pub const fn new() -> Self {
+        Self {
+            event: Event::new(),
+        }
+    }
+
+    /// something might have become readable, writable or closed
+    pub fn notify(&self) {
+        self.event.notify(usize::MAX);
+    }
+
+    /// start listening for readiness changes,
+    /// has to be called before checking the readiness to not miss any changes
+    pub fn listen(&self, listeners: &mut Listeners) {
+        listeners.push(self.event.listen());
+    }
+}
+
+impl Default for Readiness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// wait for a readiness change of any listened file or for the deadline
+pub async fn changed(listeners: Listeners, deadline: Option<Instant>) {
+    match (listeners.is_empty(), deadline) {
+        // nothing can change
+        (true, None) => core::future::pending().await,
+        (true, Some(deadline)) => sleep_until(deadline).await,
+        (false, None) => {
+            select_all(listeners).await;
+        }
+        (false, Some(deadline)) => {
+            select(select_all(listeners), sleep_until(deadline)).await;
+        }
+    }
+}
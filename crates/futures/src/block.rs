@@ -9,7 +9,7 @@ use futures_util::{
     pin_mut,
     task::{waker, ArcWake},
 };
-use hyperion_scheduler::futex;
+use hyperion_scheduler::futex::{self, Killed, WaitError};
 
 use crate::executor::run_once;
 
@@ -17,6 +17,19 @@ use crate::executor::run_once;
 
 // run a task to completion
 pub fn block_on<F: IntoFuture>(f: F) -> F::Output {
+    match block_on_with(f, false) {
+        Ok(res) => res,
+        Err(Killed) => unreachable!("only killable waits end with Killed"),
+    }
+}
+
+/// like [`block_on`], but gives up if the thread gets killed,
+/// so that it can return from the syscall and exit
+pub fn block_on_killable<F: IntoFuture>(f: F) -> Result<F::Output, Killed> {
+    block_on_with(f, true)
+}
+
+fn block_on_with<F: IntoFuture>(f: F, killable: bool) -> Result<F::Output, Killed> {
     let fut = f.into_future();
     pin_mut!(fut);
 
@@ -32,7 +45,7 @@ pub fn block_on<F: IntoFuture>(f: F) -> F::Output {
     loop {
         debug_assert_eq!(wake.wake.load(Ordering::SeqCst), 0);
         if let Poll::Ready(res) = fut.as_mut().poll(&mut cx) {
-            return res;
+            return Ok(res);
         }
 
         // run other tasks while this task is waiting
@@ -47,7 +60,11 @@ pub fn block_on<F: IntoFuture>(f: F) -> F::Output {
                 break;
             }
 
-            futex::wait(&wake.wake, 0);
+            if !killable {
+                futex::wait(&wake.wake, 0);
+            } else if futex::wait_killable(&wake.wake, 0, None) == Err(WaitError::Killed) {
+                return Err(Killed);
+            }
 
             if wake
                 .wake
@@ -18,5 +18,5 @@ mod task;
 
 //
 
-pub use block::block_on;
+pub use block::{block_on, block_on_killable};
 pub use executor::{run_tasks, spawn};
@@ -18,7 +18,10 @@ pub static INT_HANDLERS: [IntHandler; INT_COUNT] = array![IntHandler::new(); 224
 
 //
 
-pub fn set_any_interrupt_handler(can_use: impl Fn(u8) -> bool, f: fn(u8, usize)) -> Option<u8> {
+pub fn set_any_interrupt_handler(
+    can_use: impl Fn(u8) -> bool,
+    f: fn(u8, Interrupted),
+) -> Option<u8> {
     for irq in 0x20u8..=0xFF {
         if !can_use(irq) {
             continue;
@@ -32,11 +35,11 @@ pub fn set_any_interrupt_handler(can_use: impl Fn(u8) -> bool, f: fn(u8, usize))
     None
 }
 
-pub fn set_interrupt_handler_if_free(irq: u8, f: fn(u8, usize)) -> bool {
+pub fn set_interrupt_handler_if_free(irq: u8, f: fn(u8, Interrupted)) -> bool {
     handler(irq).store_if_free(f)
 }
 
-pub fn set_interrupt_handler(irq: u8, f: fn(u8, usize)) {
+pub fn set_interrupt_handler(irq: u8, f: fn(u8, Interrupted)) {
     handler(irq).store(f);
 }
 
@@ -45,9 +48,9 @@ pub fn handler(irq: u8) -> &'static IntHandler {
     &INT_HANDLERS[irq as usize - 0x20]
 }
 
-pub fn interrupt_handler(irq: u8, ip: usize) {
+pub fn interrupt_handler(irq: u8, from: Interrupted) {
     // debug!("interrupt {irq}");
-    INT_HANDLERS[irq as usize - 0x20].load()(irq, ip);
+    INT_HANDLERS[irq as usize - 0x20].load()(irq, from);
     // end_of_interrupt(irq);
 }
 
@@ -62,12 +65,21 @@ pub fn end_of_interrupt(irq: u8) {
     } */
 }
 
-pub fn default_handler(irq: u8, _ip: usize) {
+pub fn default_handler(irq: u8, _: Interrupted) {
     end_of_interrupt(irq);
 }
 
 //
 
+/// the code that got interrupted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interrupted {
+    /// instruction pointer of the interrupted code
+    pub ip: usize,
+    /// the interrupted code was running in ring 3
+    pub user: bool,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 #[repr(u8)]
 pub enum IntController {
@@ -83,7 +95,7 @@ pub enum IntController {
 
 pub struct IntHandler {
     free: AtomicBool,
-    f: AtomicCell<fn(u8, usize)>,
+    f: AtomicCell<fn(u8, Interrupted)>,
 }
 
 //
@@ -96,7 +108,7 @@ impl IntHandler {
         }
     }
 
-    pub fn store_if_free(&self, new: fn(u8, usize)) -> bool {
+    pub fn store_if_free(&self, new: fn(u8, Interrupted)) -> bool {
         let stored = self.free.swap(false, Ordering::SeqCst);
         if stored {
             self.f.store(new);
@@ -104,12 +116,12 @@ impl IntHandler {
         stored
     }
 
-    pub fn store(&self, new: fn(u8, usize)) {
+    pub fn store(&self, new: fn(u8, Interrupted)) {
         self.free.store(false, Ordering::SeqCst);
         self.f.store(new);
     }
 
-    pub fn load(&self) -> fn(u8, usize) {
+    pub fn load(&self) -> fn(u8, Interrupted) {
         self.f.load()
     }
 }
@@ -4,33 +4,43 @@
 
 extern crate alloc;
 
-use alloc::{boxed::Box, string::String, sync::Arc, vec::Vec};
+use alloc::{
+    boxed::Box,
+    collections::{BTreeSet, VecDeque},
+    format,
+    string::String,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
 use core::{
     any::Any,
+    convert::Infallible,
     mem,
-    ops::Range,
-    slice,
     sync::atomic::{AtomicUsize, Ordering},
 };
 
 use arcstr::ArcStr;
-use hyperion_loader::Loader;
+use hyperion_events::readiness::Listeners;
+use hyperion_loader::{EntryPoint, Loader};
 use hyperion_log::*;
 use hyperion_mem::vmm::PageMapImpl;
 use hyperion_scheduler::{
-    ipc::pipe::{pipe_with, Channel, Receiver, Sender},
+    exit,
+    ipc::pipe::{pipe_with, Channel, Closed, Receiver, Sender},
     lock::{Futex, Mutex},
     proc::{Pid, Process, ProcessExt},
-    process,
+    process, ExitCode,
 };
 use hyperion_syscall::{
     err::{Error, Result},
-    fs::{FileDesc, Seek},
+    fs::{DirEntryRecord, FileDesc, FileKind, FileOpenFlags, Metadata, PollEvents, Seek},
+    limit::Resource,
     net::{Protocol, SocketDomain, SocketType},
 };
 use hyperion_vfs::{
-    device::FileDevice,
-    tree::{FileRef, Node},
+    device::{FileDevice, Permissions},
+    path::{Path, PathBuf},
+    tree::{DirRef, FileRef, Node},
 };
 use spin::{Lazy, Once};
 use x86_64::{structures::paging::PageTableFlags, VirtAddr};
@@ -38,9 +48,28 @@ use x86_64::{structures::paging::PageTableFlags, VirtAddr};
 //
 
 // mod initfs;
+mod limit;
+mod mmap;
+mod perm;
 mod procfs;
+mod sched;
+mod session;
+mod signal;
 // mod sysfs;
 
+pub use limit::{getrlimit, setrlimit};
+pub use mmap::{mmap, mmap_clear, mprotect, munmap, palloc, pfree, Mappings};
+pub use perm::{
+    check_access, check_create, check_exec, check_node, check_remove, chmod, chown, getgid, getuid,
+    setgid, setuid, Access,
+};
+pub use sched::{get_priority, sched_getaffinity, sched_setaffinity, set_priority};
+pub use session::{getpgid, setpgid, setsid, tcgetpgrp, tcsetpgrp};
+pub use signal::{
+    deliver, inherit_signals, send, send_group, sigaction, sigpending, sigprocmask, sigreturn,
+    wait_child, Signals,
+};
+
 //
 
 pub static VFS_ROOT: Lazy<Node> = Lazy::new(|| {
@@ -73,23 +102,24 @@ impl<T> SparseVec<T> {
     }
 
     pub fn push(&mut self, v: T) -> usize {
-        let index;
-        if let Some((_index, spot)) = self
-            .inner
-            .iter_mut()
-            .enumerate()
-            .find(|(_, spot)| spot.is_none())
-        {
-            index = _index;
+        let index = self.next_free();
+        if let Some(spot) = self.inner.get_mut(index) {
             *spot = Some(v);
         } else {
-            index = self.inner.len();
             self.inner.push(Some(v));
         }
 
         index
     }
 
+    /// the index that the next [`Self::push`] uses
+    pub fn next_free(&self) -> usize {
+        self.inner
+            .iter()
+            .position(Option::is_none)
+            .unwrap_or(self.inner.len())
+    }
+
     pub fn remove(&mut self, index: usize) -> Option<T> {
         self.inner.get_mut(index).and_then(Option::take)
     }
@@ -129,6 +159,24 @@ pub trait FileDescriptor: Send + Sync {
         Err(Error::INVALID_ARGUMENT)
     }
 
+    /// node kind, mode, timestamps and the driver name
+    ///
+    /// the read/write position is not included
+    fn metadata(&self) -> Result<Metadata> {
+        Err(Error::INVALID_ARGUMENT)
+    }
+
+    /// the operations that wouldn't block right now, for `poll`
+    fn poll(&self) -> PollEvents {
+        PollEvents::IN | PollEvents::OUT
+    }
+
+    /// start listening for changes of [`Self::poll`], before checking it
+    ///
+    /// files that never block don't need to listen to anything
+    #[allow(unused_variables)]
+    fn listen(&self, listeners: &mut Listeners) {}
+
     // /// get the current read/write position
     // fn tell(&self) -> Result<usize> {
     //     Err(Error::INVALID_ARGUMENT)
@@ -151,6 +199,20 @@ pub trait FileDescriptor: Send + Sync {
     fn write(&self, buf: &[u8]) -> Result<usize> {
         Err(Error::INVALID_ARGUMENT)
     }
+
+    /// [`Self::read`] but [`Error::WOULD_BLOCK`] instead of blocking
+    ///
+    /// files that never block can keep the default
+    fn try_read(&self, buf: &mut [u8]) -> Result<usize> {
+        self.read(buf)
+    }
+
+    /// [`Self::write`] but [`Error::WOULD_BLOCK`] instead of blocking
+    ///
+    /// files that never block can keep the default
+    fn try_write(&self, buf: &[u8]) -> Result<usize> {
+        self.write(buf)
+    }
 }
 
 /// file descriptor backend that points to an opened VFS file
@@ -160,8 +222,6 @@ pub struct FileDescData {
 
     /// the current read/write offset
     pub position: AtomicUsize,
-
-    pub mapped: Mutex<Option<Range<VirtAddr>>>,
 }
 
 impl FileDescData {
@@ -169,7 +229,6 @@ impl FileDescData {
         Self {
             file_ref,
             position: AtomicUsize::new(position),
-            mapped: Mutex::new(None),
         }
     }
 
@@ -184,7 +243,6 @@ impl Clone for FileDescData {
         Self {
             file_ref: self.file_ref.clone(),
             position,
-            mapped: Mutex::new(None), // FIXME: this is wrong, unmap shouldnt care about the fd
         }
     }
 }
@@ -208,6 +266,18 @@ impl FileDescriptor for FileDescData {
         self.file_ref.lock().set_len(len)
     }
 
+    fn metadata(&self) -> Result<Metadata> {
+        Ok(self.file_ref.lock().metadata())
+    }
+
+    fn poll(&self) -> PollEvents {
+        self.file_ref.lock().poll()
+    }
+
+    fn listen(&self, listeners: &mut Listeners) {
+        self.file_ref.lock().listen(listeners);
+    }
+
     fn seek(&self, offset: isize, origin: Seek) -> Result<usize> {
         let pos = match origin {
             Seek::SET => {
@@ -256,6 +326,14 @@ impl FileDescriptor for FileDescData {
         Ok(bytes)
     }
 
+    fn try_read(&self, buf: &mut [u8]) -> Result<usize> {
+        let lock = self.file_ref.lock();
+        let bytes = lock.try_read(self.position.load(Ordering::SeqCst), buf)?;
+        self.position.fetch_add(bytes, Ordering::SeqCst);
+        drop(lock);
+        Ok(bytes)
+    }
+
     fn write(&self, buf: &[u8]) -> Result<usize> {
         let mut lock = self.file_ref.lock();
         let bytes = lock.write(self.position.load(Ordering::SeqCst), buf)?;
@@ -265,6 +343,72 @@ impl FileDescriptor for FileDescData {
     }
 }
 
+/// file descriptor backend that points to an opened VFS directory
+pub struct DirDescData {
+    /// VFS node
+    pub dir_ref: DirRef,
+
+    /// index of the next entry given to `read_dir`
+    pub entry: AtomicUsize,
+
+    /// the legacy `<name> <size> <mode>` text listing for plain reads
+    pub listing: FileDescData,
+}
+
+impl DirDescData {
+    /// fill `buf` with [`DirEntryRecord`]s, starting from the current entry
+    ///
+    /// returns the number of bytes written
+    pub fn read_dir(&self, buf: &mut [u8]) -> Result<usize> {
+        let mut dir = self.dir_ref.lock();
+        let skip = self.entry.load(Ordering::SeqCst);
+
+        let mut written = 0;
+        for entry in dir.nodes()?.skip(skip) {
+            let record = DirEntryRecord {
+                id: entry.node.id(),
+                kind: entry.node.kind(),
+                name: &entry.name,
+            };
+
+            let Some(n) = record.encode(&mut buf[written..]) else {
+                if written == 0 {
+                    // the buffer can't hold even a single entry
+                    return Err(Error::INVALID_ARGUMENT);
+                }
+                break;
+            };
+
+            written += n;
+            self.entry.fetch_add(1, Ordering::SeqCst);
+        }
+
+        Ok(written)
+    }
+}
+
+impl FileDescriptor for DirDescData {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn len(&self) -> Result<usize> {
+        self.listing.len()
+    }
+
+    fn metadata(&self) -> Result<Metadata> {
+        Ok(self.dir_ref.lock().metadata())
+    }
+
+    fn seek(&self, offset: isize, origin: Seek) -> Result<usize> {
+        self.listing.seek(offset, origin)
+    }
+
+    fn read(&self, buf: &mut [u8]) -> Result<usize> {
+        self.listing.read(buf)
+    }
+}
+
 impl FileDescriptor for Sender<u8> {
     fn as_any(&self) -> &dyn Any {
         self
@@ -278,6 +422,18 @@ impl FileDescriptor for Sender<u8> {
         Err(Error::IS_A_PIPE)
     }
 
+    fn metadata(&self) -> Result<Metadata> {
+        Ok(pipe_metadata(FileKind::PIPE, "pipe"))
+    }
+
+    fn poll(&self) -> PollEvents {
+        pipe_poll(false, self.is_ready(), self.is_closed())
+    }
+
+    fn listen(&self, listeners: &mut Listeners) {
+        Sender::listen(self, listeners);
+    }
+
     fn seek(&self, _: isize, _: Seek) -> Result<usize> {
         Err(Error::IS_A_PIPE)
     }
@@ -297,6 +453,10 @@ impl FileDescriptor for Sender<u8> {
             Ok(0)
         }
     }
+
+    fn try_write(&self, buf: &[u8]) -> Result<usize> {
+        try_pipe_op(buf.len(), self.try_send_slice(buf))
+    }
 }
 
 impl FileDescriptor for Receiver<u8> {
@@ -312,6 +472,18 @@ impl FileDescriptor for Receiver<u8> {
         Err(Error::IS_A_PIPE)
     }
 
+    fn metadata(&self) -> Result<Metadata> {
+        Ok(pipe_metadata(FileKind::PIPE, "pipe"))
+    }
+
+    fn poll(&self) -> PollEvents {
+        pipe_poll(self.is_ready(), false, self.is_closed())
+    }
+
+    fn listen(&self, listeners: &mut Listeners) {
+        Receiver::listen(self, listeners);
+    }
+
     fn seek(&self, _: isize, _: Seek) -> Result<usize> {
         Err(Error::IS_A_PIPE)
     }
@@ -324,6 +496,10 @@ impl FileDescriptor for Receiver<u8> {
         }
     }
 
+    fn try_read(&self, buf: &mut [u8]) -> Result<usize> {
+        try_pipe_op(buf.len(), self.try_recv_slice(buf))
+    }
+
     fn write(&self, buf: &[u8]) -> Result<usize> {
         if self.weak_send_slice(buf).is_ok() {
             Ok(buf.len())
@@ -333,6 +509,31 @@ impl FileDescriptor for Receiver<u8> {
     }
 }
 
+/// closed pipes read and write 0 bytes, like the blocking versions
+fn try_pipe_op(len: usize, result: core::result::Result<usize, Closed>) -> Result<usize> {
+    match result {
+        Ok(0) if len != 0 => Err(Error::WOULD_BLOCK),
+        Ok(n) => Ok(n),
+        Err(Closed) => Ok(0),
+    }
+}
+
+fn pipe_poll(readable: bool, writable: bool, closed: bool) -> PollEvents {
+    let mut events = PollEvents::empty();
+    events.set(PollEvents::IN, readable);
+    events.set(PollEvents::OUT, writable);
+    events.set(PollEvents::HUP, closed);
+    events
+}
+
+fn pipe_metadata(kind: FileKind, driver: &str) -> Metadata {
+    let mut meta = Metadata::zeroed();
+    meta.kind = kind;
+    meta.mode = 0o600;
+    meta.set_driver(driver);
+    meta
+}
+
 /// general socket backend info
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct SocketInfo {
@@ -341,15 +542,27 @@ pub struct SocketInfo {
     pub proto: Protocol,
 }
 
-/// file descriptor backend that points to a local domain socket listener
+/// file descriptor backend that points to a local domain socket
 pub struct LocalSocket {
     pub info: SocketInfo,
     pub inner: Once<LocalSocketType>,
+    /// the path this socket is bound to
+    pub addr: Once<ArcStr>,
 }
 
 pub enum LocalSocketType {
-    Listener { incoming: Channel<SocketPipe> },
-    Connection { pipe: SocketPipe },
+    Listener {
+        incoming: Channel<SocketConnection>,
+    },
+    Connection {
+        conn: SocketConnection,
+    },
+    Datagram {
+        /// closed when the socket is dropped, senders keep only the inbox alive
+        inbox: Inbox,
+        /// the default destination for `send`, doesn't keep the peer open
+        peer: Once<Weak<LocalSocket>>,
+    },
     None,
 }
 
@@ -358,13 +571,15 @@ impl LocalSocket {
         Self {
             info,
             inner: Once::new(),
+            addr: Once::new(),
         }
     }
 
-    pub const fn connected(info: SocketInfo, pipe: SocketPipe) -> Self {
+    pub const fn connected(info: SocketInfo, conn: SocketConnection) -> Self {
         Self {
             info,
-            inner: Once::initialized(LocalSocketType::Connection { pipe }),
+            inner: Once::initialized(LocalSocketType::Connection { conn }),
+            addr: Once::new(),
         }
     }
 
@@ -372,7 +587,11 @@ impl LocalSocket {
         !self.inner.is_completed()
     }
 
-    pub fn listener(&self) -> Result<&Channel<SocketPipe>> {
+    pub fn listener(&self) -> Result<&Channel<SocketConnection>> {
+        if self.info.ty == SocketType::DGRAM {
+            return Err(Error::INVALID_ARGUMENT);
+        }
+
         let inner = self.inner.call_once(|| LocalSocketType::Listener {
             incoming: Channel::new(16),
         });
@@ -384,23 +603,230 @@ impl LocalSocket {
         }
     }
 
-    pub fn connection(&self, pipe: SocketPipe) -> Result<&SocketPipe> {
+    pub fn connection(&self, conn: SocketConnection) -> Result<&SocketConnection> {
         let inner = self
             .inner
-            .call_once(move || LocalSocketType::Connection { pipe });
+            .call_once(move || LocalSocketType::Connection { conn });
 
-        if let LocalSocketType::Connection { pipe } = inner {
-            Ok(pipe)
+        if let LocalSocketType::Connection { conn } = inner {
+            Ok(conn)
         } else {
             Err(Error::INVALID_ARGUMENT)
         }
     }
 
+    /// the incoming packet queue and the default destination of a `DGRAM` socket
+    pub fn datagram(&self) -> Result<(&Inbox, &Once<Weak<LocalSocket>>)> {
+        if self.info.ty != SocketType::DGRAM {
+            return Err(Error::INVALID_ARGUMENT);
+        }
+
+        let inner = self.inner.call_once(|| LocalSocketType::Datagram {
+            inbox: Arc::new(Channel::new(64)),
+            peer: Once::new(),
+        });
+
+        if let LocalSocketType::Datagram { inbox, peer } = inner {
+            Ok((inbox, peer))
+        } else {
+            Err(Error::INVALID_ARGUMENT)
+        }
+    }
+
+    /// send a packet to a `DGRAM` socket
+    ///
+    /// returns [`Error::CONNECTION_REFUSED`] if the target gets closed before it has room,
+    /// or [`Error::WOULD_BLOCK`] if it has no room and `nonblock` is set
+    pub fn send_to(&self, target: Arc<LocalSocket>, buf: &[u8], nonblock: bool) -> Result<usize> {
+        self.send_to_with_fds(target, buf, Vec::new(), nonblock)
+    }
+
+    fn send_to_with_fds(
+        &self,
+        target: Arc<LocalSocket>,
+        buf: &[u8],
+        fds: FileRights,
+        nonblock: bool,
+    ) -> Result<usize> {
+        self.datagram()?;
+        let inbox = target.datagram()?.0.clone();
+
+        // a full inbox blocks, that shouldn't keep the target open
+        drop(target);
+
+        let packet = Packet {
+            from: self.addr.get().cloned(),
+            data: buf.to_vec(),
+            fds,
+        };
+        if nonblock {
+            match inbox.try_send(packet) {
+                Ok(None) => {}
+                Ok(Some(_)) => return Err(Error::WOULD_BLOCK),
+                Err(_) => return Err(Error::CONNECTION_REFUSED),
+            }
+        } else {
+            inbox.send(packet).map_err(|_| Error::CONNECTION_REFUSED)?;
+        }
+
+        Ok(buf.len())
+    }
+
+    /// the default destination of a `DGRAM` socket, if it is still open
+    pub fn peer(&self) -> Result<Arc<LocalSocket>> {
+        let (_, peer) = self.datagram()?;
+        peer.get()
+            .ok_or(Error::NOT_CONNECTED)?
+            .upgrade()
+            .ok_or(Error::CONNECTION_REFUSED)
+    }
+
+    /// a packet can be sent to this `DGRAM` socket without blocking
+    pub fn is_inbox_ready(&self) -> bool {
+        self.datagram()
+            .is_ok_and(|(inbox, _)| inbox.is_send_ready())
+    }
+
+    /// receive a packet from any socket, `DGRAM` sockets only
+    pub fn recv_from(&self, buf: &mut [u8], nonblock: bool) -> Result<(usize, Option<ArcStr>)> {
+        let packet = self.recv_packet(nonblock)?;
+        Ok((packet.copy_to(buf), packet.from))
+    }
+
+    /// receive the next packet of a `DGRAM` socket
+    fn recv_packet(&self, nonblock: bool) -> Result<Packet> {
+        let (inbox, _) = self.datagram()?;
+
+        if nonblock {
+            inbox
+                .try_recv()
+                .map_err(|_| Error::CLOSED)?
+                .ok_or(Error::WOULD_BLOCK)
+        } else {
+            inbox.recv().map_err(|_| Error::CLOSED)
+        }
+    }
+
+    /// send data and file descriptors to the connected peer
+    ///
+    /// with `STREAM` sockets, the file descriptors are queued before the data,
+    /// so they can be received as soon as the data is read
+    ///
+    /// returns [`Error::WOULD_BLOCK`] instead of blocking if `nonblock` is set,
+    /// then the file descriptors are only queued if some data was sent
+    pub fn send_msg(&self, buf: &[u8], fds: FileRights, nonblock: bool) -> Result<usize> {
+        if self.info.ty == SocketType::DGRAM {
+            return self.send_to_with_fds(self.peer()?, buf, fds, nonblock);
+        }
+
+        match self.inner() {
+            LocalSocketType::Connection {
+                conn: SocketConnection::Stream(pipe),
+            } if nonblock => {
+                // the receiver takes the queue lock after reading the data
+                let mut queue = pipe.send_fds.lock();
+                let sent = try_pipe_op(buf.len(), pipe.send.try_send_slice(buf))?;
+                queue.extend(fds);
+                Ok(sent)
+            }
+            LocalSocketType::Connection {
+                conn: SocketConnection::Stream(pipe),
+            } => {
+                pipe.send_fds.lock().extend(fds);
+                Ok(pipe.send.send_slice(buf).map(|_| buf.len()).unwrap_or(0))
+            }
+            LocalSocketType::Connection {
+                conn: SocketConnection::Packet(pipe),
+            } => {
+                let packet = Packet {
+                    from: None,
+                    data: buf.to_vec(),
+                    fds,
+                };
+                if !nonblock {
+                    return Ok(pipe.send.send(packet).map(|_| buf.len()).unwrap_or(0));
+                }
+                match pipe.send.try_send(packet) {
+                    Ok(None) => Ok(buf.len()),
+                    Ok(Some(_)) => Err(Error::WOULD_BLOCK),
+                    Err(_) => Ok(0),
+                }
+            }
+            _ => Err(Error::INVALID_ARGUMENT),
+        }
+    }
+
+    /// receive data and at most `max_fds` file descriptors
+    ///
+    /// file descriptors that don't fit are closed,
+    /// except with `STREAM` sockets, where they stay queued
+    ///
+    /// returns [`Error::WOULD_BLOCK`] instead of blocking if `nonblock` is set
+    pub fn recv_msg(
+        &self,
+        buf: &mut [u8],
+        max_fds: usize,
+        nonblock: bool,
+    ) -> Result<(usize, FileRights)> {
+        if self.info.ty == SocketType::DGRAM {
+            let mut packet = self.recv_packet(nonblock)?;
+            packet.fds.truncate(max_fds);
+            return Ok((packet.copy_to(buf), packet.fds));
+        }
+
+        match self.inner() {
+            LocalSocketType::Connection {
+                conn: SocketConnection::Stream(pipe),
+            } => {
+                let len = if buf.is_empty() {
+                    Ok(0)
+                } else if nonblock {
+                    try_pipe_op(buf.len(), pipe.recv.try_recv_slice(buf))
+                } else {
+                    Ok(pipe.recv.recv_slice(buf).unwrap_or(0))
+                };
+
+                // queued file descriptors can be received without any data
+                let mut queue = pipe.recv_fds.lock();
+                let len = match len {
+                    Err(Error::WOULD_BLOCK) if !queue.is_empty() && max_fds != 0 => 0,
+                    len => len?,
+                };
+                let n = queue.len().min(max_fds);
+                Ok((len, queue.drain(..n).collect()))
+            }
+            LocalSocketType::Connection {
+                conn: SocketConnection::Packet(pipe),
+            } => {
+                let packet = if nonblock {
+                    pipe.recv.try_recv().transpose().ok_or(Error::WOULD_BLOCK)?
+                } else {
+                    pipe.recv.recv()
+                };
+                let Ok(mut packet) = packet else {
+                    return Ok((0, Vec::new()));
+                };
+                packet.fds.truncate(max_fds);
+                Ok((packet.copy_to(buf), packet.fds))
+            }
+            _ => Err(Error::INVALID_ARGUMENT),
+        }
+    }
+
     fn inner(&self) -> &LocalSocketType {
         self.inner.get().unwrap_or(&LocalSocketType::None)
     }
 }
 
+impl Drop for LocalSocket {
+    fn drop(&mut self) {
+        // wake up the senders blocked on a full inbox
+        if let Some(LocalSocketType::Datagram { inbox, .. }) = self.inner.get() {
+            inbox.close();
+        }
+    }
+}
+
 impl FileDescriptor for LocalSocket {
     fn as_any(&self) -> &dyn Any {
         self
@@ -414,23 +840,136 @@ impl FileDescriptor for LocalSocket {
         Err(Error::IS_A_PIPE)
     }
 
+    fn metadata(&self) -> Result<Metadata> {
+        Ok(pipe_metadata(FileKind::SOCKET, "local"))
+    }
+
+    fn poll(&self) -> PollEvents {
+        match self.inner() {
+            LocalSocketType::Listener { incoming } => {
+                pipe_poll(incoming.is_recv_ready(), false, false)
+            }
+            LocalSocketType::Connection {
+                conn: SocketConnection::Stream(pipe),
+            } => pipe_poll(
+                pipe.recv.is_ready() || !pipe.recv_fds.lock().is_empty(),
+                pipe.send.is_ready(),
+                pipe.recv.is_closed(),
+            ),
+            LocalSocketType::Connection {
+                conn: SocketConnection::Packet(pipe),
+            } => pipe_poll(
+                pipe.recv.is_ready(),
+                pipe.send.is_ready(),
+                pipe.recv.is_closed(),
+            ),
+            LocalSocketType::Datagram { inbox, peer } => {
+                let peer = peer.get().map(Weak::upgrade);
+                pipe_poll(
+                    inbox.is_recv_ready(),
+                    // sending to a closed peer fails right away
+                    peer.as_ref()
+                        .is_none_or(|peer| peer.as_ref().is_none_or(|peer| peer.is_inbox_ready())),
+                    peer.is_some_and(|peer| peer.is_none()),
+                )
+            }
+            LocalSocketType::None => PollEvents::empty(),
+        }
+    }
+
+    fn listen(&self, listeners: &mut Listeners) {
+        match self.inner() {
+            LocalSocketType::Listener { incoming } => incoming.readiness.listen(listeners),
+            LocalSocketType::Connection {
+                conn: SocketConnection::Stream(pipe),
+            } => {
+                pipe.recv.listen(listeners);
+                pipe.send.listen(listeners);
+            }
+            LocalSocketType::Connection {
+                conn: SocketConnection::Packet(pipe),
+            } => {
+                pipe.recv.listen(listeners);
+                pipe.send.listen(listeners);
+            }
+            LocalSocketType::Datagram { inbox, peer } => {
+                inbox.readiness.listen(listeners);
+                if let Some(peer) = peer.get().and_then(Weak::upgrade) {
+                    if let Ok((peer_inbox, _)) = peer.datagram() {
+                        peer_inbox.readiness.listen(listeners);
+                    }
+                }
+            }
+            LocalSocketType::None => {}
+        }
+    }
+
     fn seek(&self, _: isize, _: Seek) -> Result<usize> {
         Err(Error::IS_A_PIPE)
     }
 
     fn read(&self, buf: &mut [u8]) -> Result<usize> {
-        match self.inner() {
-            LocalSocketType::Connection { pipe } => Ok(pipe.recv.recv_slice(buf).unwrap_or(0)),
-            _ => Err(Error::INVALID_ARGUMENT),
-        }
+        self.recv_msg(buf, 0, false).map(|(n, _)| n)
     }
 
     fn write(&self, buf: &[u8]) -> Result<usize> {
-        match self.inner() {
-            LocalSocketType::Connection { pipe } => {
-                Ok(pipe.send.send_slice(buf).map(|_| buf.len()).unwrap_or(0))
+        self.send_msg(buf, Vec::new(), false)
+    }
+
+    fn try_read(&self, buf: &mut [u8]) -> Result<usize> {
+        self.recv_msg(buf, 0, true).map(|(n, _)| n)
+    }
+
+    fn try_write(&self, buf: &[u8]) -> Result<usize> {
+        self.send_msg(buf, Vec::new(), true)
+    }
+}
+
+/// file descriptors sent over a local domain socket
+pub type FileRights = Vec<Arc<dyn FileDescriptor>>;
+
+/// the incoming packet queue of a `DGRAM` socket
+pub type Inbox = Arc<Channel<Packet>>;
+
+/// a single message of a `SEQPACKET` or a `DGRAM` socket
+pub struct Packet {
+    /// the bound address of the sender
+    pub from: Option<ArcStr>,
+    pub data: Vec<u8>,
+    /// file descriptors attached to this message
+    pub fds: FileRights,
+}
+
+impl Packet {
+    /// copy as much as fits, the rest of the message is discarded
+    pub fn copy_to(&self, buf: &mut [u8]) -> usize {
+        let len = self.data.len().min(buf.len());
+        buf[..len].copy_from_slice(&self.data[..len]);
+        len
+    }
+}
+
+/// one end of a connected local domain socket
+pub enum SocketConnection {
+    /// `STREAM` sockets
+    Stream(SocketPipe),
+    /// `SEQPACKET` sockets
+    Packet(PacketPipe),
+}
+
+impl SocketConnection {
+    /// create both ends of a connection for a socket type
+    pub fn new(ty: SocketType) -> Result<(Self, Self)> {
+        match ty {
+            SocketType::STREAM => {
+                let (a, b) = SocketPipe::new();
+                Ok((Self::Stream(a), Self::Stream(b)))
             }
-            _ => Err(Error::INVALID_ARGUMENT),
+            SocketType::SEQPACKET => {
+                let (a, b) = PacketPipe::new();
+                Ok((Self::Packet(a), Self::Packet(b)))
+            }
+            _ => Err(Error::INVALID_TYPE),
         }
     }
 }
@@ -439,12 +978,44 @@ impl FileDescriptor for LocalSocket {
 pub struct SocketPipe {
     pub send: Sender<u8>,
     pub recv: Receiver<u8>,
+    /// file descriptors waiting to be received by the other end
+    pub send_fds: Arc<Mutex<VecDeque<Arc<dyn FileDescriptor>>>>,
+    pub recv_fds: Arc<Mutex<VecDeque<Arc<dyn FileDescriptor>>>>,
 }
 
 impl SocketPipe {
     pub fn new() -> (Self, Self) {
         let (send_0, recv_1) = pipe_with(0x1000).split();
         let (send_1, recv_0) = pipe_with(0x1000).split();
+        let fds_0 = Arc::new(Mutex::new(VecDeque::new()));
+        let fds_1 = Arc::new(Mutex::new(VecDeque::new()));
+        (
+            Self {
+                send: send_0,
+                recv: recv_0,
+                send_fds: fds_0.clone(),
+                recv_fds: fds_1.clone(),
+            },
+            Self {
+                send: send_1,
+                recv: recv_1,
+                send_fds: fds_1,
+                recv_fds: fds_0,
+            },
+        )
+    }
+}
+
+/// local domain socket "pipe" that keeps the message boundaries
+pub struct PacketPipe {
+    pub send: Sender<Packet>,
+    pub recv: Receiver<Packet>,
+}
+
+impl PacketPipe {
+    pub fn new() -> (Self, Self) {
+        let (send_0, recv_1) = Channel::new(64).split();
+        let (send_1, recv_0) = Channel::new(64).split();
         (
             Self {
                 send: send_0,
@@ -458,14 +1029,33 @@ impl SocketPipe {
     }
 }
 
-/// VFS server socket file
-pub struct BoundSocket(pub Arc<LocalSocket>);
+/// VFS server socket file, the socket is closed even if the file is still there
+pub struct BoundSocket(pub Weak<LocalSocket>, pub Permissions);
+
+impl BoundSocket {
+    /// owned by the process that bound the socket
+    pub fn new(socket: &Arc<LocalSocket>) -> Self {
+        Self(Arc::downgrade(socket), Permissions::new(0o666))
+    }
+}
 
 impl FileDevice for BoundSocket {
     fn as_any(&self) -> &dyn core::any::Any {
         self
     }
 
+    fn driver(&self) -> &'static str {
+        "local"
+    }
+
+    fn kind(&self) -> FileKind {
+        FileKind::SOCKET
+    }
+
+    fn permissions(&self) -> Option<&Permissions> {
+        Some(&self.1)
+    }
+
     fn len(&self) -> usize {
         0
     }
@@ -475,18 +1065,19 @@ impl FileDevice for BoundSocket {
 
 pub struct ProcessExtra {
     pub files: Mutex<SparseVec<Arc<dyn FileDescriptor>>>,
+    /// file descriptors that get closed when the process image is replaced
+    pub cloexec: Mutex<BTreeSet<FileDesc>>,
+    /// file descriptors that return `WOULD_BLOCK` instead of blocking
+    pub nonblock: Mutex<BTreeSet<FileDesc>>,
     pub on_close: Mutex<Vec<Box<dyn FnOnce() + Send>>>,
-    pub cmdline: Once<ArcStr>,
-}
-
-impl Clone for ProcessExtra {
-    fn clone(&self) -> Self {
-        Self {
-            files: Mutex::new(self.files.lock().clone()),
-            on_close: Mutex::new(Vec::new()),
-            cmdline: Once::new(),
-        }
-    }
+    pub cmdline: Mutex<Option<ArcStr>>,
+    /// environment variables the current process image was started with
+    pub env: Mutex<Vec<(String, String)>>,
+    /// current working directory, relative paths are resolved from here
+    pub cwd: Mutex<PathBuf>,
+    /// memory mapped with `mmap`
+    pub mappings: Mutex<Mappings>,
+    pub signals: Signals,
 }
 
 impl ProcessExt for ProcessExtra {
@@ -494,6 +1085,10 @@ impl ProcessExt for ProcessExtra {
         self
     }
 
+    fn wait_if_stopped(&self, proc: &Process) {
+        self.signals.wait_while_stopped(proc);
+    }
+
     fn close(&self) {
         // FIXME: called twice with multiple threads + exit
         self.files.lock().inner.clear();
@@ -514,6 +1109,9 @@ impl ProcessExt for ProcessExtra {
         for f in self.on_close.lock().drain(..) {
             f();
         }
+        // release the mapped files, like the framebuffer
+        *self.mappings.lock() = Mappings::default();
+        self.signals.exit();
     }
 }
 
@@ -525,31 +1123,25 @@ impl Drop for ProcessExtra {
 
 //
 
+#[allow(clippy::too_many_arguments)]
 pub fn exec(
-    program: String,
-    args: Vec<String>,
+    mut program: String,
+    mut args: Vec<String>,
+    envs: Vec<(String, String)>,
+    cwd: PathBuf,
     stdin: Arc<dyn FileDescriptor>,
     stdout: Arc<dyn FileDescriptor>,
     stderr: Arc<dyn FileDescriptor>,
     on_close: Option<Box<dyn FnOnce() + Send>>,
-) -> Pid {
-    hyperion_scheduler::schedule(move || {
-        // set its name
-        hyperion_scheduler::rename(program.as_str());
-
-        // set up /proc/self/cmdline
-        let cmdline = slice::from_ref(&program).iter().chain(args.iter()).fold(
-            String::new(),
-            |mut acc, s| {
-                acc.push_str(s);
-                // cli args are null terminated + null separated (for compatibility)
-                acc.push_str("\x00");
-                acc
-            },
-        );
-        with_proc_ext(move |ext| {
-            ext.cmdline.call_once(move || cmdline.into());
-        });
+) -> Result<Pid> {
+    // the caller gets the errors, not the new process
+    let exe = read_executable(&mut program, &mut args)?;
+
+    Ok(hyperion_scheduler::schedule(move || {
+        // set its name, /proc/self/cmdline, the environment and the working directory
+        set_cmdline(&program, &args);
+        set_env(&envs);
+        set_cwd(cwd);
 
         // setup the STDIO
         fd_replace(FileDesc(0), stdin);
@@ -559,41 +1151,211 @@ pub fn exec(
             crate::on_close(on_close);
         }
 
-        // read the ELF file contents
-        // FIXME: read before schedule and return any read errors
-        let mut elf = Vec::new();
-        let bin = VFS_ROOT
-            .find_file(program.as_str(), false, false)
-            .unwrap_or_else(|err| panic!("could not load ELF `{program}`: {err}"));
-        let bin = bin.lock_arc();
-        loop {
-            let mut buf = [0; 64];
-            let len = bin.read(elf.len(), &mut buf).unwrap();
-            elf.extend_from_slice(&buf[..len]);
-            if len == 0 {
-                break;
-            }
+        load_elf(exe, program, args, envs);
+    }))
+}
+
+/// replace the current process image with a new ELF binary
+///
+/// the file descriptors are kept, except the close-on-exec ones,
+/// returns only if the ELF could not be read or is invalid
+pub fn exec_replace(
+    mut program: String,
+    mut args: Vec<String>,
+    envs: Vec<(String, String)>,
+) -> Result<Infallible> {
+    let exe = read_executable(&mut program, &mut args)?;
+
+    // the new image starts with just one thread
+    if hyperion_scheduler::kill_other_threads().is_err() {
+        hyperion_scheduler::exit_if_killed();
+        return Err(Error::INTERRUPTED);
+    }
+
+    // point of no return
+    fd_close_on_exec();
+    mmap_clear();
+    with_proc_ext(|ext| ext.signals.exec());
+    set_cmdline(&program, &args);
+    set_env(&envs);
+
+    hyperion_scheduler::exec(move || {
+        load_elf(exe, program, args, envs);
+    });
+}
+
+/// environment variables the current process image was started with
+pub fn env() -> Vec<(String, String)> {
+    with_proc_ext(|ext| ext.env.lock().clone())
+}
+
+/// set the environment variables of the current process image
+pub fn set_env(envs: &[(String, String)]) {
+    with_proc_ext(|ext| {
+        *ext.env.lock() = envs.to_vec();
+    });
+}
+
+/// current working directory of the current process
+pub fn cwd() -> PathBuf {
+    with_proc_ext(|ext| ext.cwd.lock().clone())
+}
+
+/// set the current working directory of the current process
+pub fn set_cwd(cwd: PathBuf) {
+    with_proc_ext(|ext| {
+        *ext.cwd.lock() = cwd;
+    });
+}
+
+/// resolve a path relative to the current working directory
+pub fn to_absolute(path: &str) -> PathBuf {
+    let cwd = cwd();
+    Path::from_str(path).to_absolute(&cwd).into_owned()
+}
+
+fn set_cmdline(program: &str, args: &[String]) {
+    // set its name
+    hyperion_scheduler::rename(program);
+
+    // set up /proc/self/cmdline
+    let cmdline = [program]
+        .into_iter()
+        .chain(args.iter().map(String::as_str))
+        .fold(String::new(), |mut acc, s| {
+            acc.push_str(s);
+            // cli args are null terminated + null separated (for compatibility)
+            acc.push_str("\x00");
+            acc
+        });
+    with_proc_ext(move |ext| {
+        *ext.cmdline.lock() = Some(cmdline.into());
+    });
+}
+
+fn read_elf(program: &str) -> Result<Vec<u8>> {
+    let mut elf = Vec::new();
+    let bin = VFS_ROOT.find_file(program, false, false)?;
+    let bin = bin.lock_arc();
+    check_exec(&bin.metadata())?;
+    loop {
+        let mut buf = [0; 64];
+        let len = bin.read(elf.len(), &mut buf)?;
+        elf.extend_from_slice(&buf[..len]);
+        if len == 0 {
+            break;
+        }
+    }
+    drop(bin);
+
+    Ok(elf)
+}
+
+/// an ELF file and its interpreter, read and validated before the process image is replaced
+struct Executable {
+    elf: Vec<u8>,
+    interpreter: Option<Vec<u8>>,
+}
+
+/// scripts can use other scripts as their interpreter, but only this deep
+const MAX_SCRIPT_DEPTH: usize = 4;
+
+/// read and validate an ELF file and its interpreter
+///
+/// `#!` scripts run their interpreter instead, so `program` and `args` are replaced with
+/// `interpreter [arg] script args..`
+///
+/// missing files are [`Error::NOT_FOUND`] and invalid ELFs are [`Error::INVALID_EXECUTABLE`]
+fn read_executable(program: &mut String, args: &mut Vec<String>) -> Result<Executable> {
+    for _ in 0..=MAX_SCRIPT_DEPTH {
+        let file = read_elf(program)?;
+
+        let Some((interpreter, arg)) = shebang(&file)? else {
+            return validate_elf(file);
+        };
+
+        let script = mem::replace(program, interpreter);
+        args.insert(0, script);
+        if let Some(arg) = arg {
+            args.insert(0, arg);
         }
-        drop(bin);
+    }
 
-        // load ..
-        let loader = Loader::new(elf.as_ref());
-        loader.load();
-        let entry = loader.finish();
+    Err(Error::INVALID_EXECUTABLE)
+}
 
-        // the elf is trying to steal our memory, drop the elf as a revenge
-        drop(elf);
+/// the interpreter and its optional argument from a `#!interpreter [arg]` line
+fn shebang(file: &[u8]) -> Result<Option<(String, Option<String>)>> {
+    let Some(line) = file.strip_prefix(b"#!") else {
+        return Ok(None);
+    };
+    let line = line.split(|b| *b == b'\n').next().unwrap_or_default();
+    let line = core::str::from_utf8(line)
+        .map_err(|_| Error::INVALID_EXECUTABLE)?
+        .trim();
+
+    // everything after the interpreter is one argument, like in Linux
+    let (interpreter, arg) = match line.split_once([' ', '\t']) {
+        Some((interpreter, arg)) => (interpreter, Some(arg.trim())),
+        None => (line, None),
+    };
+    if interpreter.is_empty() {
+        return Err(Error::INVALID_EXECUTABLE);
+    }
 
-        // .. and exec the binary
-        match entry {
-            Ok(entry) => entry.enter(program, args),
-            Err(_) => {
-                error!("no ELF entrypoint");
-                let stderr = fd_query(FileDesc(2)).unwrap();
-                stderr.write(b"invalid ELF: entry point missing").unwrap();
-            }
+    Ok(Some((
+        interpreter.into(),
+        arg.filter(|arg| !arg.is_empty()).map(Into::into),
+    )))
+}
+
+fn validate_elf(elf: Vec<u8>) -> Result<Executable> {
+    let interpreter = Loader::new(elf.as_ref())?
+        .interpreter()
+        .map(read_elf)
+        .transpose()?;
+    if let Some(interpreter) = interpreter.as_ref() {
+        Loader::new(interpreter.as_ref())?;
+    }
+
+    Ok(Executable { elf, interpreter })
+}
+
+fn load_elf(exe: Executable, program: String, args: Vec<String>, envs: Vec<(String, String)>) {
+    // load ..
+    let entry = load_executable(&exe).unwrap_or_else(|err| {
+        error!("could not load ELF `{program}`: {err}");
+        if let Ok(stderr) = fd_query(FileDesc(2)) {
+            _ = stderr.write(format!("could not load ELF: {err}\n").as_bytes());
         }
-    })
+        exit(ExitCode::CANNOT_EXECUTE);
+    });
+
+    // the elf is trying to steal our memory, drop the elf as a revenge
+    drop(exe);
+
+    // .. and exec the binary
+    entry.enter(program, args, envs);
+}
+
+fn load_executable(exe: &Executable) -> hyperion_loader::Result<EntryPoint> {
+    let loader = Loader::new(exe.elf.as_ref())?;
+    loader.load()?;
+    let mut entry = loader.finish();
+
+    // dynamically linked programs start from the dynamic linker,
+    // which is loaded right after the program
+    if let Some(interpreter) = exe.interpreter.as_ref() {
+        let base =
+            (process().heap_bottom.load(Ordering::SeqCst) as u64).next_multiple_of(0x20_0000);
+        let loader = Loader::new(interpreter.as_ref())?.with_base(base)?;
+        loader.load()?;
+        let base = loader.base();
+
+        entry.set_interpreter(loader.finish(), base);
+    }
+
+    Ok(entry)
 }
 
 pub fn on_close(on_close: Box<dyn FnOnce() + Send>) {
@@ -630,24 +1392,100 @@ pub fn fd_query_of<T: FileDescriptor + Any + 'static>(fd: FileDesc) -> Result<Ar
     // d.as_any().downcast_ref::<T>();
 }
 
-pub fn fd_push(data: Arc<dyn FileDescriptor>) -> FileDesc {
-    with_proc_ext(|ext| FileDesc(ext.files.lock().push(data)))
+/// push a file descriptor into the lowest free slot, which has to be below the open files limit
+pub fn fd_push(data: Arc<dyn FileDescriptor>) -> Result<FileDesc> {
+    let limit = process().limit(Resource::OPEN_FILES);
+
+    with_proc_ext(|ext| {
+        let mut files = ext.files.lock();
+        if files.next_free() as u64 >= limit {
+            return Err(Error::LIMIT_EXCEEDED);
+        }
+        Ok(FileDesc(files.push(data)))
+    })
 }
 
 pub fn fd_replace(fd: FileDesc, data: Arc<dyn FileDescriptor>) -> Option<Arc<dyn FileDescriptor>> {
-    with_proc_ext(|ext| ext.files.lock().replace(fd.0, data))
+    with_proc_ext(|ext| {
+        ext.cloexec.lock().remove(&fd);
+        ext.nonblock.lock().remove(&fd);
+        ext.files.lock().replace(fd.0, data)
+    })
 }
 
 pub fn fd_take(fd: FileDesc) -> Option<Arc<dyn FileDescriptor>> {
-    with_proc_ext(|ext| ext.files.lock().remove(fd.0))
+    with_proc_ext(|ext| {
+        ext.cloexec.lock().remove(&fd);
+        ext.nonblock.lock().remove(&fd);
+        ext.files.lock().remove(fd.0)
+    })
 }
 
 pub fn fd_copy(old: FileDesc, new: FileDesc) {
     with_proc_ext(|ext| {
         let mut files = ext.files.lock();
 
-        if let Some(old) = files.get(old.0).cloned() {
-            files.replace(new.0, old);
+        if let Some(old_file) = files.get(old.0).cloned() {
+            ext.cloexec.lock().remove(&new);
+
+            // the non-blocking mode is shared like the file itself
+            let mut nonblock = ext.nonblock.lock();
+            if nonblock.contains(&old) {
+                nonblock.insert(new);
+            } else {
+                nonblock.remove(&new);
+            }
+
+            files.replace(new.0, old_file);
+        }
+    })
+}
+
+/// mark a file descriptor to be closed on `exec`
+pub fn fd_set_cloexec(fd: FileDesc) {
+    with_proc_ext(|ext| {
+        ext.cloexec.lock().insert(fd);
+    })
+}
+
+/// the `NONBLOCK` and `CLOEXEC` flags of a file descriptor
+pub fn fd_flags(fd: FileDesc) -> FileOpenFlags {
+    with_proc_ext(|ext| {
+        let mut flags = FileOpenFlags::empty();
+        flags.set(FileOpenFlags::CLOEXEC, ext.cloexec.lock().contains(&fd));
+        flags.set(FileOpenFlags::NONBLOCK, ext.nonblock.lock().contains(&fd));
+        flags
+    })
+}
+
+/// set the `NONBLOCK` and `CLOEXEC` flags of a file descriptor, other flags are ignored
+pub fn fd_set_flags(fd: FileDesc, flags: FileOpenFlags) {
+    with_proc_ext(|ext| {
+        for (set, flag) in [
+            (&ext.cloexec, FileOpenFlags::CLOEXEC),
+            (&ext.nonblock, FileOpenFlags::NONBLOCK),
+        ] {
+            if flags.contains(flag) {
+                set.lock().insert(fd);
+            } else {
+                set.lock().remove(&fd);
+            }
+        }
+    })
+}
+
+pub fn fd_is_nonblock(fd: FileDesc) -> bool {
+    with_proc_ext(|ext| ext.nonblock.lock().contains(&fd))
+}
+
+/// close all close-on-exec file descriptors
+pub fn fd_close_on_exec() {
+    with_proc_ext(|ext| {
+        let mut files = ext.files.lock();
+        let mut nonblock = ext.nonblock.lock();
+        for fd in mem::take(&mut *ext.cloexec.lock()) {
+            nonblock.remove(&fd);
+            files.remove(fd.0);
         }
     })
 }
@@ -662,13 +1500,28 @@ pub fn with_proc_ext<F: FnOnce(&ProcessExtra) -> T, T>(f: F) -> T {
     f(ext)
 }
 
+/// pid 0 is the current process
+pub(crate) fn find_or_current(pid: Pid) -> Result<Arc<Process>> {
+    if pid.num() == 0 {
+        Ok(process())
+    } else {
+        pid.find().ok_or(Error::NO_SUCH_PROCESS)
+    }
+}
+
 pub fn process_ext_with(proc: &Process) -> &ProcessExtra {
     proc.ext
         .call_once(|| {
             Box::new(ProcessExtra {
                 files: Mutex::new(SparseVec::new()),
+                cloexec: Mutex::new(BTreeSet::new()),
+                nonblock: Mutex::new(BTreeSet::new()),
                 on_close: Mutex::new(Vec::new()),
-                cmdline: Once::new(),
+                cmdline: Mutex::new(None),
+                env: Mutex::new(Vec::new()),
+                cwd: Mutex::new(PathBuf::new("/")),
+                mappings: Mutex::new(Mappings::default()),
+                signals: Signals::new(proc.ppid),
             })
         })
         .as_any()
@@ -731,6 +1584,24 @@ pub fn read_untrusted_slice<'a, T: Copy>(ptr: u64, len: u64) -> Result<&'a [T]>
     })
 }
 
+pub fn read_untrusted_slice_mut<'a, T: Copy>(ptr: u64, len: u64) -> Result<&'a mut [T]> {
+    if !(ptr as *const T).is_aligned() {
+        return Err(Error::INVALID_ADDRESS);
+    }
+
+    let Some(size) = len.checked_mul(mem::size_of::<T>() as u64) else {
+        return Err(Error::INVALID_ADDRESS);
+    };
+
+    read_slice_parts(ptr, size).map(|(start, _)| {
+        if len == 0 {
+            &mut []
+        } else {
+            unsafe { core::slice::from_raw_parts_mut(start.as_mut_ptr(), len as _) }
+        }
+    })
+}
+
 pub fn read_untrusted_bytes<'a>(ptr: u64, len: u64) -> Result<&'a [u8]> {
     read_slice_parts(ptr, len).map(|(start, len)| {
         // TODO:
@@ -0,0 +1,32 @@
+use hyperion_scheduler::process;
+use hyperion_syscall::{
+    err::{Error, Result},
+    limit::{Limit, Resource},
+};
+
+//
+
+/// the soft and hard limits of a resource of the current process
+pub fn getrlimit(resource: Resource) -> Result<Limit> {
+    process()
+        .limits
+        .get(resource)
+        .ok_or(Error::INVALID_ARGUMENT)
+}
+
+/// set the soft and hard limits of a resource of the current process
+///
+/// hard limits can only be lowered, unless the process runs as root
+pub fn setrlimit(resource: Resource, limit: Limit) -> Result<()> {
+    if limit.cur > limit.max {
+        return Err(Error::INVALID_ARGUMENT);
+    }
+
+    let this = process();
+    this.limits.update(resource, |old| {
+        if limit.max > old.max && !this.is_root() {
+            return Err(Error::PERMISSION_DENIED);
+        }
+        Ok(limit)
+    })
+}
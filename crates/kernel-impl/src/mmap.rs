@@ -0,0 +1,412 @@
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
+use core::{ops::Range, slice, sync::atomic::Ordering};
+
+use hyperion_arch::{stack::USER_HEAP_TOP, vmm::NO_FREE};
+use hyperion_log::*;
+use hyperion_mem::vmm::{MapTarget, PageMapImpl};
+use hyperion_scheduler::process;
+use hyperion_syscall::{
+    err::{Error, Result},
+    mem::{MapFlags, Prot},
+};
+use hyperion_vfs::{ramdisk, tree::FileRef};
+use x86_64::{structures::paging::PageTableFlags, VirtAddr};
+
+use crate::with_proc_ext;
+
+//
+
+/// the lowest address that user mappings can use
+const USER_BOTTOM: u64 = 0x1000;
+
+//
+
+/// all `mmap` mappings of a process, keyed by their start address
+#[derive(Clone, Default)]
+pub struct Mappings {
+    inner: BTreeMap<VirtAddr, Mapping>,
+}
+
+#[derive(Clone)]
+struct Mapping {
+    end: VirtAddr,
+    prot: Prot,
+    /// allocated with [`palloc`], so it can be freed with [`pfree`]
+    palloc: bool,
+    /// the file behind a shared mapping, shared by every piece split from the original mapping
+    ///
+    /// only kept for unmapping the file when dropped
+    _file: Option<Arc<MappedFile>>,
+}
+
+/// a file mapped with [`hyperion_vfs::device::FileDevice::map_phys`]
+///
+/// the file is unmapped once the last piece of the mapping is gone
+struct MappedFile(FileRef);
+
+impl Drop for MappedFile {
+    fn drop(&mut self) {
+        if let Err(err) = self.0.lock().unmap_phys() {
+            warn!("failed to unmap a mapped file: {err}");
+        }
+    }
+}
+
+impl Mappings {
+    /// split the mapping that goes over `at`, so that `at` is on a mapping boundary
+    fn split_at(&mut self, at: VirtAddr) {
+        let Some((_, mapping)) = self.inner.range_mut(..at).next_back() else {
+            return;
+        };
+        if mapping.end <= at {
+            return;
+        }
+
+        let tail = mapping.clone();
+        mapping.end = at;
+        self.inner.insert(at, tail);
+    }
+
+    /// remove all mappings and mapping pieces in `range`
+    ///
+    /// returns the removed mappings, the caller should drop them only after the pages are unmapped
+    fn remove(&mut self, range: Range<VirtAddr>) -> Vec<(VirtAddr, Mapping)> {
+        self.split_at(range.start);
+        self.split_at(range.end);
+
+        let starts: Vec<VirtAddr> = self.inner.range(range).map(|(&start, _)| start).collect();
+        starts
+            .into_iter()
+            .filter_map(|start| Some((start, self.inner.remove(&start)?)))
+            .collect()
+    }
+
+    /// test if every page in `range` is in some mapping that passes `filter`
+    fn covers(&self, range: Range<VirtAddr>, filter: impl Fn(&Mapping) -> bool) -> bool {
+        let mut pos = range.start;
+        while pos < range.end {
+            match self.inner.range(..=pos).next_back() {
+                Some((_, mapping)) if mapping.end > pos && filter(mapping) => pos = mapping.end,
+                _ => return false,
+            }
+        }
+        true
+    }
+
+    /// the number of mapped bytes in `range`
+    fn bytes_in(&self, range: Range<VirtAddr>) -> u64 {
+        self.inner
+            .range(..range.end)
+            .rev()
+            .take_while(|(_, mapping)| mapping.end > range.start)
+            .map(|(&start, mapping)| mapping.end.min(range.end) - start.max(range.start))
+            .sum()
+    }
+
+    /// test if any page in `range` is in some mapping
+    fn overlaps(&self, range: Range<VirtAddr>) -> bool {
+        self.inner
+            .range(..range.end)
+            .next_back()
+            .is_some_and(|(_, mapping)| mapping.end > range.start)
+    }
+}
+
+//
+
+/// the page table flags for user pages with these protections
+fn prot_flags(prot: Prot) -> PageTableFlags {
+    let mut flags = PageTableFlags::empty();
+    if !prot.is_empty() {
+        flags.insert(PageTableFlags::USER_ACCESSIBLE);
+    }
+    if prot.contains(Prot::WRITE) {
+        flags.insert(PageTableFlags::WRITABLE);
+    }
+    if !prot.contains(Prot::EXEC) {
+        flags.insert(PageTableFlags::NO_EXECUTE);
+    }
+    flags
+}
+
+/// map anonymous memory or a file into the user address space of the current process
+///
+/// `file` is required unless `flags` has [`MapFlags::ANONYMOUS`]
+pub fn mmap(
+    at: Option<VirtAddr>,
+    size: usize,
+    prot: Prot,
+    flags: MapFlags,
+    file: Option<FileRef>,
+    offset: usize,
+) -> Result<VirtAddr> {
+    mmap_with(at, size, prot, flags, file, offset, false)
+}
+
+/// allocate `n_pages` of zeroed read and write memory
+pub fn palloc(n_pages: usize) -> Result<VirtAddr> {
+    let size = n_pages
+        .checked_mul(0x1000)
+        .ok_or(Error::OUT_OF_VIRTUAL_MEMORY)?;
+    mmap_with(
+        None,
+        size,
+        Prot::READ | Prot::WRITE,
+        MapFlags::PRIVATE | MapFlags::ANONYMOUS,
+        None,
+        0,
+        true,
+    )
+}
+
+/// free memory allocated with [`palloc`], other memory is left alone
+pub fn pfree(at: VirtAddr, n_pages: usize) -> Result<()> {
+    let size = n_pages.checked_mul(0x1000).ok_or(Error::INVALID_ALLOC)?;
+    let range = user_range(at, size as u64).map_err(|_| Error::INVALID_ALLOC)?;
+
+    let is_palloc =
+        with_proc_ext(|ext| ext.mappings.lock().covers(range, |mapping| mapping.palloc));
+    if !is_palloc {
+        return Err(Error::INVALID_ALLOC);
+    }
+
+    munmap(at, size)
+}
+
+fn mmap_with(
+    at: Option<VirtAddr>,
+    size: usize,
+    prot: Prot,
+    flags: MapFlags,
+    file: Option<FileRef>,
+    offset: usize,
+    palloc: bool,
+) -> Result<VirtAddr> {
+    if flags.contains(MapFlags::SHARED) == flags.contains(MapFlags::PRIVATE) {
+        return Err(Error::INVALID_FLAGS);
+    }
+    if size == 0 || offset & 0xfff != 0 {
+        return Err(Error::INVALID_ARGUMENT);
+    }
+    if flags.contains(MapFlags::FIXED) && at.is_none() {
+        return Err(Error::INVALID_ADDRESS);
+    }
+    let size = (size as u64)
+        .checked_next_multiple_of(0x1000)
+        .ok_or(Error::OUT_OF_VIRTUAL_MEMORY)?;
+
+    let file = match (flags.contains(MapFlags::ANONYMOUS), file) {
+        // shared anonymous memory is just an unnamed file
+        (true, _) if flags.contains(MapFlags::SHARED) => Some(ramdisk::File::new_empty()),
+        (true, _) => None,
+        (false, Some(file)) => Some(file),
+        (false, None) => return Err(Error::BAD_FILE_DESCRIPTOR),
+    };
+
+    let this = process();
+    let page_map = &this.address_space.page_map;
+
+    with_proc_ext(|ext| {
+        let mut mappings = ext.mappings.lock();
+
+        let start = match at {
+            Some(at) if flags.contains(MapFlags::FIXED) => {
+                let range = user_range(at, size)?;
+
+                // the old mappings are still counted, so only the difference is reserved,
+                // the call fails without touching them if the new mapping doesn't fit
+                let replaced = mappings.bytes_in(range.clone());
+                this.reserve_virt_mem((size - replaced) as usize)
+                    .map_err(|_| Error::OUT_OF_VIRTUAL_MEMORY)?;
+
+                page_map.unmap(range.clone());
+                drop(mappings.remove(range.clone()));
+
+                this.heap_bottom
+                    .fetch_max(range.end.as_u64() as usize, Ordering::SeqCst);
+                at
+            }
+            // the hint is used if nothing has been mapped there yet
+            Some(at)
+                if user_range(at.align_down(0x1000u64), size).is_ok_and(|range| {
+                    range.start.as_u64() as usize >= this.heap_bottom.load(Ordering::SeqCst)
+                        && !mappings.overlaps(range)
+                }) =>
+            {
+                let at = at.align_down(0x1000u64);
+                this.heap_bottom
+                    .fetch_max((at + size).as_u64() as usize, Ordering::SeqCst);
+                at
+            }
+            _ => {
+                let at = this.heap_bottom.fetch_add(size as usize, Ordering::SeqCst);
+                let at = VirtAddr::try_new(at as u64).map_err(|_| Error::OUT_OF_VIRTUAL_MEMORY)?;
+                user_range(at, size).map_err(|_| Error::OUT_OF_VIRTUAL_MEMORY)?;
+                at
+            }
+        };
+        let mut end = start + size;
+
+        if !flags.contains(MapFlags::FIXED) {
+            this.reserve_virt_mem(size as usize)
+                .map_err(|_| Error::OUT_OF_VIRTUAL_MEMORY)?;
+        }
+        let release = |n_bytes: u64| {
+            this.virt_mem.fetch_sub(n_bytes as usize, Ordering::Relaxed);
+        };
+
+        let file = match file {
+            Some(file) if flags.contains(MapFlags::SHARED) => {
+                let mapped = file
+                    .lock()
+                    .map_phys(page_map, start..end, offset, prot_flags(prot) | NO_FREE)
+                    .inspect_err(|_| release(size))?;
+                // unmap_phys is called even if nothing got mapped
+                let file = Arc::new(MappedFile(file));
+                if mapped == 0 {
+                    release(size);
+                    return Err(Error::INVALID_ARGUMENT);
+                }
+
+                end = (start + mapped as u64).align_up(0x1000u64);
+                Some(file)
+            }
+            Some(file) => {
+                // private file mappings are copies of the file contents
+                page_map.map(
+                    start..end,
+                    MapTarget::LazyAlloc,
+                    PageTableFlags::USER_ACCESSIBLE
+                        | PageTableFlags::WRITABLE
+                        | PageTableFlags::NO_EXECUTE,
+                );
+
+                let buf = unsafe { slice::from_raw_parts_mut(start.as_mut_ptr::<u8>(), size as _) };
+                if let Err(err) = read_to_fill(&file, offset, buf) {
+                    page_map.unmap(start..end);
+                    release(size);
+                    return Err(err);
+                }
+
+                page_map.remap(start..end, prot_flags(prot));
+                None
+            }
+            None => {
+                page_map.map(start..end, MapTarget::LazyAlloc, prot_flags(prot));
+                None
+            }
+        };
+
+        // shared file mappings can be shorter than requested
+        release(size.saturating_sub(end - start));
+        mappings.inner.insert(
+            start,
+            Mapping {
+                end,
+                prot,
+                palloc,
+                _file: file,
+            },
+        );
+
+        Ok(start)
+    })
+}
+
+/// unmap any page aligned range of the user address space of the current process
+///
+/// mappings that are only partially in the range are split
+pub fn munmap(at: VirtAddr, size: usize) -> Result<()> {
+    if size == 0 {
+        return Err(Error::INVALID_ARGUMENT);
+    }
+    let size = (size as u64)
+        .checked_next_multiple_of(0x1000)
+        .ok_or(Error::INVALID_ADDRESS)?;
+    let range = user_range(at, size)?;
+
+    let this = process();
+
+    with_proc_ext(|ext| {
+        let old = ext.mappings.lock().remove(range.clone());
+        this.address_space.page_map.unmap(range);
+        this.virt_mem
+            .fetch_sub(mapped_bytes(&old), Ordering::Relaxed);
+    });
+
+    Ok(())
+}
+
+/// change the page protections of a page aligned range of mapped memory of the current process
+pub fn mprotect(at: VirtAddr, size: usize, prot: Prot) -> Result<()> {
+    if size == 0 {
+        return Err(Error::INVALID_ARGUMENT);
+    }
+    let size = (size as u64)
+        .checked_next_multiple_of(0x1000)
+        .ok_or(Error::INVALID_ADDRESS)?;
+    let range = user_range(at, size)?;
+
+    let this = process();
+    let page_map = &this.address_space.page_map;
+
+    with_proc_ext(|ext| {
+        let mut mappings = ext.mappings.lock();
+
+        // memory not mapped with `mmap`, like the ELF segments and the stacks,
+        // can be protected too if it is all user accessible
+        if !mappings.covers(range.clone(), |_| true)
+            && !page_map.is_mapped(range.clone(), PageTableFlags::USER_ACCESSIBLE)
+        {
+            return Err(Error::INVALID_ADDRESS);
+        }
+
+        mappings.split_at(range.start);
+        mappings.split_at(range.end);
+        for (_, mapping) in mappings.inner.range_mut(range.clone()) {
+            mapping.prot = prot;
+        }
+
+        page_map.remap(range, prot_flags(prot));
+
+        Ok(())
+    })
+}
+
+/// forget all mappings, the pages themselves are unmapped with the rest of the user memory
+pub fn mmap_clear() {
+    with_proc_ext(|ext| ext.mappings.lock().inner.clear());
+}
+
+fn user_range(at: VirtAddr, size: u64) -> Result<Range<VirtAddr>> {
+    if !at.is_aligned(0x1000u64) {
+        return Err(Error::INVALID_ADDRESS);
+    }
+
+    let end = at
+        .as_u64()
+        .checked_add(size)
+        .filter(|end| at.as_u64() >= USER_BOTTOM && *end <= USER_HEAP_TOP)
+        .ok_or(Error::INVALID_ADDRESS)?;
+
+    Ok(at..VirtAddr::new(end))
+}
+
+fn mapped_bytes(mappings: &[(VirtAddr, Mapping)]) -> usize {
+    mappings
+        .iter()
+        .map(|(start, mapping)| (mapping.end - *start) as usize)
+        .sum()
+}
+
+fn read_to_fill(file: &FileRef, offset: usize, buf: &mut [u8]) -> Result<()> {
+    let file = file.lock();
+    let mut filled = 0;
+    while filled < buf.len() {
+        match file.read(offset + filled, &mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(())
+}
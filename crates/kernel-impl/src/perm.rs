@@ -0,0 +1,174 @@
+use core::sync::atomic::Ordering;
+
+use hyperion_scheduler::process;
+use hyperion_syscall::{
+    err::{Error, Result},
+    fs::{FileOpenFlags, Metadata},
+};
+use hyperion_vfs::{device::Permissions, path::Path, tree::Node};
+
+use crate::VFS_ROOT;
+
+//
+
+/// unix style `rwx` permission bits, for one of owner, group or others
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Access(pub usize);
+
+impl Access {
+    pub const READ: Self = Self(0o4);
+    pub const WRITE: Self = Self(0o2);
+    pub const EXEC: Self = Self(0o1);
+    pub const READ_WRITE: Self = Self(0o6);
+
+    /// the access that opening a node with `flags` needs
+    pub fn from_open_flags(flags: FileOpenFlags) -> Self {
+        let mut access = 0;
+        if flags.intersects(FileOpenFlags::READ | FileOpenFlags::IS_DIR) {
+            access |= Self::READ.0;
+        }
+        if flags.intersects(FileOpenFlags::WRITE | FileOpenFlags::TRUNC | FileOpenFlags::APPEND) {
+            access |= Self::WRITE.0;
+        }
+        Self(access)
+    }
+}
+
+//
+
+/// test if the current process can access a node with the given metadata
+///
+/// root can access everything
+pub fn check_access(meta: &Metadata, access: Access) -> Result<()> {
+    let this = process();
+    if this.is_root() {
+        return Ok(());
+    }
+
+    let bits = if meta.uid == this.uid() {
+        meta.mode >> 6
+    } else if meta.gid == this.gid() {
+        meta.mode >> 3
+    } else {
+        meta.mode
+    };
+
+    if bits & access.0 == access.0 {
+        Ok(())
+    } else {
+        Err(Error::PERMISSION_DENIED)
+    }
+}
+
+/// see [`check_access`]
+pub fn check_node(node: &Node, access: Access) -> Result<()> {
+    check_access(&node.metadata(), access)
+}
+
+/// test if the current process can execute a file with the given metadata
+///
+/// root can execute it if anyone can
+pub fn check_exec(meta: &Metadata) -> Result<()> {
+    if meta.mode & 0o111 == 0 {
+        return Err(Error::PERMISSION_DENIED);
+    }
+    check_access(meta, Access::EXEC)
+}
+
+/// test if the current process can remove `path`, its parent directory has to be writable
+pub fn check_remove(path: &str) -> Result<()> {
+    check_node(
+        &VFS_ROOT.find(Path::from_str(path).parent(), false)?,
+        Access::WRITE,
+    )
+}
+
+/// test if the current process can create `path`
+///
+/// the closest existing parent directory has to be writable
+pub fn check_create(path: &str) -> Result<()> {
+    let mut dir = Path::from_str(path).parent();
+    loop {
+        match VFS_ROOT.find(dir, false) {
+            Ok(node) => return check_node(&node, Access::WRITE),
+            Err(Error::NOT_FOUND) if !dir.is_root() => dir = dir.parent(),
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+//
+
+/// the user id of the current process
+pub fn getuid() -> usize {
+    process().uid()
+}
+
+/// set the user id of the current process, only root can switch to another user
+pub fn setuid(uid: usize) -> Result<()> {
+    let this = process();
+    if uid != this.uid() && !this.is_root() {
+        return Err(Error::PERMISSION_DENIED);
+    }
+
+    this.uid.store(uid, Ordering::SeqCst);
+    Ok(())
+}
+
+/// the group id of the current process
+pub fn getgid() -> usize {
+    process().gid()
+}
+
+/// set the group id of the current process, only root can switch to another group
+pub fn setgid(gid: usize) -> Result<()> {
+    let this = process();
+    if gid != this.gid() && !this.is_root() {
+        return Err(Error::PERMISSION_DENIED);
+    }
+
+    this.gid.store(gid, Ordering::SeqCst);
+    Ok(())
+}
+
+/// set the permission bits of a node, only its owner and root can do it
+pub fn chmod(path: &str, mode: usize) -> Result<()> {
+    let this = process();
+    with_permissions(&VFS_ROOT.find(path, false)?, |perms| {
+        if perms.uid.load(Ordering::Relaxed) != this.uid() && !this.is_root() {
+            return Err(Error::PERMISSION_DENIED);
+        }
+
+        perms.chmod(mode);
+        Ok(())
+    })
+}
+
+/// set the owner and the group of a node, only root can do it
+pub fn chown(path: &str, uid: Option<usize>, gid: Option<usize>) -> Result<()> {
+    if !process().is_root() {
+        return Err(Error::PERMISSION_DENIED);
+    }
+
+    with_permissions(&VFS_ROOT.find(path, false)?, |perms| {
+        perms.chown(
+            uid.unwrap_or_else(|| perms.uid.load(Ordering::Relaxed)),
+            gid.unwrap_or_else(|| perms.gid.load(Ordering::Relaxed)),
+        );
+        Ok(())
+    })
+}
+
+/// nodes without changeable permissions are always owned by root
+fn with_permissions(node: &Node, f: impl FnOnce(&Permissions) -> Result<()>) -> Result<()> {
+    match node {
+        Node::File(file) => file
+            .lock()
+            .permissions()
+            .map_or(Err(Error::PERMISSION_DENIED), f),
+        Node::Directory(dir) => dir
+            .lock()
+            .permissions()
+            .map_or(Err(Error::PERMISSION_DENIED), f),
+    }
+}
@@ -3,6 +3,7 @@ use alloc::{
     format,
     string::String,
     sync::{Arc, Weak},
+    vec::Vec,
 };
 use core::{
     any::Any,
@@ -13,10 +14,13 @@ use core::{
 use arcstr::ArcStr;
 use hyperion_mem::vmm::PageMapImpl;
 use hyperion_scheduler::{
-    proc::{processes, Pid, Process, PROCESSES},
-    process,
+    proc::{processes, Limits, Pid, Process, PROCESSES},
+    process, RunQueueStats,
+};
+use hyperion_syscall::{
+    err::{Error, Result},
+    limit::{LimitValue, Resource},
 };
-use hyperion_syscall::err::{Error, Result};
 use hyperion_vfs::{
     device::{ArcOrRef, DirEntry, DirectoryDevice, FileDevice},
     tree::{IntoNode, Node},
@@ -106,6 +110,12 @@ impl ProcFs {
             .clone()
     }
 
+    fn schedstat(&self) -> Node {
+        Node::new_file(DisplayFile(SchedStat(
+            hyperion_scheduler::run_queue_stats().collect(),
+        )))
+    }
+
     fn self_dir(&self) -> Node {
         Node::new_dir(ProcDir(process()))
     }
@@ -129,6 +139,7 @@ impl DirectoryDevice for ProcFs {
             "version" => Ok(self.version()),
             "uptime" => Ok(self.uptime()),
             "cpuinfo" => Ok(self.cpuinfo()),
+            "schedstat" => Ok(self.schedstat()),
             "self" => Ok(self.self_dir()),
             "sys" => Ok(self.sys()),
             _ => {
@@ -172,6 +183,7 @@ impl DirectoryDevice for ProcFs {
                 ("cmdline", self.cmdline()),
                 ("cpuinfo", self.cpuinfo()),
                 ("meminfo", self.meminfo()),
+                ("schedstat", self.schedstat()),
                 ("uptime", self.uptime()),
                 ("version", self.version()),
                 ("self", self.self_dir()),
@@ -214,8 +226,12 @@ impl ProcDir {
         Node::new_file(DisplayFile(ProcStatus::new(self.0.clone())))
     }
 
+    fn limits(&self) -> Node {
+        Node::new_file(DisplayFile(ProcLimits(self.0.limits.clone())))
+    }
+
     fn cmdline(&self) -> Node {
-        if let Some(cmdline) = process_ext_with(&self.0).cmdline.get().cloned() {
+        if let Some(cmdline) = process_ext_with(&self.0).cmdline.lock().clone() {
             Node::new_file(DisplayFile(cmdline))
         } else {
             Node::new_file(DisplayFile(self.0.name.read().clone()))
@@ -228,6 +244,7 @@ impl DirectoryDevice for ProcDir {
         match name {
             "status" => Ok(self.status()),
             "cmdline" => Ok(self.cmdline()),
+            "limits" => Ok(self.limits()),
             _ => Err(Error::NOT_FOUND),
         }
     }
@@ -243,6 +260,10 @@ impl DirectoryDevice for ProcDir {
                     name: ArcOrRef::Ref("cmdline"),
                     node: self.cmdline(),
                 },
+                DirEntry {
+                    name: ArcOrRef::Ref("limits"),
+                    node: self.limits(),
+                },
             ]
             .into_iter(),
         ))
@@ -256,6 +277,8 @@ struct ProcStatus {
     pid: Pid,
     threads: usize,
     nanos: u64,
+    nice: i32,
+    cpus_allowed: u64,
     vm_size: u64,
 }
 
@@ -266,6 +289,8 @@ impl ProcStatus {
             pid: proc.pid,
             threads: proc.threads.load(Ordering::Relaxed),
             nanos: proc.nanos.load(Ordering::Relaxed),
+            nice: proc.nice(),
+            cpus_allowed: proc.affinity.load(Ordering::Relaxed) & hyperion_scheduler::online_cpus(),
             vm_size: proc.address_space.page_map.info().virt_size() as u64 >> 10,
         }
     }
@@ -277,6 +302,8 @@ impl fmt::Display for ProcStatus {
         writeln!(f, "Pid: {}", self.pid)?;
         writeln!(f, "Threads: {}", self.threads)?;
         writeln!(f, "Nanos: {}", self.nanos)?;
+        writeln!(f, "Nice: {}", self.nice)?;
+        writeln!(f, "Cpus_allowed: {:x}", self.cpus_allowed)?;
         writeln!(f, "VmSize: {} kB", self.vm_size)?;
         Ok(())
     }
@@ -284,6 +311,30 @@ impl fmt::Display for ProcStatus {
 
 //
 
+struct ProcLimits(Limits);
+
+impl fmt::Display for ProcLimits {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Limit                Soft Limit   Hard Limit   Units")?;
+        for resource in Resource::ALL {
+            let Some(limit) = self.0.get(resource) else {
+                continue;
+            };
+            writeln!(
+                f,
+                "{:<20} {:<12} {:<12} {}",
+                resource.name(),
+                LimitValue(limit.cur),
+                LimitValue(limit.max),
+                resource.unit()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+//
+
 struct MemInfo {
     total: usize,
     free: usize,
@@ -299,6 +350,21 @@ impl fmt::Display for MemInfo {
 
 //
 
+/// ready queue length and the number of stolen tasks per CPU
+struct SchedStat(Vec<RunQueueStats>);
+
+impl fmt::Display for SchedStat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "CPU  Ready  Migrations")?;
+        for (cpu, stats) in self.0.iter().enumerate() {
+            writeln!(f, "{cpu:<4} {:<6} {}", stats.ready, stats.migrations)?;
+        }
+        Ok(())
+    }
+}
+
+//
+
 struct Uptime {
     system_s: f32,
     cpu_idle_sum_s: f32,
@@ -0,0 +1,67 @@
+use core::sync::atomic::Ordering;
+
+use hyperion_scheduler::{online_cpus, proc::Pid, process, task};
+use hyperion_syscall::err::{Error, Result};
+
+use crate::find_or_current;
+
+//
+
+/// the nice value of a process, pid 0 is the current process
+pub fn get_priority(pid: Pid) -> Result<i32> {
+    Ok(find_or_current(pid)?.nice())
+}
+
+/// set the nice value of a process, pid 0 is the current process
+///
+/// only root can raise the priority or change the processes of other users
+pub fn set_priority(pid: Pid, nice: i32) -> Result<()> {
+    let this = process();
+    let target = find_or_current(pid)?;
+
+    if !this.is_root() && (target.uid() != this.uid() || nice < target.nice()) {
+        return Err(Error::PERMISSION_DENIED);
+    }
+
+    target.set_nice(nice);
+    Ok(())
+}
+
+/// the affinity mask of a process, or the current thread if pid is 0
+pub fn sched_getaffinity(pid: Pid) -> Result<u64> {
+    if pid.num() == 0 {
+        return Ok(task().allowed_cpus());
+    }
+
+    let target = pid.find().ok_or(Error::NO_SUCH_PROCESS)?;
+    Ok(target.affinity.load(Ordering::Relaxed) & online_cpus())
+}
+
+/// set the affinity mask of a process, or the current thread if pid is 0
+///
+/// the mask has to include at least one online CPU, and for threads one that the process
+/// allows, only root can change the processes of other users,
+/// running threads move to an allowed CPU the next time they are scheduled
+pub fn sched_setaffinity(pid: Pid, mask: u64) -> Result<()> {
+    if mask & online_cpus() == 0 {
+        return Err(Error::INVALID_ARGUMENT);
+    }
+
+    if pid.num() == 0 {
+        if mask & process().affinity.load(Ordering::Relaxed) & online_cpus() == 0 {
+            return Err(Error::INVALID_ARGUMENT);
+        }
+
+        task().affinity.store(mask, Ordering::Relaxed);
+        return Ok(());
+    }
+
+    let this = process();
+    let target = pid.find().ok_or(Error::NO_SUCH_PROCESS)?;
+    if !this.is_root() && target.uid() != this.uid() {
+        return Err(Error::PERMISSION_DENIED);
+    }
+
+    target.affinity.store(mask, Ordering::Relaxed);
+    Ok(())
+}
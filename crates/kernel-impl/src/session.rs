@@ -0,0 +1,94 @@
+use alloc::sync::Arc;
+use core::sync::atomic::Ordering;
+
+use hyperion_scheduler::{
+    proc::{processes, Pid, Process},
+    process,
+};
+use hyperion_syscall::err::{Error, Result};
+
+use crate::find_or_current;
+
+//
+
+/// test if a process group has any live processes in a session
+fn group_exists(pgid: Pid, sid: Pid) -> bool {
+    processes()
+        .iter()
+        .any(|proc| proc.pgid() == pgid && proc.sid() == sid && proc.exit_code.get().is_none())
+}
+
+/// the session leader holds the controlling terminal of its session
+fn session_leader(sid: Pid) -> Result<Arc<Process>> {
+    sid.find()
+        .filter(|leader| leader.sid() == leader.pid)
+        .ok_or(Error::NO_SUCH_PROCESS)
+}
+
+//
+
+/// move the current process or one of its children into a process group
+///
+/// pid 0 is the current process and pgid 0 makes a new group with the same id as the process
+pub fn setpgid(pid: Pid, pgid: Pid) -> Result<()> {
+    let this = process();
+    let target = find_or_current(pid)?;
+    let pgid = if pgid.num() == 0 { target.pid } else { pgid };
+
+    if target.pid != this.pid && target.ppid != this.pid {
+        return Err(Error::NO_SUCH_PROCESS);
+    }
+
+    // session leaders can't leave their group and groups can't span sessions
+    let sid = this.sid();
+    if target.sid() != sid || target.pid == sid {
+        return Err(Error::PERMISSION_DENIED);
+    }
+    if pgid != target.pid && !group_exists(pgid, sid) {
+        return Err(Error::PERMISSION_DENIED);
+    }
+
+    target.pgid.store(pgid.num(), Ordering::SeqCst);
+    Ok(())
+}
+
+/// the process group of a process, pid 0 is the current process
+pub fn getpgid(pid: Pid) -> Result<Pid> {
+    Ok(find_or_current(pid)?.pgid())
+}
+
+/// make the current process the leader of a new session and a new process group
+///
+/// process group leaders can't start new sessions
+pub fn setsid() -> Result<Pid> {
+    let this = process();
+    if this.pgid() == this.pid {
+        return Err(Error::PERMISSION_DENIED);
+    }
+
+    this.sid.store(this.pid.num(), Ordering::SeqCst);
+    this.pgid.store(this.pid.num(), Ordering::SeqCst);
+    this.foreground.store(this.pid.num(), Ordering::SeqCst);
+    Ok(this.pid)
+}
+
+/// set the foreground process group of the current session
+pub fn tcsetpgrp(pgid: Pid) -> Result<()> {
+    let sid = process().sid();
+    let leader = session_leader(sid)?;
+
+    if !group_exists(pgid, sid) {
+        return Err(Error::PERMISSION_DENIED);
+    }
+
+    leader.foreground.store(pgid.num(), Ordering::SeqCst);
+    Ok(())
+}
+
+/// the foreground process group of a session, sid 0 is the current session
+pub fn tcgetpgrp(sid: Pid) -> Result<Pid> {
+    let sid = if sid.num() == 0 { process().sid() } else { sid };
+    let leader = session_leader(sid)?;
+
+    Ok(Pid::new(leader.foreground.load(Ordering::SeqCst)))
+}
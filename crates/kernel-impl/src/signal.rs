@@ -0,0 +1,459 @@
+use alloc::{sync::Arc, vec::Vec};
+use core::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+use hyperion_arch::syscall::{SignalFrame, SyscallRegs};
+use hyperion_scheduler::{
+    futex,
+    lock::Mutex,
+    proc::{processes, Pid, Process},
+    process, ExitCode,
+};
+use hyperion_syscall::{
+    err::{Error, Result},
+    signal::{SigMaskHow, Signal, SignalSet},
+    WaitFlags, WaitStatus,
+};
+
+use crate::{process_ext_with, read_untrusted_mut, read_untrusted_ref, with_proc_ext};
+
+//
+
+/// signals that only [`Signal::CONT`] ends
+const STOP_SIGNALS: SignalSet = SignalSet(
+    1 << Signal::STOP.0 | 1 << Signal::TSTP.0 | 1 << Signal::TTIN.0 | 1 << Signal::TTOU.0,
+);
+
+/// signals that cannot be blocked
+const UNBLOCKABLE: SignalSet = SignalSet(1 << Signal::KILL.0 | 1 << Signal::STOP.0);
+
+//
+
+/// signal state of a process
+pub struct Signals {
+    /// the parent process, notified with [`Signal::CHLD`]
+    parent: Pid,
+    pending: AtomicU64,
+    blocked: AtomicU64,
+    actions: Mutex<[Action; Signal::COUNT]>,
+    /// 1 if the process is stopped, 0 otherwise
+    stopped: AtomicUsize,
+    /// the signal that stopped the process
+    stop_signal: AtomicUsize,
+    /// the latest stop has been returned from [`wait_child`]
+    stop_reported: AtomicBool,
+    /// the parent has been told that this process exited
+    exited: AtomicBool,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Action {
+    /// raw [`hyperion_syscall::signal::SignalHandler`]
+    handler: usize,
+    /// the user space trampoline that calls `handler`
+    entry: usize,
+}
+
+impl Action {
+    const DEFAULT: usize = 0;
+    const IGNORE: usize = 1;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DefaultAction {
+    Terminate,
+    Ignore,
+    Stop,
+    Continue,
+}
+
+impl Signals {
+    pub fn new(parent: Pid) -> Self {
+        Self {
+            parent,
+            pending: AtomicU64::new(0),
+            blocked: AtomicU64::new(0),
+            actions: Mutex::new([Action::default(); Signal::COUNT]),
+            stopped: AtomicUsize::new(0),
+            stop_signal: AtomicUsize::new(0),
+            stop_reported: AtomicBool::new(true),
+            exited: AtomicBool::new(false),
+        }
+    }
+
+    /// inherit the handlers and the blocked mask, but not the pending signals
+    pub fn inherit(&self, from: &Signals) {
+        self.blocked
+            .store(from.blocked.load(Ordering::SeqCst), Ordering::SeqCst);
+        *self.actions.lock() = *from.actions.lock();
+    }
+
+    /// reset the handlers for a new process image, ignored signals stay ignored
+    pub fn exec(&self) {
+        for action in self.actions.lock().iter_mut() {
+            if action.handler != Action::IGNORE {
+                *action = Action::default();
+            }
+        }
+    }
+
+    /// tell the parent that this process has exited, only once
+    pub fn exit(&self) {
+        if !self.exited.swap(true, Ordering::SeqCst) {
+            notify(self.parent, Signal::CHLD);
+        }
+    }
+
+    /// stop every thread of `proc`, the running ones are interrupted
+    /// and the rest stop before they return to user space
+    fn stop(&self, proc: &Process, signal: Signal) {
+        self.stop_signal.store(signal.0, Ordering::SeqCst);
+        self.stop_reported.store(false, Ordering::SeqCst);
+        self.stopped.store(1, Ordering::SeqCst);
+        proc.interrupt_threads();
+        if let Some(parent) = self.parent.find() {
+            parent.child_changed();
+        }
+        notify(self.parent, Signal::CHLD);
+    }
+
+    fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::SeqCst) != 0
+    }
+
+    fn resume(&self) {
+        if self.stopped.swap(0, Ordering::SeqCst) != 0 {
+            futex::wake(&self.stopped, usize::MAX);
+        }
+    }
+
+    fn raise(&self, proc: &Process, signal: Signal) {
+        let bit = 1 << signal.0;
+
+        if signal == Signal::KILL {
+            terminate(proc, signal);
+            self.resume();
+            return;
+        }
+
+        if signal == Signal::CONT {
+            self.pending.fetch_and(!STOP_SIGNALS.0, Ordering::SeqCst);
+            self.resume();
+        } else if STOP_SIGNALS.contains(signal) {
+            self.pending
+                .fetch_and(!(1 << Signal::CONT.0), Ordering::SeqCst);
+        }
+
+        let action = self.actions.lock()[signal.0];
+        let blocked = self.blocked.load(Ordering::SeqCst) & bit != 0;
+        match action.handler {
+            Action::IGNORE => {}
+            Action::DEFAULT if default_action(signal) == DefaultAction::Ignore => {}
+            // the process might never make another syscall, so it is terminated right away
+            Action::DEFAULT if default_action(signal) == DefaultAction::Terminate && !blocked => {
+                terminate(proc, signal);
+                self.resume();
+            }
+            // and stopped right away for the same reason
+            Action::DEFAULT if default_action(signal) == DefaultAction::Stop && !blocked => {
+                self.stop(proc, signal);
+            }
+            _ => {
+                self.pending.fetch_or(bit, Ordering::SeqCst);
+            }
+        }
+    }
+
+    /// take the lowest pending signal that is not blocked
+    fn take_next(&self) -> Option<Signal> {
+        let ready = self.pending.load(Ordering::SeqCst) & !self.blocked.load(Ordering::SeqCst);
+        let signal = SignalSet(ready).first()?;
+        self.pending.fetch_and(!(1 << signal.0), Ordering::SeqCst);
+        Some(signal)
+    }
+
+    /// block the current thread until this process is continued or terminated
+    pub(crate) fn wait_while_stopped(&self, proc: &Process) {
+        while self.is_stopped() && proc.exit_code.get().is_none() {
+            if futex::wait_killable(&self.stopped, 1, None).is_err() {
+                // another thread is killing this one
+                return;
+            }
+        }
+    }
+}
+
+fn default_action(signal: Signal) -> DefaultAction {
+    match signal {
+        Signal::CHLD => DefaultAction::Ignore,
+        Signal::CONT => DefaultAction::Continue,
+        Signal::STOP | Signal::TSTP | Signal::TTIN | Signal::TTOU => DefaultAction::Stop,
+        _ => DefaultAction::Terminate,
+    }
+}
+
+/// the process exits right away on the CPUs running it,
+/// the other threads exit the next time they are scheduled or return from a syscall
+fn terminate(proc: &Process, signal: Signal) {
+    proc.kill(ExitCode::from_signal(signal.0));
+}
+
+//
+
+/// send a signal to a process
+///
+/// signal 0 only checks if the process exists and if it could be signalled,
+/// only root can signal the processes of other users
+pub fn send(pid: Pid, signal: Signal) -> Result<()> {
+    if signal.0 != 0 && !signal.is_valid() {
+        return Err(Error::INVALID_ARGUMENT);
+    }
+
+    let proc = pid.find().ok_or(Error::NO_SUCH_PROCESS)?;
+    if !can_signal(&process(), &proc) {
+        return Err(Error::PERMISSION_DENIED);
+    }
+    if signal.0 == 0 {
+        return Ok(());
+    }
+
+    process_ext_with(&proc).signals.raise(&proc, signal);
+    Ok(())
+}
+
+/// send a signal to every process in a process group that this process can signal
+pub fn send_group(pgid: Pid, signal: Signal) -> Result<()> {
+    if signal.0 != 0 && !signal.is_valid() {
+        return Err(Error::INVALID_ARGUMENT);
+    }
+
+    let mut group: Vec<Arc<Process>> = processes()
+        .into_iter()
+        .filter(|proc| proc.pgid() == pgid && proc.exit_code.get().is_none())
+        .collect();
+    if group.is_empty() {
+        return Err(Error::NO_SUCH_PROCESS);
+    }
+    let this = process();
+    group.retain(|proc| can_signal(&this, proc));
+    if group.is_empty() {
+        return Err(Error::PERMISSION_DENIED);
+    }
+    if signal.0 == 0 {
+        return Ok(());
+    }
+
+    for proc in group {
+        process_ext_with(&proc).signals.raise(&proc, signal);
+    }
+    Ok(())
+}
+
+/// root can signal every process, other users only their own
+fn can_signal(sender: &Process, target: &Process) -> bool {
+    sender.is_root() || sender.uid() == target.uid()
+}
+
+/// send a signal from the kernel, like [`Signal::CHLD`] to the parent, without permission checks
+fn notify(pid: Pid, signal: Signal) {
+    if let Some(proc) = pid.find() {
+        process_ext_with(&proc).signals.raise(&proc, signal);
+    }
+}
+
+/// wait for a child process, or any child if `pid` is `None`, to exit or,
+/// with [`WaitFlags::UNTRACED`], to get stopped
+///
+/// exited children are reaped and each stop is returned only once,
+/// returns `None` with [`WaitFlags::NOHANG`] if no child has changed
+pub fn wait_child(pid: Option<Pid>, flags: WaitFlags) -> Result<Option<(Pid, WaitStatus)>> {
+    let this = process();
+
+    loop {
+        let events = this.child_events.load(Ordering::SeqCst);
+
+        if let Some(changed) = try_wait_child(&this, pid, flags.contains(WaitFlags::UNTRACED))? {
+            return Ok(Some(changed));
+        }
+        if flags.contains(WaitFlags::NOHANG) {
+            return Ok(None);
+        }
+
+        futex::wait_killable(&this.child_events, events, None).map_err(|_| Error::INTERRUPTED)?;
+    }
+}
+
+fn try_wait_child(
+    this: &Process,
+    pid: Option<Pid>,
+    untraced: bool,
+) -> Result<Option<(Pid, WaitStatus)>> {
+    let is_waited = |child: &Pid| pid.is_none_or(|pid| *child == pid);
+
+    let mut children = this.children.lock();
+    let waited: Vec<Pid> = children.keys().copied().filter(is_waited).collect();
+    if waited.is_empty() {
+        return Err(Error::NO_CHILD_PROCESSES);
+    }
+
+    let zombie = children
+        .iter()
+        .filter(|(child, _)| is_waited(child))
+        .find_map(|(child, code)| Some((*child, (*code)?)));
+    if let Some((child, code)) = zombie {
+        children.remove(&child);
+        return Ok(Some((child, exit_status(code))));
+    }
+    drop(children);
+
+    if !untraced {
+        return Ok(None);
+    }
+
+    for child in waited {
+        let Some(proc) = child.find() else {
+            continue;
+        };
+        let signals = &process_ext_with(&proc).signals;
+        if signals.is_stopped() && !signals.stop_reported.swap(true, Ordering::SeqCst) {
+            let signal = Signal(signals.stop_signal.load(Ordering::SeqCst));
+            return Ok(Some((child, WaitStatus::Stopped(signal))));
+        }
+    }
+
+    Ok(None)
+}
+
+fn exit_status(code: ExitCode) -> WaitStatus {
+    if code.is_fatal() {
+        WaitStatus::Signaled(Signal(code.signal))
+    } else {
+        WaitStatus::Exited(code.code)
+    }
+}
+
+/// set the raw signal handler and its trampoline, returns the old raw handler
+pub fn sigaction(signal: Signal, handler: usize, entry: usize) -> Result<usize> {
+    if !signal.is_valid() || (!signal.is_catchable() && handler != Action::DEFAULT) {
+        return Err(Error::INVALID_ARGUMENT);
+    }
+
+    with_proc_ext(|ext| {
+        let old = core::mem::replace(
+            &mut ext.signals.actions.lock()[signal.0],
+            Action { handler, entry },
+        );
+
+        if handler == Action::IGNORE {
+            ext.signals
+                .pending
+                .fetch_and(!(1 << signal.0), Ordering::SeqCst);
+        }
+
+        Ok(old.handler)
+    })
+}
+
+/// change the blocked signal mask, returns the old mask
+pub fn sigprocmask(how: SigMaskHow, set: SignalSet) -> Result<SignalSet> {
+    let set = set.0 & !UNBLOCKABLE.0;
+
+    with_proc_ext(|ext| {
+        let blocked = &ext.signals.blocked;
+        let old = match how {
+            SigMaskHow::BLOCK => blocked.fetch_or(set, Ordering::SeqCst),
+            SigMaskHow::UNBLOCK => blocked.fetch_and(!set, Ordering::SeqCst),
+            SigMaskHow::SET => blocked.swap(set, Ordering::SeqCst),
+            _ => return Err(Error::INVALID_ARGUMENT),
+        };
+        Ok(SignalSet(old))
+    })
+}
+
+/// inherit the signal handlers and the blocked mask of a forked process
+pub fn inherit_signals(parent: &Process) {
+    with_proc_ext(|ext| ext.signals.inherit(&process_ext_with(parent).signals));
+}
+
+/// pending signals of the current process
+pub fn sigpending() -> SignalSet {
+    with_proc_ext(|ext| SignalSet(ext.signals.pending.load(Ordering::SeqCst)))
+}
+
+/// handle pending signals of the current process before returning from a syscall
+///
+/// the registers are changed to enter a signal handler, if there is one
+pub fn deliver(regs: &mut SyscallRegs) {
+    let this = process();
+    let code = deliver_with(&this, regs);
+
+    // exit doesn't return, so `this` has to be dropped first
+    drop(this);
+    if let Some(code) = code {
+        hyperion_scheduler::exit(code);
+    }
+
+    // another thread could be killing the rest of the threads
+    hyperion_scheduler::exit_if_killed();
+}
+
+fn deliver_with(this: &Process, regs: &mut SyscallRegs) -> Option<ExitCode> {
+    let signals = &process_ext_with(this).signals;
+
+    loop {
+        signals.wait_while_stopped(this);
+        if let Some(code) = this.exit_code.get() {
+            return Some(*code);
+        }
+
+        let signal = signals.take_next()?;
+
+        let action = signals.actions.lock()[signal.0];
+        match action.handler {
+            Action::IGNORE => {}
+            Action::DEFAULT => match default_action(signal) {
+                DefaultAction::Terminate => return Some(ExitCode::from_signal(signal.0)),
+                DefaultAction::Stop => signals.stop(this, signal),
+                DefaultAction::Ignore | DefaultAction::Continue => {}
+            },
+            handler => {
+                let blocked = signals.blocked.load(Ordering::SeqCst);
+
+                // no stack space for the signal frame
+                let Some((addr, frame)) = regs
+                    .signal_frame_addr()
+                    .and_then(|addr| Some((addr, read_untrusted_mut::<SignalFrame>(addr).ok()?)))
+                else {
+                    return Some(ExitCode::FATAL_SIGSEGV);
+                };
+                *frame = SignalFrame {
+                    regs: *regs,
+                    blocked,
+                };
+
+                if !regs.enter_signal(action.entry as _, addr, signal.0 as _, handler as _) {
+                    return Some(ExitCode::FATAL_SIGSEGV);
+                }
+                // the signal is blocked while its own handler runs
+                signals.blocked.fetch_or(1 << signal.0, Ordering::SeqCst);
+                return None;
+            }
+        }
+    }
+}
+
+/// return from a signal handler, the registers are restored from the signal frame on the user stack
+pub fn sigreturn(regs: &mut SyscallRegs) {
+    let Ok(&frame) = read_untrusted_ref::<SignalFrame>(regs.user_stack_ptr) else {
+        hyperion_scheduler::exit(ExitCode::FATAL_SIGSEGV);
+    };
+
+    if !regs.restore_signal(&frame.regs) {
+        hyperion_scheduler::exit(ExitCode::FATAL_SIGSEGV);
+    }
+
+    with_proc_ext(|ext| {
+        ext.signals
+            .blocked
+            .store(frame.blocked & !UNBLOCKABLE.0, Ordering::SeqCst);
+    });
+}
@@ -7,40 +7,42 @@ use alloc::{
 use core::{
     fmt::Write,
     ops::Deref,
-    ptr::NonNull,
     sync::atomic::{AtomicUsize, Ordering},
 };
 
-use hyperion_arch::{syscall::SyscallRegs, vmm::NO_FREE};
+use hyperion_arch::syscall::SyscallRegs;
 use hyperion_defer::DeferInit;
 use hyperion_drivers::acpi::hpet::HPET;
+use hyperion_events::readiness;
+use hyperion_futures::block_on_killable;
 use hyperion_instant::Instant;
 use hyperion_kernel_impl::{
-    fd_push, fd_query, fd_query_of, fd_replace, fd_take, read_untrusted_bytes,
-    read_untrusted_bytes_mut, read_untrusted_mut, read_untrusted_ref, read_untrusted_slice,
-    read_untrusted_str, BoundSocket, FileDescData, LocalSocket, SocketInfo, SocketPipe, VFS_ROOT,
+    fd_flags, fd_is_nonblock, fd_push, fd_query, fd_query_of, fd_replace, fd_set_flags, fd_take,
+    read_untrusted_bytes, read_untrusted_bytes_mut, read_untrusted_mut, read_untrusted_ref,
+    read_untrusted_slice, read_untrusted_slice_mut, read_untrusted_str, Access, BoundSocket,
+    DirDescData, FileDescData, LocalSocket, SocketConnection, SocketInfo, VFS_ROOT,
 };
 use hyperion_log::*;
-use hyperion_mem::{
-    pmm::PageFrame,
-    vmm::{MapTarget, PageMapImpl},
-};
+use hyperion_mem::{pmm::PageFrame, vmm::MapTarget};
 use hyperion_scheduler::{
-    futex,
+    futex::{self, WaitError},
     lock::Mutex,
-    proc::{AllocErr, FreeErr, Pid},
+    proc::Pid,
     process, task, ExitCode,
 };
 use hyperion_syscall::{
     err::{Error, Result},
-    fs::{FileDesc, FileOpenFlags, Metadata, Seek},
+    fs::{Fcntl, FileDesc, FileOpenFlags, Metadata, PollEvents, PollFd, Seek, UnlinkFlags},
     id,
+    limit::{Limit, Resource},
+    mem::{MapFlags, Prot},
     net::{Protocol, SocketDomain, SocketType},
-    LaunchConfig,
+    signal::{SigMaskHow, Signal, SignalSet},
+    EnvList, LaunchConfig, Timeout, WaitFlags, WAIT_ANY,
 };
 use hyperion_vfs::{path::Path, ramdisk, tree::Node};
 use time::Duration;
-use x86_64::{align_down, align_up, structures::paging::PageTableFlags, VirtAddr};
+use x86_64::VirtAddr;
 
 //
 
@@ -88,6 +90,38 @@ pub fn syscall(args: &mut SyscallRegs) {
         id::LISTEN => call_id(listen, args),
         id::ACCEPT => call_id(accept, args),
         id::CONNECT => call_id(connect, args),
+        id::SENDTO => call_id(sendto, args),
+        id::RECVFROM => call_id(recvfrom, args),
+        id::SENDMSG => call_id(sendmsg, args),
+        id::RECVMSG => call_id(recvmsg, args),
+        id::POLL => call_id(poll, args),
+        id::FCNTL => call_id(fcntl, args),
+        id::MMAP => call_id(mmap, args),
+        id::MUNMAP => call_id(munmap, args),
+        id::MPROTECT => call_id(mprotect, args),
+        id::KILL => call_id(kill, args),
+        id::SIGACTION => call_id(sigaction, args),
+        id::SIGPROCMASK => call_id(sigprocmask, args),
+        id::SIGRETURN => call_id(sigreturn, args),
+        id::SETPGID => call_id(setpgid, args),
+        id::GETPGID => call_id(getpgid, args),
+        id::SETSID => call_id(setsid, args),
+        id::TCSETPGRP => call_id(tcsetpgrp, args),
+        id::TCGETPGRP => call_id(tcgetpgrp, args),
+        id::GETRLIMIT => call_id(getrlimit, args),
+        id::SETRLIMIT => call_id(setrlimit, args),
+
+        id::GETUID => call_id(getuid, args),
+        id::SETUID => call_id(setuid, args),
+        id::GETGID => call_id(getgid, args),
+        id::SETGID => call_id(setgid, args),
+        id::CHMOD => call_id(chmod, args),
+        id::CHOWN => call_id(chown, args),
+
+        id::SET_PRIORITY => call_id(set_priority, args),
+        id::GET_PRIORITY => call_id(get_priority, args),
+        id::SCHED_SETAFFINITY => call_id(sched_setaffinity, args),
+        id::SCHED_GETAFFINITY => call_id(sched_getaffinity, args),
 
         id::GET_PID => call_id(get_pid, args),
         id::GET_TID => call_id(get_tid, args),
@@ -96,6 +130,7 @@ pub fn syscall(args: &mut SyscallRegs) {
         id::PIPE => call_id(pipe, args),
         id::FUTEX_WAIT => call_id(futex_wait, args),
         id::FUTEX_WAKE => call_id(futex_wake, args),
+        id::FUTEX_REQUEUE => call_id(futex_requeue, args),
 
         id::MAP_FILE => call_id(map_file, args),
         id::UNMAP_FILE => call_id(unmap_file, args),
@@ -105,12 +140,24 @@ pub fn syscall(args: &mut SyscallRegs) {
         id::SYSTEM => call_id(system, args),
         id::FORK => call_id(fork, args),
         id::WAITPID => call_id(waitpid, args),
+        id::EXEC => call_id(exec, args),
+
+        id::CHDIR => call_id(chdir, args),
+        id::GETCWD => call_id(getcwd, args),
+
+        id::UNLINK => call_id(unlink, args),
+        id::RENAME_PATH => call_id(rename_path, args),
+        id::STAT => call_id(stat, args),
+        id::READ_DIR => call_id(read_dir, args),
 
         other => {
             debug!("invalid syscall ({other})");
             hyperion_scheduler::exit(ExitCode::INVALID_SYSCALL);
         }
     };
+
+    // pending signals are handled when returning to user space
+    hyperion_kernel_impl::deliver(args);
 }
 
 fn call_id(f: impl FnOnce(&mut SyscallRegs) -> Result<usize>, args: &mut SyscallRegs) {
@@ -151,7 +198,7 @@ fn _log(str: &str) {
 ///
 /// [`hyperion_syscall::exit`]
 pub fn exit(args: &mut SyscallRegs) -> Result<usize> {
-    let code = ExitCode(args.arg0 as _);
+    let code = ExitCode::new(args.arg0 as _);
     hyperion_scheduler::exit(code);
 }
 
@@ -204,13 +251,17 @@ fn _timestamp(bytes: &mut [u8]) {
 pub fn nanosleep(args: &mut SyscallRegs) -> Result<usize> {
     let nanos = args.arg0 as i64;
 
-    _nanosleep(nanos);
+    _nanosleep(nanos)?;
     return Ok(0);
 }
 
 // #[trace]
-fn _nanosleep(nanos: i64) {
-    hyperion_scheduler::sleep(Duration::nanoseconds(nanos.max(0)));
+fn _nanosleep(nanos: i64) -> Result<()> {
+    _nanosleep_until(
+        Instant::now()
+            .nanosecond()
+            .saturating_add(nanos.max(0) as u128),
+    )
 }
 
 /// sleep at least until the nanosecond arg0 happens
@@ -219,13 +270,14 @@ fn _nanosleep(nanos: i64) {
 pub fn nanosleep_until(args: &mut SyscallRegs) -> Result<usize> {
     let nanosecond = args.arg0 as u128;
 
-    _nanosleep_until(nanosecond);
+    _nanosleep_until(nanosecond)?;
     return Ok(0);
 }
 
 // #[trace]
-fn _nanosleep_until(nanosecond: u128) {
-    hyperion_scheduler::sleep_until(Instant::new(nanosecond));
+fn _nanosleep_until(nanosecond: u128) -> Result<()> {
+    hyperion_scheduler::sleep_until_killable(Instant::new(nanosecond))
+        .map_err(|_| Error::INTERRUPTED)
 }
 
 /// spawn a new thread
@@ -234,13 +286,20 @@ fn _nanosleep_until(nanosecond: u128) {
 ///
 /// [`hyperion_syscall::spawn`]
 pub fn spawn(args: &mut SyscallRegs) -> Result<usize> {
-    _spawn(args.arg0, args.arg1);
+    _spawn(args.arg0, args.arg1)?;
     return Ok(0);
 }
 
 // #[trace]
-fn _spawn(fn_ptr: u64, fn_arg: u64) {
+fn _spawn(fn_ptr: u64, fn_arg: u64) -> Result<()> {
+    let this = process();
+    if this.threads.load(Ordering::SeqCst) as u64 >= this.limit(Resource::THREADS) {
+        return Err(Error::LIMIT_EXCEEDED);
+    }
+    drop(this);
+
     hyperion_scheduler::spawn_userspace(fn_ptr, fn_arg);
+    Ok(())
 }
 
 /// allocate physical pages and map them to virtual memory
@@ -255,12 +314,7 @@ pub fn palloc(args: &mut SyscallRegs) -> Result<usize> {
 
 // #[trace]
 fn _palloc(n_pages: usize) -> Result<VirtAddr> {
-    let flags = PageTableFlags::WRITABLE | PageTableFlags::USER_ACCESSIBLE;
-
-    match process().alloc(n_pages, flags) {
-        Ok(ptr) => Ok(ptr),
-        Err(AllocErr::OutOfVirtMem) => Err(Error::OUT_OF_VIRTUAL_MEMORY),
-    }
+    hyperion_kernel_impl::palloc(n_pages)
 }
 
 /// free allocated physical pages
@@ -278,11 +332,7 @@ pub fn pfree(args: &mut SyscallRegs) -> Result<usize> {
 
 // #[trace]
 fn _pfree(ptr: VirtAddr, n_pages: usize) -> Result<()> {
-    match process().free(n_pages, ptr) {
-        Ok(()) => Ok(()),
-        Err(FreeErr::InvalidAddr) => Err(Error::INVALID_ADDRESS),
-        Err(FreeErr::InvalidAlloc) => Err(Error::INVALID_ALLOC),
-    }
+    hyperion_kernel_impl::pfree(ptr, n_pages)
 }
 
 /// rename the current process
@@ -309,7 +359,8 @@ pub fn open(args: &mut SyscallRegs) -> Result<usize> {
         return Err(Error::INVALID_FLAGS);
     };
 
-    let fd = _open(path, flags)?;
+    let path = hyperion_kernel_impl::to_absolute(path);
+    let fd = _open(path.as_str(), flags)?;
     return Ok(fd.0);
 }
 
@@ -318,11 +369,24 @@ fn _open(path: &str, flags: FileOpenFlags) -> Result<FileDesc> {
     let create = flags.contains(FileOpenFlags::CREATE) || flags.contains(FileOpenFlags::CREATE_NEW);
     let create_dirs = flags.contains(FileOpenFlags::CREATE_DIRS);
 
-    if flags.contains(FileOpenFlags::IS_DIR) {
-        _open_dir(path, flags, create, create_dirs)
-    } else {
-        _open_file(path, flags, create, create_dirs)
+    // permissions are checked before anything gets created
+    match VFS_ROOT.find(path, false) {
+        Ok(node) => hyperion_kernel_impl::check_node(&node, Access::from_open_flags(flags))?,
+        Err(Error::NOT_FOUND) if create || create_dirs => hyperion_kernel_impl::check_create(path)?,
+        // the actual open reports the error
+        Err(_) => {}
     }
+
+    let fd = if flags.contains(FileOpenFlags::IS_DIR) {
+        _open_dir(path, flags, create, create_dirs)?
+    } else {
+        _open_file(path, flags, create, create_dirs)?
+    };
+
+    // CLOEXEC and NONBLOCK
+    fd_set_flags(fd, flags);
+
+    return Ok(fd);
 }
 
 fn _open_dir(
@@ -336,14 +400,12 @@ fn _open_dir(
         return Err(Error::INVALID_FLAGS);
     }
 
-    let mut dir = VFS_ROOT
-        .find_dir(path, create_dirs, create)? // TODO: mkdir
-        .lock_arc();
+    let dir_ref = VFS_ROOT.find_dir(path, create_dirs, create)?; // TODO: mkdir
+    let mut dir = dir_ref.lock();
 
-    let s = dir.nodes()?;
-
-    let mut buf = String::new(); // TODO: real readdir
-    for entry in s.into_iter() {
+    // the legacy text format for plain reads, `read_dir` should be used instead
+    let mut buf = String::new();
+    for entry in dir.nodes()? {
         let name = entry.name.deref();
         let node = entry.node;
 
@@ -354,12 +416,16 @@ fn _open_dir(
 
         writeln!(&mut buf, "{name} {size} {mode}").unwrap();
     }
+    drop(dir);
 
-    let fd = fd_push(Arc::new(FileDescData {
-        file_ref: Arc::new(Mutex::new(ramdisk::File::new(buf.as_bytes()))),
-        position: AtomicUsize::new(0),
-        mapped: Mutex::new(None),
-    }));
+    let fd = fd_push(Arc::new(DirDescData {
+        dir_ref,
+        entry: AtomicUsize::new(0),
+        listing: FileDescData {
+            file_ref: Arc::new(Mutex::new(ramdisk::File::new(buf.as_bytes()))),
+            position: AtomicUsize::new(0),
+        },
+    }))?;
 
     return Ok(fd);
 }
@@ -397,7 +463,7 @@ fn _open_file(
 
     drop(file_lock);
 
-    return Ok(fd_push(Arc::new(FileDescData::new(file_ref, position))));
+    return fd_push(Arc::new(FileDescData::new(file_ref, position)));
 }
 
 /// close a file
@@ -431,6 +497,9 @@ pub fn read(args: &mut SyscallRegs) -> Result<usize> {
 // #[trace]
 fn _read(fd: FileDesc, buf: &mut [u8]) -> Result<usize> {
     let file = fd_query(fd)?;
+    if fd_is_nonblock(fd) {
+        return file.try_read(buf);
+    }
     return file.read(buf);
 }
 
@@ -446,9 +515,38 @@ pub fn write(args: &mut SyscallRegs) -> Result<usize> {
 
 fn _write(fd: FileDesc, buf: &[u8]) -> Result<usize> {
     let file = fd_query(fd)?;
+    if fd_is_nonblock(fd) {
+        return file.try_write(buf);
+    }
     return file.write(buf);
 }
 
+/// get or set file descriptor flags
+///
+/// [`hyperion_syscall::fcntl`]
+pub fn fcntl(args: &mut SyscallRegs) -> Result<usize> {
+    let fd = FileDesc(args.arg0 as _);
+    let cmd = Fcntl(args.arg1 as _);
+    let arg = args.arg2 as usize;
+
+    _fcntl(fd, cmd, arg)
+}
+
+fn _fcntl(fd: FileDesc, cmd: Fcntl, arg: usize) -> Result<usize> {
+    // validate the fd
+    fd_query(fd)?;
+
+    match cmd {
+        Fcntl::GET_FLAGS => Ok(fd_flags(fd).bits()),
+        Fcntl::SET_FLAGS => {
+            let flags = FileOpenFlags::from_bits(arg).ok_or(Error::INVALID_FLAGS)?;
+            fd_set_flags(fd, flags);
+            Ok(0)
+        }
+        _ => Err(Error::INVALID_ARGUMENT),
+    }
+}
+
 /// create a socket
 ///
 /// [`hyperion_syscall::socket`]
@@ -466,7 +564,7 @@ fn _socket(info: SocketInfo) -> Result<FileDesc> {
         return Err(Error::INVALID_DOMAIN);
     }
 
-    if info.ty != SocketType::STREAM {
+    if ![SocketType::STREAM, SocketType::DGRAM, SocketType::SEQPACKET].contains(&info.ty) {
         return Err(Error::INVALID_TYPE);
     }
 
@@ -474,7 +572,7 @@ fn _socket(info: SocketInfo) -> Result<FileDesc> {
         return Err(Error::UNKNOWN_PROTOCOL);
     }
 
-    Ok(fd_push(Arc::new(LocalSocket::new(info))))
+    fd_push(Arc::new(LocalSocket::new(info)))
 }
 
 /// bind a socket
@@ -484,7 +582,8 @@ fn bind(args: &mut SyscallRegs) -> Result<usize> {
     let socket_fd = FileDesc(args.arg0 as _);
     let addr = read_untrusted_str(args.arg1, args.arg2)?;
 
-    return _bind(socket_fd, addr).map(|_| 0);
+    let addr = hyperion_kernel_impl::to_absolute(addr);
+    return _bind(socket_fd, addr.as_str()).map(|_| 0);
 }
 
 fn _bind(socket_fd: FileDesc, addr: &str) -> Result<()> {
@@ -494,6 +593,13 @@ fn _bind(socket_fd: FileDesc, addr: &str) -> Result<()> {
 
     let socket = fd_query_of::<LocalSocket>(socket_fd)?;
 
+    if socket.addr.is_completed() {
+        // already bound
+        return Err(Error::INVALID_ARGUMENT);
+    }
+
+    hyperion_kernel_impl::check_create(addr)?;
+
     VFS_ROOT
         // find the directory node
         .find_dir(dir, false, true)?
@@ -502,9 +608,11 @@ fn _bind(socket_fd: FileDesc, addr: &str) -> Result<()> {
         // create the socket file in that directory
         .create_node(
             sock_file,
-            Node::File(Arc::new(Mutex::new(BoundSocket(socket)))),
+            Node::File(Arc::new(Mutex::new(BoundSocket::new(&socket)))),
         )?;
 
+    socket.addr.call_once(|| addr.into());
+
     return Ok(());
 }
 
@@ -538,12 +646,19 @@ fn _accept(socket_fd: FileDesc) -> Result<FileDesc> {
     // `listen` syscall is not required
     let incoming = socket.listener()?;
 
-    // blocks here
-    let pipe = incoming
-        .recv()
-        .expect("local socket listener send end should never close");
+    let conn = if fd_is_nonblock(socket_fd) {
+        incoming
+            .try_recv()
+            .expect("local socket listener send end should never close")
+            .ok_or(Error::WOULD_BLOCK)?
+    } else {
+        // blocks here
+        incoming
+            .recv()
+            .expect("local socket listener send end should never close")
+    };
 
-    Ok(fd_push(Arc::new(LocalSocket::connected(socket.info, pipe))))
+    fd_push(Arc::new(LocalSocket::connected(socket.info, conn)))
 }
 
 /// connect to a socket
@@ -553,28 +668,31 @@ fn connect(args: &mut SyscallRegs) -> Result<usize> {
     let socket = FileDesc(args.arg0 as _);
     let addr = read_untrusted_str(args.arg1, args.arg2)?;
 
-    _connect(socket, addr).map(|_| 0)
+    let addr = hyperion_kernel_impl::to_absolute(addr);
+    _connect(socket, addr.as_str()).map(|_| 0)
 }
 
 fn _connect(socket_fd: FileDesc, addr: &str) -> Result<()> {
     let client = fd_query_of::<LocalSocket>(socket_fd)?;
+    let server = find_bound_socket(addr)?;
 
-    let server = VFS_ROOT
-        // TODO: inode
-        .find_file(addr, false, false)?
-        .lock_arc()
-        .as_any()
-        .downcast_ref::<BoundSocket>()
-        .ok_or(Error::CONNECTION_REFUSED)?
-        .0
-        .clone();
+    if client.info.ty != server.info.ty {
+        return Err(Error::CONNECTION_REFUSED);
+    }
+
+    if client.info.ty == SocketType::DGRAM {
+        // only sets the default destination
+        let (_, peer) = client.datagram()?;
+        peer.call_once(|| Arc::downgrade(&server));
+        return Ok(());
+    }
 
     if !client.is_uninit() {
         return Err(Error::INVALID_ARGUMENT);
     }
     let listener = server.listener()?;
 
-    let (conn_client, conn_server) = SocketPipe::new();
+    let (conn_client, conn_server) = SocketConnection::new(client.info.ty)?;
     client.connection(conn_client)?;
     listener
         .send(conn_server)
@@ -583,6 +701,166 @@ fn _connect(socket_fd: FileDesc, addr: &str) -> Result<()> {
     Ok(())
 }
 
+fn find_bound_socket(addr: &str) -> Result<Arc<LocalSocket>> {
+    let socket = VFS_ROOT
+        // TODO: inode
+        .find_file(addr, false, false)?
+        .lock_arc();
+
+    // sending to a socket needs write access to the socket file
+    hyperion_kernel_impl::check_access(&socket.metadata(), Access::WRITE)?;
+
+    let socket = socket
+        .as_any()
+        .downcast_ref::<BoundSocket>()
+        .ok_or(Error::CONNECTION_REFUSED)?
+        .0
+        .upgrade()
+        // bound, but already closed
+        .ok_or(Error::CONNECTION_REFUSED)?;
+
+    Ok(socket)
+}
+
+/// send a message to a bound socket
+///
+/// [`hyperion_syscall::sendto`]
+pub fn sendto(args: &mut SyscallRegs) -> Result<usize> {
+    let socket = FileDesc(args.arg0 as _);
+    let buf = read_untrusted_bytes(args.arg1, args.arg2)?;
+    let addr = read_untrusted_str(args.arg3, args.arg4)?;
+
+    let addr = hyperion_kernel_impl::to_absolute(addr);
+    _sendto(socket, buf, addr.as_str())
+}
+
+fn _sendto(socket_fd: FileDesc, buf: &[u8], addr: &str) -> Result<usize> {
+    let socket = fd_query_of::<LocalSocket>(socket_fd)?;
+    let target = find_bound_socket(addr)?;
+
+    socket.send_to(target, buf, fd_is_nonblock(socket_fd))
+}
+
+/// receive a message and the address of the sender
+///
+/// [`hyperion_syscall::recvfrom`]
+pub fn recvfrom(args: &mut SyscallRegs) -> Result<usize> {
+    let socket = FileDesc(args.arg0 as _);
+    let buf = read_untrusted_bytes_mut(args.arg1, args.arg2)?;
+    let addr = read_untrusted_bytes_mut(args.arg3, args.arg4)?;
+
+    _recvfrom(socket, buf, addr)
+}
+
+fn _recvfrom(socket_fd: FileDesc, buf: &mut [u8], addr: &mut [u8]) -> Result<usize> {
+    let socket = fd_query_of::<LocalSocket>(socket_fd)?;
+    let nonblock = fd_is_nonblock(socket_fd);
+
+    let (len, from) = if socket.info.ty == SocketType::DGRAM {
+        socket.recv_from(buf, nonblock)?
+    } else {
+        // connected sockets have no sender address
+        (socket.recv_msg(buf, 0, nonblock)?.0, None)
+    };
+
+    addr.fill(0);
+    if let Some(from) = from {
+        let n = from.len().min(addr.len());
+        addr[..n].copy_from_slice(&from.as_bytes()[..n]);
+    }
+
+    Ok(len)
+}
+
+/// send a message and file descriptors to the connected peer
+///
+/// [`hyperion_syscall::sendmsg`]
+pub fn sendmsg(args: &mut SyscallRegs) -> Result<usize> {
+    let socket = FileDesc(args.arg0 as _);
+    let buf = read_untrusted_bytes(args.arg1, args.arg2)?;
+    let fds = read_untrusted_slice_mut::<FileDesc>(args.arg3, args.arg4)?;
+
+    _sendmsg(socket, buf, fds)
+}
+
+fn _sendmsg(socket_fd: FileDesc, buf: &[u8], fds: &[FileDesc]) -> Result<usize> {
+    let socket = fd_query_of::<LocalSocket>(socket_fd)?;
+    let fds = fds
+        .iter()
+        .map(|fd| fd_query(*fd))
+        .collect::<Result<Vec<_>>>()?;
+
+    socket.send_msg(buf, fds, fd_is_nonblock(socket_fd))
+}
+
+/// receive a message and file descriptors from the connected peer
+///
+/// [`hyperion_syscall::recvmsg`]
+pub fn recvmsg(args: &mut SyscallRegs) -> Result<usize> {
+    let socket = FileDesc(args.arg0 as _);
+    let buf = read_untrusted_bytes_mut(args.arg1, args.arg2)?;
+    let fds = read_untrusted_slice_mut::<FileDesc>(args.arg3, args.arg4)?;
+
+    _recvmsg(socket, buf, fds)
+}
+
+fn _recvmsg(socket_fd: FileDesc, buf: &mut [u8], fds: &mut [FileDesc]) -> Result<usize> {
+    let socket = fd_query_of::<LocalSocket>(socket_fd)?;
+
+    let (len, received) = socket.recv_msg(buf, fds.len(), fd_is_nonblock(socket_fd))?;
+
+    // files over the open files limit are dropped
+    fds.fill(FileDesc::NONE);
+    for (slot, file) in fds.iter_mut().zip(received) {
+        *slot = fd_push(file).unwrap_or(FileDesc::NONE);
+    }
+
+    Ok(len)
+}
+
+/// wait until any of the file descriptors is ready
+///
+/// [`hyperion_syscall::poll`]
+pub fn poll(args: &mut SyscallRegs) -> Result<usize> {
+    let fds = read_untrusted_slice_mut::<PollFd>(args.arg0, args.arg1)?;
+    let timeout = args.arg2 as usize;
+
+    let deadline = (timeout != usize::MAX)
+        .then(|| Instant::now() + Duration::nanoseconds(timeout.min(i64::MAX as usize) as i64));
+
+    _poll(fds, deadline)
+}
+
+fn _poll(fds: &mut [PollFd], deadline: Option<Instant>) -> Result<usize> {
+    loop {
+        let mut listeners = Vec::new();
+
+        let mut ready = 0;
+        for poll_fd in fds.iter_mut() {
+            poll_fd.revents = match fd_query(poll_fd.fd) {
+                Ok(file) => {
+                    // listen before checking, so that no readiness changes are missed
+                    file.listen(&mut listeners);
+                    // HUP and NVAL are always reported
+                    file.poll() & (poll_fd.events | PollEvents::HUP)
+                }
+                Err(_) => PollEvents::NVAL,
+            };
+
+            if !poll_fd.revents.is_empty() {
+                ready += 1;
+            }
+        }
+
+        if ready != 0 || deadline.is_some_and(|deadline| deadline.is_reached()) {
+            return Ok(ready);
+        }
+
+        block_on_killable(readiness::changed(listeners, deadline))
+            .map_err(|_| Error::INTERRUPTED)?;
+    }
+}
+
 /// send data to a socket
 ///
 /// [`hyperion_syscall::send`]
@@ -595,8 +873,7 @@ pub fn send(args: &mut SyscallRegs) -> Result<usize> {
 }
 
 fn _send(socket_fd: FileDesc, buf: &[u8], _flags: usize) -> Result<usize> {
-    let socket = fd_query(socket_fd)?;
-    socket.write(buf)
+    _write(socket_fd, buf)
 }
 
 /// recv data from a socket
@@ -613,8 +890,7 @@ pub fn recv(args: &mut SyscallRegs) -> Result<usize> {
 }
 
 fn _recv(socket_fd: FileDesc, buf: &mut [u8], _flags: usize) -> Result<usize> {
-    let socket = fd_query(socket_fd)?;
-    socket.read(buf)
+    _read(socket_fd, buf)
 }
 
 /// pid of the current process
@@ -639,6 +915,9 @@ pub fn dup(args: &mut SyscallRegs) -> Result<usize> {
     let new = FileDesc(args.arg1 as _);
 
     let old = fd_query(old)?;
+    if new.0 as u64 >= process().limit(Resource::OPEN_FILES) {
+        return Err(Error::BAD_FILE_DESCRIPTOR);
+    }
     fd_replace(new, old);
 
     Ok(new.0 as _)
@@ -651,8 +930,12 @@ pub fn pipe(args: &mut SyscallRegs) -> Result<usize> {
     let [read, write]: &mut [FileDesc; 2] = read_untrusted_mut(args.arg0)?;
 
     let (send, recv) = hyperion_scheduler::ipc::pipe::pipe().split();
-    *write = fd_push(Arc::new(send));
-    *read = fd_push(Arc::new(recv));
+    let write_fd = fd_push(Arc::new(send))?;
+    let read_fd = fd_push(Arc::new(recv)).inspect_err(|_| {
+        fd_take(write_fd);
+    })?;
+    *write = write_fd;
+    *read = read_fd;
 
     Ok(0)
 }
@@ -663,12 +946,28 @@ pub fn pipe(args: &mut SyscallRegs) -> Result<usize> {
 pub fn futex_wait(args: &mut SyscallRegs) -> Result<usize> {
     let addr = read_untrusted_ref::<AtomicUsize>(args.arg0)?;
     let val = args.arg1 as usize;
+    let timeout =
+        Timeout::from_raw(args.arg2 as _, args.arg3 as _).ok_or(Error::INVALID_ARGUMENT)?;
 
-    futex::wait(addr, val);
-
+    _futex_wait(addr, val, timeout)?;
     return Ok(0);
 }
 
+fn _futex_wait(addr: &AtomicUsize, val: usize, timeout: Timeout) -> Result<()> {
+    let deadline = match timeout {
+        Timeout::Never => None,
+        Timeout::After(nanos) => Some(Instant::new(
+            Instant::now().nanosecond().saturating_add(nanos as u128),
+        )),
+        Timeout::At(nanos) => Some(Instant::new(nanos as u128)),
+    };
+
+    futex::wait_killable(addr, val, deadline).map_err(|err| match err {
+        WaitError::TimedOut => Error::TIMED_OUT,
+        WaitError::Killed => Error::INTERRUPTED,
+    })
+}
+
 /// futex wake
 ///
 /// [`hyperion_syscall::futex_wake`]
@@ -681,44 +980,42 @@ pub fn futex_wake(args: &mut SyscallRegs) -> Result<usize> {
     return Ok(0);
 }
 
+/// futex requeue
+///
+/// [`hyperion_syscall::futex_requeue`]
+pub fn futex_requeue(args: &mut SyscallRegs) -> Result<usize> {
+    let addr = read_untrusted_ref::<AtomicUsize>(args.arg0)?;
+    let num_wake = args.arg1 as usize;
+    let to = read_untrusted_ref::<AtomicUsize>(args.arg2)?;
+    let num_requeue = args.arg3 as usize;
+
+    futex::requeue(addr, num_wake, to, num_requeue);
+
+    return Ok(0);
+}
+
 /// map file to memory
 ///
 /// [`hyperion_syscall::map_file`]
 pub fn map_file(args: &mut SyscallRegs) -> Result<usize> {
     let fd = FileDesc(args.arg0 as _);
-    let at = NonNull::new(args.arg1 as *mut ());
+    let at = read_map_addr(args.arg1)?;
     let size = args.arg2 as usize;
     let offset = args.arg3 as usize;
 
-    if let Some(at) = at {
-        error!("handle map_file at {at:?}");
-    }
-    if offset != 0 {
-        error!("handle map_file offset {offset:?}");
-    }
-
-    let file = fd_query_of::<FileDescData>(fd)?;
-    let mut file_ref = file.file_ref.lock_arc();
-    let this = process();
-
-    let size = align_up(size as _, 0x1000);
-    let bottom = VirtAddr::try_new(align_down(
-        this.heap_bottom.fetch_add(size as usize, Ordering::SeqCst) as u64,
-        0x1000,
-    ))
-    .map_err(|_| Error::OUT_OF_VIRTUAL_MEMORY)?;
-    let top = VirtAddr::try_new(bottom.as_u64() + size) //
-        .map_err(|_| Error::OUT_OF_VIRTUAL_MEMORY)?;
-
-    file_ref.map_phys(
-        &this.address_space.page_map,
-        bottom..top,
-        PageTableFlags::WRITABLE | PageTableFlags::USER_ACCESSIBLE | NO_FREE, // FIXME: the file already does NO_FREE, because of borrow target
-    )?;
-
-    *file.mapped.lock() = Some(bottom..top);
-
-    Ok(bottom.as_u64() as usize)
+    // the mapping starts from the page that has the offset
+    let page_offset = offset & 0xfff;
+    let at = at.map(|at| at.align_down(0x1000u64));
+
+    _mmap(
+        at,
+        size + page_offset,
+        Prot::READ | Prot::WRITE,
+        MapFlags::SHARED,
+        fd,
+        offset - page_offset,
+    )
+    .map(|ptr| ptr.as_u64() as usize)
 }
 
 /// unmap file from memory
@@ -726,26 +1023,164 @@ pub fn map_file(args: &mut SyscallRegs) -> Result<usize> {
 /// [`hyperion_syscall::unmap_file`]
 pub fn unmap_file(args: &mut SyscallRegs) -> Result<usize> {
     let fd = FileDesc(args.arg0 as _);
-    // let at = NonNull::new(args.arg1 as *mut ());
-    // let size = args.arg2 as usize;
+    let at = VirtAddr::try_new(args.arg1).map_err(|_| Error::INVALID_ADDRESS)?;
+    let size = args.arg2 as usize;
 
-    // hyperion_log::debug!("unmap_file({file:?}, {at:?}, {size})");
+    // size 0 unmaps the whole file
+    let file = fd_query_of::<FileDescData>(fd)?;
+    let size = if size == 0 {
+        file.file_ref.lock().len()
+    } else {
+        size
+    };
 
-    // FIXME: general unmap like munmap in linux
+    _munmap(at, size)?;
+    Ok(0)
+}
 
-    let file = fd_query_of::<FileDescData>(fd)?;
+/// map anonymous memory or a file to memory
+///
+/// [`hyperion_syscall::mmap`]
+pub fn mmap(args: &mut SyscallRegs) -> Result<usize> {
+    let at = read_map_addr(args.arg0)?;
+    let size = args.arg1 as usize;
+    let (flags, prot) = MapFlags::unpack(args.arg2 as usize);
+    let fd = FileDesc(args.arg3 as _);
+    let offset = args.arg4 as usize;
+
+    _mmap(at, size, prot, flags, fd, offset).map(|ptr| ptr.as_u64() as usize)
+}
 
-    let Some(mapping) = file.mapped.lock().take() else {
-        return Err(Error::BAD_FILE_DESCRIPTOR);
+fn _mmap(
+    at: Option<VirtAddr>,
+    size: usize,
+    prot: Prot,
+    flags: MapFlags,
+    fd: FileDesc,
+    offset: usize,
+) -> Result<VirtAddr> {
+    if !MapFlags::all().contains(flags) || !Prot::all().contains(prot) {
+        return Err(Error::INVALID_FLAGS);
+    }
+
+    let file = if flags.contains(MapFlags::ANONYMOUS) {
+        None
+    } else {
+        let file_ref = fd_query_of::<FileDescData>(fd)?.file_ref.clone();
+
+        // writes to shared mappings go to the file
+        let access = if flags.contains(MapFlags::SHARED) && prot.contains(Prot::WRITE) {
+            Access::READ_WRITE
+        } else {
+            Access::READ
+        };
+        hyperion_kernel_impl::check_access(&file_ref.lock().metadata(), access)?;
+
+        Some(file_ref)
     };
 
-    process().address_space.page_map.unmap(mapping);
+    hyperion_kernel_impl::mmap(at, size, prot, flags, file, offset)
+}
+
+/// unmap memory
+///
+/// [`hyperion_syscall::munmap`]
+pub fn munmap(args: &mut SyscallRegs) -> Result<usize> {
+    let at = VirtAddr::try_new(args.arg0).map_err(|_| Error::INVALID_ADDRESS)?;
+    let size = args.arg1 as usize;
 
-    file.file_ref.lock_arc().unmap_phys()?;
+    _munmap(at, size)?;
+    Ok(0)
+}
 
+fn _munmap(at: VirtAddr, size: usize) -> Result<()> {
+    hyperion_kernel_impl::munmap(at, size)
+}
+
+/// change memory page protections
+///
+/// [`hyperion_syscall::mprotect`]
+pub fn mprotect(args: &mut SyscallRegs) -> Result<usize> {
+    let at = VirtAddr::try_new(args.arg0).map_err(|_| Error::INVALID_ADDRESS)?;
+    let size = args.arg1 as usize;
+    let prot = u8::try_from(args.arg2)
+        .ok()
+        .and_then(Prot::from_bits)
+        .ok_or(Error::INVALID_FLAGS)?;
+
+    _mprotect(at, size, prot)?;
     Ok(0)
 }
 
+fn _mprotect(at: VirtAddr, size: usize, prot: Prot) -> Result<()> {
+    hyperion_kernel_impl::mprotect(at, size, prot)
+}
+
+/// null is no address
+fn read_map_addr(addr: u64) -> Result<Option<VirtAddr>> {
+    if addr == 0 {
+        return Ok(None);
+    }
+    VirtAddr::try_new(addr)
+        .map(Some)
+        .map_err(|_| Error::INVALID_ADDRESS)
+}
+
+/// send a signal to a process
+///
+/// [`hyperion_syscall::kill`]
+pub fn kill(args: &mut SyscallRegs) -> Result<usize> {
+    let pid = args.arg0 as isize;
+    let signal = Signal(args.arg1 as _);
+
+    // negative pids are process groups
+    if pid < 0 {
+        hyperion_kernel_impl::send_group(Pid::new(pid.unsigned_abs()), signal)?;
+    } else {
+        _kill(Pid::new(pid as _), signal)?;
+    }
+    Ok(0)
+}
+
+fn _kill(pid: Pid, signal: Signal) -> Result<()> {
+    hyperion_kernel_impl::send(pid, signal)
+}
+
+/// set a signal handler
+///
+/// [`hyperion_syscall::sigaction`]
+pub fn sigaction(args: &mut SyscallRegs) -> Result<usize> {
+    let signal = Signal(args.arg0 as _);
+    let handler = args.arg1 as usize;
+    let entry = args.arg2 as usize;
+
+    hyperion_kernel_impl::sigaction(signal, handler, entry)
+}
+
+/// change the blocked signal mask
+///
+/// [`hyperion_syscall::sigprocmask`]
+pub fn sigprocmask(args: &mut SyscallRegs) -> Result<usize> {
+    let how = SigMaskHow(args.arg0 as _);
+    let set = SignalSet(args.arg1);
+
+    _sigprocmask(how, set).map(|old| old.0 as usize)
+}
+
+fn _sigprocmask(how: SigMaskHow, set: SignalSet) -> Result<SignalSet> {
+    hyperion_kernel_impl::sigprocmask(how, set)
+}
+
+/// return from a signal handler
+///
+/// the signal trampoline in [`hyperion_syscall::signal`] calls this
+pub fn sigreturn(args: &mut SyscallRegs) -> Result<usize> {
+    hyperion_kernel_impl::sigreturn(args);
+
+    // the return value of the interrupted syscall
+    Ok(args.syscall_id as usize)
+}
+
 /// get file metadata
 ///
 /// [`hyperion_syscall::metadata`]
@@ -755,8 +1190,9 @@ pub fn metadata(args: &mut SyscallRegs) -> Result<usize> {
     let meta: &mut Metadata = read_untrusted_mut(args.arg1)?;
 
     let file = fd_query(fd)?;
-    meta.len = file.len()?;
-    meta.position = file.seek(0, Seek::CUR)?;
+    *meta = file.metadata()?;
+    // pipes and sockets have no position
+    meta.position = file.seek(0, Seek::CUR).unwrap_or(0);
 
     Ok(0)
 }
@@ -793,7 +1229,7 @@ pub fn system(args: &mut SyscallRegs) -> Result<usize> {
         *read_untrusted_ref(args.arg4)?
     };
 
-    let program = program.to_string();
+    let program = hyperion_kernel_impl::to_absolute(program).0;
     let args = cli_args
         .iter()
         .map(ToString::to_string)
@@ -803,7 +1239,11 @@ pub fn system(args: &mut SyscallRegs) -> Result<usize> {
     let stdout = fd_query(stdio.stdout)?;
     let stderr = fd_query(stdio.stderr)?;
 
-    let pid = hyperion_kernel_impl::exec(program, args, stdin, stdout, stderr, None);
+    // the new process inherits the environment and the working directory
+    let envs = hyperion_kernel_impl::env();
+    let cwd = hyperion_kernel_impl::cwd();
+
+    let pid = hyperion_kernel_impl::exec(program, args, envs, cwd, stdin, stdout, stderr, None)?;
 
     Ok(pid.num())
 }
@@ -816,10 +1256,18 @@ pub fn fork(args: &mut SyscallRegs) -> Result<usize> {
     let stdin = fd_query(FileDesc(0)).unwrap();
     let stdout = fd_query(FileDesc(1)).unwrap();
     let stderr = fd_query(FileDesc(2)).unwrap();
+    let envs = hyperion_kernel_impl::env();
+    let cwd = hyperion_kernel_impl::cwd();
+    let parent = process();
     let pid = hyperion_scheduler::fork(move || {
-        fd_push(stdin);
-        fd_push(stdout);
-        fd_push(stderr);
+        // the open files limit is inherited, so stdio might not fit
+        _ = fd_push(stdin);
+        _ = fd_push(stdout);
+        _ = fd_push(stderr);
+        hyperion_kernel_impl::set_env(&envs);
+        hyperion_kernel_impl::set_cwd(cwd);
+        hyperion_kernel_impl::inherit_signals(&parent);
+        drop(parent);
 
         let mut args = args;
         args.syscall_id = Error::encode(Ok(0)) as _;
@@ -828,18 +1276,1471 @@ pub fn fork(args: &mut SyscallRegs) -> Result<usize> {
     return Ok(pid.num());
 }
 
-/// wait for a process to exit
+/// wait for a child process to exit or get stopped
 ///
-/// [`hyperion_syscall::waitpid`]
+/// [`hyperion_syscall::waitpid`] and [`hyperion_syscall::waitpid_with`]
 pub fn waitpid(args: &mut SyscallRegs) -> Result<usize> {
+    let pid = match args.arg0 as usize {
+        WAIT_ANY => None,
+        pid => Some(Pid::new(pid)),
+    };
+
+    if args.arg2 == 0 {
+        let (_, status) = hyperion_kernel_impl::wait_child(pid, WaitFlags::empty())?
+            .expect("a blocking wait always returns a child");
+
+        // negatives wrap, but the syscaller handles it
+        return Ok(status.code() as usize);
+    }
+
+    let flags = WaitFlags::from_bits(args.arg1 as _).ok_or(Error::INVALID_FLAGS)?;
+    let status = read_untrusted_mut::<[usize; 2]>(args.arg2)?;
+
+    let Some((child, changed)) = hyperion_kernel_impl::wait_child(pid, flags)? else {
+        return Ok(0);
+    };
+    *status = changed.as_raw();
+
+    Ok(child.num())
+}
+
+/// move a process into a process group
+///
+/// [`hyperion_syscall::setpgid`]
+pub fn setpgid(args: &mut SyscallRegs) -> Result<usize> {
+    let pid = Pid::new(args.arg0 as _);
+    let pgid = Pid::new(args.arg1 as _);
+
+    hyperion_kernel_impl::setpgid(pid, pgid)?;
+    Ok(0)
+}
+
+/// get the process group of a process
+///
+/// [`hyperion_syscall::getpgid`]
+pub fn getpgid(args: &mut SyscallRegs) -> Result<usize> {
     let pid = Pid::new(args.arg0 as _);
 
-    let Some(proc) = pid.find() else {
-        return Err(Error::NO_SUCH_PROCESS);
+    hyperion_kernel_impl::getpgid(pid).map(Pid::num)
+}
+
+/// start a new session
+///
+/// [`hyperion_syscall::setsid`]
+pub fn setsid(_args: &mut SyscallRegs) -> Result<usize> {
+    hyperion_kernel_impl::setsid().map(Pid::num)
+}
+
+/// get the resource limits of the current process
+///
+/// [`hyperion_syscall::getrlimit`]
+pub fn getrlimit(args: &mut SyscallRegs) -> Result<usize> {
+    let resource = Resource(args.arg0 as _);
+    let limit = read_untrusted_mut::<Limit>(args.arg1)?;
+
+    *limit = hyperion_kernel_impl::getrlimit(resource)?;
+    Ok(0)
+}
+
+/// set the resource limits of the current process
+///
+/// [`hyperion_syscall::setrlimit`]
+pub fn setrlimit(args: &mut SyscallRegs) -> Result<usize> {
+    let resource = Resource(args.arg0 as _);
+    let limit = Limit {
+        cur: args.arg1,
+        max: args.arg2,
     };
 
-    let exit_code = *proc.exit_code.wait();
+    hyperion_kernel_impl::setrlimit(resource, limit)?;
+    Ok(0)
+}
+
+/// get the user id of the current process
+///
+/// [`hyperion_syscall::getuid`]
+pub fn getuid(_args: &mut SyscallRegs) -> Result<usize> {
+    Ok(hyperion_kernel_impl::getuid())
+}
+
+/// set the user id of the current process
+///
+/// [`hyperion_syscall::setuid`]
+pub fn setuid(args: &mut SyscallRegs) -> Result<usize> {
+    hyperion_kernel_impl::setuid(args.arg0 as _)?;
+    Ok(0)
+}
+
+/// get the group id of the current process
+///
+/// [`hyperion_syscall::getgid`]
+pub fn getgid(_args: &mut SyscallRegs) -> Result<usize> {
+    Ok(hyperion_kernel_impl::getgid())
+}
+
+/// set the group id of the current process
+///
+/// [`hyperion_syscall::setgid`]
+pub fn setgid(args: &mut SyscallRegs) -> Result<usize> {
+    hyperion_kernel_impl::setgid(args.arg0 as _)?;
+    Ok(0)
+}
+
+/// set the permission bits of a file or a directory
+///
+/// [`hyperion_syscall::chmod`]
+pub fn chmod(args: &mut SyscallRegs) -> Result<usize> {
+    let path = read_untrusted_str(args.arg0, args.arg1)?;
+    let mode = args.arg2 as usize;
+
+    let path = hyperion_kernel_impl::to_absolute(path);
+    hyperion_kernel_impl::chmod(path.as_str(), mode)?;
+    Ok(0)
+}
+
+/// set the owner and the group of a file or a directory
+///
+/// [`hyperion_syscall::chown`]
+pub fn chown(args: &mut SyscallRegs) -> Result<usize> {
+    let path = read_untrusted_str(args.arg0, args.arg1)?;
+    let uid = Some(args.arg2 as usize).filter(|uid| *uid != usize::MAX);
+    let gid = Some(args.arg3 as usize).filter(|gid| *gid != usize::MAX);
+
+    let path = hyperion_kernel_impl::to_absolute(path);
+    hyperion_kernel_impl::chown(path.as_str(), uid, gid)?;
+    Ok(0)
+}
+
+/// set the nice value of a process
+///
+/// [`hyperion_syscall::set_priority`]
+pub fn set_priority(args: &mut SyscallRegs) -> Result<usize> {
+    let pid = Pid::new(args.arg0 as _);
+    let nice = (args.arg1 as isize).clamp(i32::MIN as isize, i32::MAX as isize) as i32;
+
+    hyperion_kernel_impl::set_priority(pid, nice)?;
+    Ok(0)
+}
+
+/// get the nice value of a process
+///
+/// [`hyperion_syscall::get_priority`]
+pub fn get_priority(args: &mut SyscallRegs) -> Result<usize> {
+    let pid = Pid::new(args.arg0 as _);
+
+    let nice = hyperion_kernel_impl::get_priority(pid)?;
+    Ok((20 - nice) as usize)
+}
+
+/// pin a process or the current thread to a set of CPUs
+///
+/// [`hyperion_syscall::sched_setaffinity`]
+pub fn sched_setaffinity(args: &mut SyscallRegs) -> Result<usize> {
+    let pid = Pid::new(args.arg0 as _);
+
+    hyperion_kernel_impl::sched_setaffinity(pid, args.arg1)?;
+    Ok(0)
+}
+
+/// get the CPUs a process or the current thread may run on
+///
+/// [`hyperion_syscall::sched_getaffinity`]
+pub fn sched_getaffinity(args: &mut SyscallRegs) -> Result<usize> {
+    let pid = Pid::new(args.arg0 as _);
+    let mask = read_untrusted_mut::<u64>(args.arg1)?;
+
+    *mask = hyperion_kernel_impl::sched_getaffinity(pid)?;
+    Ok(0)
+}
+
+/// set the foreground process group of the current session
+///
+/// [`hyperion_syscall::tcsetpgrp`]
+pub fn tcsetpgrp(args: &mut SyscallRegs) -> Result<usize> {
+    let pgid = Pid::new(args.arg0 as _);
+
+    hyperion_kernel_impl::tcsetpgrp(pgid)?;
+    Ok(0)
+}
+
+/// get the foreground process group of a session
+///
+/// [`hyperion_syscall::tcgetpgrp`]
+pub fn tcgetpgrp(args: &mut SyscallRegs) -> Result<usize> {
+    let sid = Pid::new(args.arg0 as _);
+
+    hyperion_kernel_impl::tcgetpgrp(sid).map(Pid::num)
+}
+
+/// replace the current process image
+///
+/// [`hyperion_syscall::exec`]
+pub fn exec(args: &mut SyscallRegs) -> Result<usize> {
+    let program: &str = read_untrusted_str(args.arg0, args.arg1)?;
+    // FIXME: &str (&[u8]) is not yet ABI stable
+    let cli_args: &[&str] = read_untrusted_slice(args.arg2, args.arg3)?;
+
+    let envs = if args.arg4 == 0 {
+        hyperion_kernel_impl::env()
+    } else {
+        let envs: EnvList = *read_untrusted_ref(args.arg4)?;
+        // FIXME: &str (&[u8]) is not yet ABI stable
+        let envs: &[&str] = read_untrusted_slice(envs.ptr as _, envs.len as _)?;
+
+        envs.iter()
+            .map(|env| {
+                let (key, value) = env.split_once('=').ok_or(Error::INVALID_ARGUMENT)?;
+                Ok((key.to_string(), value.to_string()))
+            })
+            .collect::<Result<Vec<(String, String)>>>()?
+    };
+
+    let program = hyperion_kernel_impl::to_absolute(program).0;
+    let args = cli_args
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<String>>();
+
+    match hyperion_kernel_impl::exec_replace(program, args, envs)? {}
+}
+
+/// change the current working directory
+///
+/// [`hyperion_syscall::chdir`]
+pub fn chdir(args: &mut SyscallRegs) -> Result<usize> {
+    let path = read_untrusted_str(args.arg0, args.arg1)?;
+    let path = hyperion_kernel_impl::to_absolute(path);
+
+    // the new working directory has to exist
+    VFS_ROOT.find_dir(path.as_str(), false, false)?;
+
+    hyperion_kernel_impl::set_cwd(path);
+    return Ok(0);
+}
+
+/// read the current working directory
+///
+/// [`hyperion_syscall::getcwd`]
+pub fn getcwd(args: &mut SyscallRegs) -> Result<usize> {
+    let buf = read_untrusted_bytes_mut(args.arg0, args.arg1)?;
+
+    let cwd = hyperion_kernel_impl::cwd();
+    let cwd = cwd.as_str().as_bytes();
+
+    let len = cwd.len().min(buf.len());
+    buf[..len].copy_from_slice(&cwd[..len]);
+
+    return Ok(cwd.len());
+}
+
+/// remove a file or an empty directory
+///
+/// [`hyperion_syscall::unlink`]
+pub fn unlink(args: &mut SyscallRegs) -> Result<usize> {
+    let path = read_untrusted_str(args.arg0, args.arg1)?;
+    let Some(flags) = UnlinkFlags::from_bits(args.arg2 as usize) else {
+        return Err(Error::INVALID_FLAGS);
+    };
+
+    _unlink(path, flags)?;
+    return Ok(0);
+}
+
+fn _unlink(path: &str, flags: UnlinkFlags) -> Result<()> {
+    let path = hyperion_kernel_impl::to_absolute(path);
+    hyperion_kernel_impl::check_remove(path.as_str())?;
+    VFS_ROOT.remove(path.as_str(), flags.contains(UnlinkFlags::REMOVE_DIR))?;
+    Ok(())
+}
+
+/// move a file or a directory
+///
+/// [`hyperion_syscall::rename_path`]
+pub fn rename_path(args: &mut SyscallRegs) -> Result<usize> {
+    let from = read_untrusted_str(args.arg0, args.arg1)?;
+    let to = read_untrusted_str(args.arg2, args.arg3)?;
+
+    _rename_path(from, to)?;
+    return Ok(0);
+}
+
+fn _rename_path(from: &str, to: &str) -> Result<()> {
+    let from = hyperion_kernel_impl::to_absolute(from);
+    let to = hyperion_kernel_impl::to_absolute(to);
+    hyperion_kernel_impl::check_remove(from.as_str())?;
+    hyperion_kernel_impl::check_create(to.as_str())?;
+    VFS_ROOT.rename(from.as_str(), to.as_str())
+}
+
+/// get file metadata from a path
+///
+/// [`hyperion_syscall::stat`]
+pub fn stat(args: &mut SyscallRegs) -> Result<usize> {
+    let path = read_untrusted_str(args.arg0, args.arg1)?;
+    let meta: &mut Metadata = read_untrusted_mut(args.arg2)?;
+
+    let path = hyperion_kernel_impl::to_absolute(path);
+    *meta = VFS_ROOT.find(path.as_str(), false)?.metadata();
+
+    return Ok(0);
+}
+
+/// read directory entries
+///
+/// [`hyperion_syscall::read_dir`]
+pub fn read_dir(args: &mut SyscallRegs) -> Result<usize> {
+    let fd = FileDesc(args.arg0 as _);
+    let buf = read_untrusted_bytes_mut(args.arg1, args.arg2)?;
+
+    let dir = fd_query(fd)?;
+    let dir = dir
+        .as_any()
+        .downcast_ref::<DirDescData>()
+        .ok_or(Error::NOT_A_DIRECTORY)?;
+
+    return dir.read_dir(buf);
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use hyperion_kernel_impl::FileDescriptor;
+    use hyperion_mem::vmm::PageMapImpl;
+    use hyperion_syscall::WaitStatus;
+    use x86_64::structures::paging::PageTableFlags;
+
+    use super::*;
+
+    /// run a program with empty stdin and collect everything it writes to stdout and stderr
+    fn run_and_collect(path: &str, args: &[&str]) -> Vec<u8> {
+        let (_stdin_send, stdin_recv) = hyperion_scheduler::ipc::pipe::pipe().split();
+        let (stdout_send, stdout_recv) = hyperion_scheduler::ipc::pipe::pipe().split();
+        let stdout: Arc<dyn FileDescriptor> = Arc::new(stdout_send);
+        hyperion_kernel_impl::exec(
+            path.into(),
+            args.iter().map(|arg| (*arg).into()).collect(),
+            Vec::new(),
+            hyperion_vfs::path::PathBuf::new("/"),
+            Arc::new(stdin_recv),
+            stdout.clone(),
+            stdout,
+            None,
+        )
+        .unwrap();
+
+        // the output pipe closes when the process exits
+        let mut output = Vec::new();
+        let mut buf = [0u8; 64];
+        while let Ok(len @ 1..) = stdout_recv.recv_slice(&mut buf) {
+            output.extend_from_slice(&buf[..len]);
+        }
+        output
+    }
+
+    /// run a closure in a new process owned by another user and wait for its result,
+    /// so that the test process and the other tests keep running as root
+    fn as_user<T: Clone + Send + Sync + 'static>(
+        uid: usize,
+        f: impl FnOnce() -> T + Send + 'static,
+    ) -> T {
+        let result = Arc::new(hyperion_scheduler::lock::Once::new());
+        let result_send = result.clone();
+        hyperion_scheduler::schedule(move || {
+            process().uid.store(uid, Ordering::SeqCst);
+            result_send.call_once(f);
+            hyperion_scheduler::done();
+        });
+        result.wait().clone()
+    }
+
+    fn local_socket(ty: SocketType) -> FileDesc {
+        _socket(SocketInfo {
+            domain: SocketDomain::LOCAL,
+            ty,
+            proto: Protocol::LOCAL,
+        })
+        .unwrap()
+    }
+
+    #[test_case]
+    fn local_socket_dgram() {
+        let server_addr = "/tmp/test-dgram-server.sock";
+        let client_addr = "/tmp/test-dgram-client.sock";
+
+        let server = local_socket(SocketType::DGRAM);
+        let client = local_socket(SocketType::DGRAM);
+        _bind(server, server_addr).unwrap();
+        _bind(client, client_addr).unwrap();
+
+        _sendto(client, b"first", server_addr).unwrap();
+        _sendto(client, b"second message", server_addr).unwrap();
+
+        let mut buf = [0u8; 64];
+        let mut addr = [0u8; 64];
+
+        let len = _recvfrom(server, &mut buf, &mut addr).unwrap();
+        assert_eq!(&buf[..len], b"first");
+        assert_eq!(&addr[..client_addr.len()], client_addr.as_bytes());
+        assert_eq!(addr[client_addr.len()], 0);
+
+        // the rest of a message is discarded if it doesn't fit
+        let len = _recvfrom(server, &mut buf[..6], &mut addr).unwrap();
+        assert_eq!(&buf[..len], b"second");
+
+        // `send` requires a default destination
+        assert_eq!(_send(client, b"third", 0), Err(Error::NOT_CONNECTED));
+        _connect(client, server_addr).unwrap();
+        _send(client, b"third", 0).unwrap();
+
+        let len = _recv(server, &mut buf, 0).unwrap();
+        assert_eq!(&buf[..len], b"third");
+
+        // the server is closed, but its address is still bound
+        fd_take(server);
+        assert_eq!(
+            _sendto(client, b"fourth", server_addr),
+            Err(Error::CONNECTION_REFUSED)
+        );
+        assert_eq!(_send(client, b"fourth", 0), Err(Error::CONNECTION_REFUSED));
+
+        VFS_ROOT.remove(server_addr, false).unwrap();
+        VFS_ROOT.remove(client_addr, false).unwrap();
+        fd_take(client);
+    }
+
+    #[test_case]
+    fn local_socket_seqpacket() {
+        let server_addr = "/tmp/test-seqpacket.sock";
+
+        let server = local_socket(SocketType::SEQPACKET);
+        _bind(server, server_addr).unwrap();
+        _listen(server).unwrap();
+
+        // the socket types have to match
+        let stream = local_socket(SocketType::STREAM);
+        assert_eq!(
+            _connect(stream, server_addr),
+            Err(Error::CONNECTION_REFUSED)
+        );
+
+        let client = local_socket(SocketType::SEQPACKET);
+        _connect(client, server_addr).unwrap();
+        let conn = _accept(server).unwrap();
+
+        _send(client, b"hello", 0).unwrap();
+        _send(client, b"world", 0).unwrap();
+        _send(conn, b"reply", 0).unwrap();
+
+        // message boundaries are kept
+        let mut buf = [0u8; 64];
+        let len = _recv(conn, &mut buf, 0).unwrap();
+        assert_eq!(&buf[..len], b"hello");
+        let len = _recv(conn, &mut buf, 0).unwrap();
+        assert_eq!(&buf[..len], b"world");
+        let len = _recv(client, &mut buf, 0).unwrap();
+        assert_eq!(&buf[..len], b"reply");
+
+        VFS_ROOT.remove(server_addr, false).unwrap();
+        for fd in [server, stream, client, conn] {
+            fd_take(fd);
+        }
+    }
+
+    #[test_case]
+    fn local_socket_fd_passing() {
+        let server_addr = "/tmp/test-fd-passing.sock";
+
+        let server = local_socket(SocketType::STREAM);
+        _bind(server, server_addr).unwrap();
+        _listen(server).unwrap();
+
+        let client = local_socket(SocketType::STREAM);
+        _connect(client, server_addr).unwrap();
+        let conn = _accept(server).unwrap();
+
+        let passed = local_socket(SocketType::DGRAM);
+        _sendmsg(conn, b"fd", &[passed]).unwrap();
+
+        let mut buf = [0u8; 64];
+        let mut fds = [FileDesc(0); 2];
+        let len = _recvmsg(client, &mut buf, &mut fds).unwrap();
+        assert_eq!(&buf[..len], b"fd");
+        assert_eq!(fds[1], FileDesc::NONE);
+
+        // the received fd is a new fd pointing to the same file
+        assert_ne!(fds[0], passed);
+        assert!(Arc::ptr_eq(
+            &fd_query(fds[0]).unwrap(),
+            &fd_query(passed).unwrap()
+        ));
+
+        VFS_ROOT.remove(server_addr, false).unwrap();
+        for fd in [server, client, conn, passed, fds[0]] {
+            fd_take(fd);
+        }
+    }
+
+    #[test_case]
+    fn poll_pipe() {
+        let (send, recv) = hyperion_scheduler::ipc::pipe::pipe().split();
+        let write = fd_push(Arc::new(send)).unwrap();
+        let read = fd_push(Arc::new(recv)).unwrap();
+
+        let mut fds = [
+            PollFd::new(read, PollEvents::IN),
+            PollFd::new(write, PollEvents::OUT),
+            PollFd::new(FileDesc::NONE, PollEvents::IN),
+        ];
+
+        // an empty pipe is only writable
+        assert_eq!(_poll(&mut fds, Some(Instant::now())), Ok(2));
+        assert_eq!(fds[0].revents, PollEvents::empty());
+        assert_eq!(fds[1].revents, PollEvents::OUT);
+        assert_eq!(fds[2].revents, PollEvents::NVAL);
+
+        fd_query(write).unwrap().write(b"data").unwrap();
+        assert_eq!(_poll(&mut fds[..1], None), Ok(1));
+        assert_eq!(fds[0].revents, PollEvents::IN);
+
+        // closing the other end is a hangup
+        fd_take(write);
+        assert_eq!(_poll(&mut fds[..1], None), Ok(1));
+        assert_eq!(fds[0].revents, PollEvents::IN | PollEvents::HUP);
+
+        fd_take(read);
+    }
+
+    #[test_case]
+    fn nonblocking_fds() {
+        let (send, recv) = hyperion_scheduler::ipc::pipe::pipe().split();
+        let write = fd_push(Arc::new(send)).unwrap();
+        let read = fd_push(Arc::new(recv)).unwrap();
+
+        _fcntl(read, Fcntl::SET_FLAGS, FileOpenFlags::NONBLOCK.bits()).unwrap();
+        assert_eq!(
+            _fcntl(read, Fcntl::GET_FLAGS, 0),
+            Ok(FileOpenFlags::NONBLOCK.bits())
+        );
+
+        let mut buf = [0u8; 16];
+        assert_eq!(_read(read, &mut buf), Err(Error::WOULD_BLOCK));
+        _write(write, b"data").unwrap();
+        assert_eq!(_read(read, &mut buf), Ok(4));
+        assert_eq!(_read(read, &mut buf), Err(Error::WOULD_BLOCK));
+
+        // EOF instead of WOULD_BLOCK after the write end is closed
+        fd_take(write);
+        assert_eq!(_read(read, &mut buf), Ok(0));
+        fd_take(read);
+
+        let server_addr = "/tmp/test-nonblock.sock";
+        let server = local_socket(SocketType::STREAM);
+        _bind(server, server_addr).unwrap();
+        _listen(server).unwrap();
+        _fcntl(server, Fcntl::SET_FLAGS, FileOpenFlags::NONBLOCK.bits()).unwrap();
+        assert_eq!(_accept(server), Err(Error::WOULD_BLOCK));
+
+        VFS_ROOT.remove(server_addr, false).unwrap();
+        fd_take(server);
+
+        // datagrams are received and sent without blocking on an empty or full inbox
+        let addr = "/tmp/test-nonblock-dgram.sock";
+        let socket = local_socket(SocketType::DGRAM);
+        _bind(socket, addr).unwrap();
+        _fcntl(socket, Fcntl::SET_FLAGS, FileOpenFlags::NONBLOCK.bits()).unwrap();
+        assert_eq!(
+            _recvfrom(socket, &mut buf, &mut []),
+            Err(Error::WOULD_BLOCK)
+        );
+        assert_eq!(_recvmsg(socket, &mut buf, &mut []), Err(Error::WOULD_BLOCK));
+
+        let mut sent = 0;
+        while let Ok(4) = _sendto(socket, b"data", addr) {
+            sent += 1;
+        }
+        assert_eq!(_sendto(socket, b"data", addr), Err(Error::WOULD_BLOCK));
+        assert_ne!(sent, 0);
+        for _ in 0..sent {
+            assert_eq!(_recvfrom(socket, &mut buf, &mut []), Ok(4));
+        }
+        assert_eq!(
+            _recvfrom(socket, &mut buf, &mut []),
+            Err(Error::WOULD_BLOCK)
+        );
 
-    // negatives wrap, but the syscaller handles it
-    return Ok(exit_code.0 as usize);
+        VFS_ROOT.remove(addr, false).unwrap();
+        fd_take(socket);
+    }
+
+    #[test_case]
+    fn mmap_ranges() {
+        let page_map = &process().address_space.page_map;
+        let rw = Prot::READ | Prot::WRITE;
+        let anon = MapFlags::PRIVATE | MapFlags::ANONYMOUS;
+        let user = PageTableFlags::USER_ACCESSIBLE;
+
+        let at = _mmap(None, 0x3000, rw, anon, FileDesc::NONE, 0).unwrap();
+        assert!(page_map.is_mapped(at..at + 0x3000u64, user | PageTableFlags::WRITABLE));
+
+        // unmapping from the middle splits the mapping
+        _munmap(at + 0x1000u64, 0x1000).unwrap();
+        assert!(!page_map.is_mapped(at..at + 0x3000u64, user));
+        assert!(page_map.is_mapped(at + 0x2000u64..at + 0x3000u64, user));
+
+        _mprotect(at, 0x1000, Prot::READ).unwrap();
+        assert!(page_map.is_mapped(at..at + 0x1000u64, user));
+        assert!(!page_map.is_mapped(at..at + 0x1000u64, user | PageTableFlags::WRITABLE));
+        assert_eq!(
+            _mprotect(at + 0x1000u64, 0x1000, Prot::READ),
+            Err(Error::INVALID_ADDRESS)
+        );
+
+        // a fixed mapping fills the hole
+        let hole = Some(at + 0x1000u64);
+        assert_eq!(
+            _mmap(hole, 0x1000, rw, anon | MapFlags::FIXED, FileDesc::NONE, 0),
+            Ok(at + 0x1000u64)
+        );
+        assert!(page_map.is_mapped(at + 0x1000u64..at + 0x3000u64, user));
+
+        // a fixed mapping over the address space limit leaves the old mappings alone
+        let space = Resource::ADDRESS_SPACE;
+        let old = hyperion_kernel_impl::getrlimit(space).unwrap();
+        _munmap(at + 0x2000u64, 0x1000).unwrap();
+        let used = process().virt_mem.load(Ordering::Relaxed) as u64;
+        let limit = Limit {
+            cur: used,
+            max: old.max,
+        };
+        hyperion_kernel_impl::setrlimit(space, limit).unwrap();
+        assert_eq!(
+            _mmap(
+                Some(at),
+                0x3000,
+                rw,
+                anon | MapFlags::FIXED,
+                FileDesc::NONE,
+                0
+            ),
+            Err(Error::OUT_OF_VIRTUAL_MEMORY)
+        );
+        assert!(page_map.is_mapped(at + 0x1000u64..at + 0x2000u64, user));
+        assert_eq!(
+            _mmap(
+                Some(at),
+                0x2000,
+                rw,
+                anon | MapFlags::FIXED,
+                FileDesc::NONE,
+                0
+            ),
+            Ok(at)
+        );
+        hyperion_kernel_impl::setrlimit(space, old).unwrap();
+
+        // only memory from palloc can be freed with pfree
+        assert_eq!(_pfree(at, 3), Err(Error::INVALID_ALLOC));
+        let alloc = _palloc(2).unwrap();
+        assert_eq!(_pfree(alloc, 3), Err(Error::INVALID_ALLOC));
+        _pfree(alloc, 2).unwrap();
+        assert!(!page_map.is_mapped(alloc..alloc + 0x2000u64, user));
+
+        _munmap(at, 0x3000).unwrap();
+    }
+
+    #[test_case]
+    fn mmap_file_offset() {
+        let mut bytes = alloc::vec![0u8; 0x2000];
+        bytes[0x1000..0x1004].copy_from_slice(b"page");
+        let file = Arc::new(FileDescData {
+            file_ref: Arc::new(Mutex::new(ramdisk::File::new(&bytes))),
+            position: AtomicUsize::new(0),
+        });
+        let fd = fd_push(file.clone()).unwrap();
+        let rw = Prot::READ | Prot::WRITE;
+
+        let private = _mmap(None, 0x1000, rw, MapFlags::PRIVATE, fd, 0x1000).unwrap();
+        let shared = _mmap(None, 0x1000, rw, MapFlags::SHARED, fd, 0x1000).unwrap();
+        let private_buf = unsafe { &mut *private.as_mut_ptr::<[u8; 4]>() };
+        let shared_buf = unsafe { &mut *shared.as_mut_ptr::<[u8; 4]>() };
+        assert_eq!(private_buf, b"page");
+        assert_eq!(shared_buf, b"page");
+
+        // only shared mappings write to the file
+        private_buf[0] = b'x';
+        shared_buf[1] = b'A';
+        let mut buf = [0u8; 4];
+        file.file_ref.lock().read(0x1000, &mut buf).unwrap();
+        assert_eq!(&buf, b"pAge");
+
+        _munmap(private, 0x1000).unwrap();
+        _munmap(shared, 0x1000).unwrap();
+        fd_take(fd);
+    }
+
+    #[test_case]
+    fn futex_timeout() {
+        let futex = AtomicUsize::new(0);
+
+        // the value has already changed
+        assert_eq!(_futex_wait(&futex, 1, Timeout::After(0)), Ok(()));
+
+        assert_eq!(
+            _futex_wait(&futex, 0, Timeout::After(1_000_000)),
+            Err(Error::TIMED_OUT)
+        );
+        assert_eq!(
+            _futex_wait(&futex, 0, Timeout::At(0)),
+            Err(Error::TIMED_OUT)
+        );
+    }
+
+    #[test_case]
+    fn condvar_notify_all() {
+        use hyperion_scheduler::{condvar::Condvar, lock::Mutex};
+
+        const WAITERS: usize = 4;
+        static STATE: Mutex<(usize, bool)> = Mutex::new((0, false));
+        static CONDVAR: Condvar = Condvar::new();
+        static WOKEN: AtomicUsize = AtomicUsize::new(0);
+
+        for _ in 0..WAITERS {
+            hyperion_scheduler::spawn(|| {
+                let mut state = STATE.lock();
+                state.0 += 1;
+                while !state.1 {
+                    state = CONDVAR.wait(state);
+                }
+                drop(state);
+
+                WOKEN.fetch_add(1, Ordering::SeqCst);
+                hyperion_scheduler::done();
+            });
+        }
+
+        // every waiter releases the mutex only in `wait`
+        let mut state = loop {
+            let state = STATE.lock();
+            if state.0 == WAITERS {
+                break state;
+            }
+            drop(state);
+            hyperion_scheduler::yield_now();
+        };
+
+        // one waiter is woken up and the rest are requeued onto the mutex,
+        // each unlock then wakes up the next one
+        state.1 = true;
+        CONDVAR.notify_all();
+        drop(state);
+
+        for _ in 0..10_000 {
+            if WOKEN.load(Ordering::SeqCst) == WAITERS {
+                break;
+            }
+            hyperion_scheduler::yield_now();
+        }
+        assert_eq!(WOKEN.load(Ordering::SeqCst), WAITERS);
+    }
+
+    #[test_case]
+    fn signal_masks() {
+        let this = process().pid;
+        let usr1 = SignalSet::EMPTY.with(Signal::USR1);
+
+        // blocked signals stay pending
+        let old = _sigprocmask(SigMaskHow::BLOCK, usr1).unwrap();
+        _kill(this, Signal::USR1).unwrap();
+        assert!(hyperion_kernel_impl::sigpending().contains(Signal::USR1));
+
+        // KILL and STOP cannot be blocked or caught
+        let unblockable = SignalSet::EMPTY.with(Signal::KILL).with(Signal::STOP);
+        _sigprocmask(SigMaskHow::BLOCK, unblockable).unwrap();
+        assert_eq!(
+            _sigprocmask(SigMaskHow::BLOCK, SignalSet::EMPTY),
+            Ok(SignalSet(old.0 | usr1.0))
+        );
+        assert_eq!(
+            hyperion_kernel_impl::sigaction(Signal::STOP, 1, 0),
+            Err(Error::INVALID_ARGUMENT)
+        );
+
+        // ignoring a signal discards it
+        assert_eq!(hyperion_kernel_impl::sigaction(Signal::USR1, 1, 0), Ok(0));
+        assert!(!hyperion_kernel_impl::sigpending().contains(Signal::USR1));
+        _kill(this, Signal::USR1).unwrap();
+        assert!(!hyperion_kernel_impl::sigpending().contains(Signal::USR1));
+
+        // signal 0 only checks if the process exists
+        assert_eq!(_kill(this, Signal(0)), Ok(()));
+        assert_eq!(
+            _kill(Pid::new(usize::MAX), Signal(0)),
+            Err(Error::NO_SUCH_PROCESS)
+        );
+        assert_eq!(_kill(this, Signal(64)), Err(Error::INVALID_ARGUMENT));
+
+        assert_eq!(hyperion_kernel_impl::sigaction(Signal::USR1, 0, 0), Ok(1));
+        _sigprocmask(SigMaskHow::SET, old).unwrap();
+    }
+
+    #[test_case]
+    fn process_groups() {
+        let this = process();
+        let pgid = this.pgid();
+
+        assert_eq!(hyperion_kernel_impl::getpgid(Pid::new(0)), Ok(pgid));
+        assert_eq!(
+            hyperion_kernel_impl::getpgid(Pid::new(usize::MAX)),
+            Err(Error::NO_SUCH_PROCESS)
+        );
+
+        // the group has to exist in the same session
+        assert_eq!(
+            hyperion_kernel_impl::setpgid(Pid::new(0), Pid::new(usize::MAX)),
+            Err(Error::PERMISSION_DENIED)
+        );
+
+        // signal 0 only checks if the group exists
+        assert_eq!(hyperion_kernel_impl::send_group(pgid, Signal(0)), Ok(()));
+        assert_eq!(
+            hyperion_kernel_impl::send_group(Pid::new(usize::MAX), Signal(0)),
+            Err(Error::NO_SUCH_PROCESS)
+        );
+    }
+
+    #[test_case]
+    fn resource_limits() {
+        let files = Resource::OPEN_FILES;
+        let old = hyperion_kernel_impl::getrlimit(files).unwrap();
+
+        assert_eq!(
+            hyperion_kernel_impl::setrlimit(files, Limit { cur: 2, max: 1 }),
+            Err(Error::INVALID_ARGUMENT)
+        );
+        assert_eq!(
+            hyperion_kernel_impl::getrlimit(Resource(Resource::COUNT)),
+            Err(Error::INVALID_ARGUMENT)
+        );
+
+        // no new file descriptors over the soft limit
+        hyperion_kernel_impl::setrlimit(
+            files,
+            Limit {
+                cur: 0,
+                max: old.max,
+            },
+        )
+        .unwrap();
+        let (send, _recv) = hyperion_scheduler::ipc::pipe::pipe().split();
+        assert_eq!(fd_push(Arc::new(send)).err(), Some(Error::LIMIT_EXCEEDED));
+
+        hyperion_kernel_impl::setrlimit(files, old).unwrap();
+
+        // going over the cpu time limit kills the process with XCPU
+        let child = Arc::new(hyperion_scheduler::lock::Once::new());
+        let child_send = child.clone();
+        hyperion_scheduler::schedule(move || {
+            child_send.call_once(process);
+            hyperion_kernel_impl::setrlimit(Resource::CPU_TIME, Limit { cur: 0, max: 0 }).unwrap();
+            loop {
+                hyperion_scheduler::yield_now();
+                hyperion_scheduler::exit_if_killed();
+            }
+        });
+
+        let child = child.wait().clone();
+        loop {
+            let threads = child.threads.load(Ordering::SeqCst);
+            if threads == 0 {
+                break;
+            }
+            futex::wait(&child.threads, threads);
+        }
+        assert_eq!(
+            child.exit_code.get().copied(),
+            Some(ExitCode::from_signal(Signal::XCPU.0))
+        );
+    }
+
+    #[test_case]
+    fn file_permissions() {
+        let path = "/tmp/test-permissions.txt";
+        let rw = FileOpenFlags::READ | FileOpenFlags::WRITE;
+
+        let fd = _open(
+            path,
+            rw | FileOpenFlags::CREATE | FileOpenFlags::CREATE_DIRS,
+        )
+        .unwrap();
+        _close(fd).unwrap();
+
+        hyperion_kernel_impl::chmod(path, 0o640).unwrap();
+        hyperion_kernel_impl::chown(path, Some(1000), None).unwrap();
+        let meta = VFS_ROOT.find(path, false).unwrap().metadata();
+        assert_eq!((meta.mode, meta.uid, meta.gid), (0o640, 1000, 0));
+
+        // another user in the same group can only read
+        let (read, write, chmod, setuid, unlink, rename) = as_user(1001, move || {
+            (
+                _open(path, FileOpenFlags::READ).and_then(_close),
+                _open(path, rw).err(),
+                hyperion_kernel_impl::chmod(path, 0o666),
+                hyperion_kernel_impl::setuid(0),
+                // removing and moving need a writable parent directory
+                _unlink(path, UnlinkFlags::empty()),
+                _rename_path(path, "/tmp/test-permissions-moved.txt"),
+            )
+        });
+        assert_eq!(read, Ok(()));
+        assert_eq!(write, Some(Error::PERMISSION_DENIED));
+        assert_eq!(chmod, Err(Error::PERMISSION_DENIED));
+        assert_eq!(setuid, Err(Error::PERMISSION_DENIED));
+        assert_eq!(unlink, Err(Error::PERMISSION_DENIED));
+        assert_eq!(rename, Err(Error::PERMISSION_DENIED));
+
+        VFS_ROOT.remove(path, false).unwrap();
+    }
+
+    #[test_case]
+    fn unlink_and_rename() {
+        let dir = "/tmp/test-unlink-dir";
+        VFS_ROOT.find_dir(dir, true, true).unwrap();
+        VFS_ROOT
+            .find_file("/tmp/test-unlink-dir/file", false, true)
+            .unwrap();
+
+        // only empty directories are removed, and only with REMOVE_DIR
+        let remove_dir = UnlinkFlags::REMOVE_DIR;
+        assert_eq!(
+            _unlink("/tmp/test-unlink-dir/", remove_dir),
+            Err(Error::DIRECTORY_NOT_EMPTY)
+        );
+        assert_eq!(
+            _unlink("/tmp/test-unlink-dir/", UnlinkFlags::empty()),
+            Err(Error::NOT_A_FILE)
+        );
+        assert_eq!(
+            _unlink("/tmp/test-unlink-dir/file", remove_dir),
+            Err(Error::NOT_A_DIRECTORY)
+        );
+
+        _rename_path("/tmp/test-unlink-dir/file", "/tmp/test-unlink-dir/moved").unwrap();
+        assert_eq!(
+            VFS_ROOT.find("/tmp/test-unlink-dir/file", false).err(),
+            Some(Error::NOT_FOUND)
+        );
+        _unlink("/tmp/test-unlink-dir/moved", UnlinkFlags::empty()).unwrap();
+
+        // trailing slashes name the directory itself, with absolute and relative paths
+        _rename_path("/tmp/test-unlink-dir/", "/tmp/test-unlink-moved/").unwrap();
+        assert_eq!(VFS_ROOT.find(dir, false).err(), Some(Error::NOT_FOUND));
+        _rename_path("/tmp/test-unlink-moved/", "tmp/test-unlink-dir/").unwrap();
+        _unlink("/tmp/test-unlink-dir/", remove_dir).unwrap();
+        assert_eq!(VFS_ROOT.find(dir, false).err(), Some(Error::NOT_FOUND));
+
+        VFS_ROOT.find_dir(dir, false, true).unwrap();
+        _unlink("tmp/test-unlink-dir/", remove_dir).unwrap();
+        assert_eq!(VFS_ROOT.find(dir, false).err(), Some(Error::NOT_FOUND));
+    }
+
+    #[test_case]
+    fn node_ids() {
+        let [a, b] = ["/tmp/test-id-a.txt", "/tmp/test-id-b.txt"].map(|path| {
+            _close(_open(path, FileOpenFlags::WRITE | FileOpenFlags::CREATE).unwrap()).unwrap();
+            VFS_ROOT.find(path, false).unwrap().id()
+        });
+        assert_ne!(a, 0);
+        assert_ne!(a, b);
+
+        // the id belongs to the node, not to its path
+        _rename_path("/tmp/test-id-a.txt", "/tmp/test-id-c.txt").unwrap();
+        assert_eq!(VFS_ROOT.find("/tmp/test-id-c.txt", false).unwrap().id(), a);
+
+        VFS_ROOT.remove("/tmp/test-id-b.txt", false).unwrap();
+        VFS_ROOT.remove("/tmp/test-id-c.txt", false).unwrap();
+    }
+
+    #[test_case]
+    fn pie_relocations() {
+        // the relocations come from `PT_DYNAMIC`, the section headers are optional
+        let mut stripped = hyperion_kshell::sample_pie().to_vec();
+        stripped[0x28..0x30].fill(0); // e_shoff
+        stripped[0x3c..0x40].fill(0); // e_shnum and e_shstrndx
+        let stripped: &'static [u8] = Vec::leak(stripped);
+
+        let path = "/bin/test-pie";
+        for bin in [hyperion_kshell::sample_pie(), stripped] {
+            VFS_ROOT.install_dev(path, ramdisk::StaticRoFile::executable(bin));
+            assert_eq!(run_and_collect(path, &[]), b"first + second = 3\n");
+
+            VFS_ROOT.remove(path, false).unwrap();
+        }
+    }
+
+    #[test_case]
+    fn dynamic_linking() {
+        // the kernel loads the program and `PT_INTERP`, which loads and links the shared object
+        let path = "/bin/test-dynamic";
+        VFS_ROOT.install_dev(
+            path,
+            ramdisk::StaticRoFile::executable(hyperion_kshell::sample_dynamic()),
+        );
+        VFS_ROOT.install_dev(
+            "/lib/ld.so",
+            ramdisk::StaticRoFile::executable(hyperion_kshell::ld_so()),
+        );
+        VFS_ROOT.install_dev(
+            "/lib/libsample.so",
+            ramdisk::StaticRoFile::new(hyperion_kshell::sample_so()),
+        );
+        assert_eq!(run_and_collect(path, &[]), b"1 + 2 = 3 from libsample.so\n");
+
+        VFS_ROOT.remove(path, false).unwrap();
+        VFS_ROOT.remove("/lib/ld.so", false).unwrap();
+        VFS_ROOT.remove("/lib/libsample.so", false).unwrap();
+    }
+
+    #[test_case]
+    fn invalid_executables() {
+        let (send, recv) = hyperion_scheduler::ipc::pipe::pipe().split();
+        let stdin: Arc<dyn FileDescriptor> = Arc::new(recv);
+        let stdout: Arc<dyn FileDescriptor> = Arc::new(send);
+        let exec = |path: &str| {
+            hyperion_kernel_impl::exec(
+                path.into(),
+                Vec::new(),
+                Vec::new(),
+                hyperion_vfs::path::PathBuf::new("/"),
+                stdin.clone(),
+                stdout.clone(),
+                stdout.clone(),
+                None,
+            )
+            .map(|_| ())
+        };
+
+        assert_eq!(exec("/bin/test-missing"), Err(Error::NOT_FOUND));
+
+        // not even root can execute files without any execute bits
+        let path = "/bin/test-not-executable";
+        VFS_ROOT.install_dev(
+            path,
+            ramdisk::StaticRoFile::new(hyperion_kshell::sample_pie()),
+        );
+        assert_eq!(exec(path), Err(Error::PERMISSION_DENIED));
+        VFS_ROOT.remove(path, false).unwrap();
+
+        // garbage and an ELF header without the rest of the file
+        let path = "/bin/test-invalid";
+        for bytes in [
+            &b"not an ELF file"[..],
+            &hyperion_kshell::sample_pie()[..0x40],
+        ] {
+            VFS_ROOT.install_dev(path, ramdisk::StaticRoFile::executable(bytes));
+            assert_eq!(exec(path), Err(Error::INVALID_EXECUTABLE));
+            VFS_ROOT.remove(path, false).unwrap();
+        }
+
+        // the same PIE with a different p_align in every PT_LOAD,
+        // 0 means no alignment but anything else has to be a power of two
+        let with_align = |align: u64| -> &'static [u8] {
+            let mut bin = hyperion_kshell::sample_pie().to_vec();
+            let phoff = u64::from_le_bytes(bin[0x20..0x28].try_into().unwrap()) as usize;
+            let phentsize = u16::from_le_bytes(bin[0x36..0x38].try_into().unwrap()) as usize;
+            let phnum = u16::from_le_bytes(bin[0x38..0x3a].try_into().unwrap()) as usize;
+            for phdr in (0..phnum).map(|i| phoff + i * phentsize) {
+                // p_type PT_LOAD
+                if bin[phdr..phdr + 4] == 1u32.to_le_bytes() {
+                    bin[phdr + 0x30..phdr + 0x38].copy_from_slice(&align.to_le_bytes());
+                }
+            }
+            Vec::leak(bin)
+        };
+        for (align, result) in [(0, Ok(())), (3, Err(Error::INVALID_EXECUTABLE))] {
+            VFS_ROOT.install_dev(path, ramdisk::StaticRoFile::executable(with_align(align)));
+            assert_eq!(exec(path), result);
+            VFS_ROOT.remove(path, false).unwrap();
+        }
+    }
+
+    #[test_case]
+    fn child_processes() {
+        // `timeout` polls its child with `Child::try_wait` and kills it when the time is up
+        let bins = [
+            "/tmp/test-bin/timeout",
+            "/tmp/test-bin/sleep",
+            "/tmp/test-bin/echo",
+        ];
+        for bin in bins {
+            VFS_ROOT.install_dev(
+                bin,
+                ramdisk::StaticRoFile::executable(hyperion_kshell::coreutils()),
+            );
+        }
+
+        assert_eq!(
+            run_and_collect(bins[0], &["10", bins[2], "exited"]),
+            b"exited\n"
+        );
+        assert_eq!(
+            run_and_collect(bins[0], &["0.1", bins[1], "10"]),
+            b"/tmp/test-bin/sleep timed out, signal: SIGKILL\n"
+        );
+
+        for bin in bins {
+            VFS_ROOT.remove(bin, false).unwrap();
+        }
+    }
+
+    #[test_case]
+    fn shebang_scripts() {
+        let interpreter = "/bin/test-interpreter";
+        let script = "/bin/test-script";
+        VFS_ROOT.install_dev(
+            interpreter,
+            ramdisk::StaticRoFile::executable(hyperion_kshell::sample_args()),
+        );
+
+        // the interpreter runs instead, with `interpreter [arg] script args..`
+        for (bytes, expected) in [
+            (
+                &b"#!/bin/test-interpreter -x\nignored\n"[..],
+                &b"/bin/test-interpreter\n-x\n/bin/test-script\nfirst\nsecond\n"[..],
+            ),
+            (
+                &b"#!/bin/test-interpreter\nignored\n"[..],
+                &b"/bin/test-interpreter\n/bin/test-script\nfirst\nsecond\n"[..],
+            ),
+        ] {
+            VFS_ROOT.install_dev(script, ramdisk::StaticRoFile::executable(bytes));
+            assert_eq!(run_and_collect(script, &["first", "second"]), expected);
+
+            VFS_ROOT.remove(script, false).unwrap();
+        }
+
+        VFS_ROOT.remove(interpreter, false).unwrap();
+    }
+
+    #[test_case]
+    fn wait_for_children() {
+        let path = "/bin/test-child";
+        VFS_ROOT.install_dev(
+            path,
+            ramdisk::StaticRoFile::executable(hyperion_kshell::sample_pie()),
+        );
+
+        // the test runs in a kernel process, which doesn't keep zombies, so wait in a new one
+        let result = Arc::new(hyperion_scheduler::lock::Once::new());
+        let result_send = result.clone();
+        hyperion_scheduler::schedule(move || {
+            let (send, recv) = hyperion_scheduler::ipc::pipe::pipe().split();
+            let stdout: Arc<dyn FileDescriptor> = Arc::new(send);
+            let child = hyperion_kernel_impl::exec(
+                path.into(),
+                Vec::new(),
+                Vec::new(),
+                hyperion_vfs::path::PathBuf::new("/"),
+                Arc::new(recv),
+                stdout.clone(),
+                stdout,
+                None,
+            )
+            .unwrap();
+
+            let waited = hyperion_kernel_impl::wait_child(None, WaitFlags::empty());
+            let reaped = hyperion_kernel_impl::wait_child(Some(child), WaitFlags::NOHANG);
+            let not_a_child =
+                hyperion_kernel_impl::wait_child(Some(Pid::new(0)), WaitFlags::empty());
+            result_send.call_once(|| (child, waited, reaped, not_a_child));
+            hyperion_scheduler::done();
+        });
+
+        let (child, waited, reaped, not_a_child) = result.wait();
+        assert_eq!(*waited, Ok(Some((*child, WaitStatus::Exited(0)))));
+        assert_eq!(*reaped, Err(Error::NO_CHILD_PROCESSES));
+        assert_eq!(*not_a_child, Err(Error::NO_CHILD_PROCESSES));
+
+        VFS_ROOT.remove(path, false).unwrap();
+    }
+
+    #[test_case]
+    fn nice_values() {
+        static STARTED: AtomicUsize = AtomicUsize::new(0);
+        static STOP: AtomicUsize = AtomicUsize::new(0);
+        static DONE: AtomicUsize = AtomicUsize::new(0);
+        static COUNTS: [AtomicUsize; 2] = [const { AtomicUsize::new(0) }; 2];
+
+        let this = Pid::new(0);
+        let old = hyperion_kernel_impl::get_priority(this).unwrap();
+
+        hyperion_kernel_impl::set_priority(this, 5).unwrap();
+        assert_eq!(hyperion_kernel_impl::get_priority(this), Ok(5));
+
+        // out of range values are clamped
+        hyperion_kernel_impl::set_priority(this, 100).unwrap();
+        assert_eq!(hyperion_kernel_impl::get_priority(this), Ok(19));
+        hyperion_kernel_impl::set_priority(this, -100).unwrap();
+        assert_eq!(hyperion_kernel_impl::get_priority(this), Ok(-20));
+
+        assert_eq!(
+            hyperion_kernel_impl::get_priority(Pid::new(usize::MAX)),
+            Err(Error::NO_SUCH_PROCESS)
+        );
+
+        hyperion_kernel_impl::set_priority(this, old).unwrap();
+
+        // two processes compete for the first CPU, the higher priority one gets most of it
+        let pids = [-20, 19].map(|nice| {
+            hyperion_scheduler::schedule(move || {
+                let i = (nice > 0) as usize;
+                hyperion_kernel_impl::set_priority(Pid::new(0), nice).unwrap();
+                hyperion_kernel_impl::sched_setaffinity(Pid::new(0), 1).unwrap();
+                STARTED.fetch_add(1, Ordering::SeqCst);
+                while STARTED.load(Ordering::SeqCst) != 2 {
+                    hyperion_scheduler::yield_now();
+                }
+
+                while STOP.load(Ordering::SeqCst) == 0 {
+                    COUNTS[i].fetch_add(1, Ordering::Relaxed);
+                }
+
+                DONE.fetch_add(1, Ordering::SeqCst);
+                futex::wake(&DONE, usize::MAX);
+                hyperion_scheduler::done();
+            })
+        });
+
+        // only root can raise the priority or change the processes of other users
+        let other = pids[1];
+        let (lower, raise, change_other) = as_user(1001, move || {
+            (
+                hyperion_kernel_impl::set_priority(this, 10),
+                hyperion_kernel_impl::set_priority(this, 5),
+                hyperion_kernel_impl::set_priority(other, 19),
+            )
+        });
+        assert_eq!(lower, Ok(()));
+        assert_eq!(raise, Err(Error::PERMISSION_DENIED));
+        assert_eq!(change_other, Err(Error::PERMISSION_DENIED));
+
+        hyperion_scheduler::sleep(Duration::milliseconds(100));
+        STOP.store(1, Ordering::SeqCst);
+        loop {
+            let done = DONE.load(Ordering::SeqCst);
+            if done == 2 {
+                break;
+            }
+            futex::wait(&DONE, done);
+        }
+
+        let [high, low] = COUNTS.each_ref().map(|count| count.load(Ordering::Relaxed));
+        assert!(
+            high > low,
+            "nice -20 counted to {high} and nice 19 to {low}"
+        );
+    }
+
+    #[test_case]
+    fn affinity_masks() {
+        let this = Pid::new(0);
+        let online = hyperion_scheduler::online_cpus();
+        let proc = process();
+
+        assert_eq!(
+            hyperion_kernel_impl::sched_setaffinity(this, 0),
+            Err(Error::INVALID_ARGUMENT)
+        );
+
+        // pin this thread to the first CPU, it moves there the next time it is scheduled
+        hyperion_kernel_impl::sched_setaffinity(this, 1).unwrap();
+        assert_eq!(hyperion_kernel_impl::sched_getaffinity(this), Ok(1));
+        for _ in 0..4 {
+            hyperion_scheduler::yield_now();
+            assert_eq!(hyperion_cpu_id::cpu_id(), 0);
+        }
+
+        // the thread mask has to overlap with the process mask
+        if online != 1 {
+            let old = proc.affinity.swap(online & !1, Ordering::Relaxed);
+            assert_eq!(
+                hyperion_kernel_impl::sched_setaffinity(this, 1),
+                Err(Error::INVALID_ARGUMENT)
+            );
+            proc.affinity.store(old, Ordering::Relaxed);
+        }
+
+        // offline CPUs are not reported
+        hyperion_kernel_impl::sched_setaffinity(this, u64::MAX).unwrap();
+        assert_eq!(hyperion_kernel_impl::sched_getaffinity(this), Ok(online));
+    }
+
+    #[test_case]
+    fn run_queues() {
+        static RAN: AtomicUsize = AtomicUsize::new(0);
+        const THREADS: usize = 4;
+
+        let cpus = hyperion_cpu_id::cpu_count();
+        let migrations = || -> u64 {
+            hyperion_scheduler::run_queue_stats()
+                .map(|stats| stats.migrations)
+                .sum()
+        };
+        assert_eq!(hyperion_scheduler::run_queue_stats().count(), cpus);
+        let before = migrations();
+
+        // new threads are queued on this CPU, which keeps spinning, so the idle CPUs steal them
+        let this = Pid::new(0);
+        hyperion_kernel_impl::sched_setaffinity(this, 1).unwrap();
+        hyperion_scheduler::yield_now();
+        assert_eq!(hyperion_cpu_id::cpu_id(), 0);
+
+        RAN.store(0, Ordering::SeqCst);
+        for _ in 0..THREADS {
+            hyperion_scheduler::spawn(|| {
+                RAN.fetch_add(1, Ordering::SeqCst);
+                hyperion_scheduler::done();
+            });
+        }
+        while RAN.load(Ordering::SeqCst) != THREADS {
+            if cpus == 1 {
+                hyperion_scheduler::yield_now();
+            }
+            core::hint::spin_loop();
+        }
+        hyperion_kernel_impl::sched_setaffinity(this, u64::MAX).unwrap();
+
+        if cpus > 1 {
+            assert!(migrations() > before);
+        }
+
+        // one line for each CPU, the migrations only grow while the file is read
+        let low = migrations();
+        let fd = _open("/proc/schedstat", FileOpenFlags::READ).unwrap();
+        let mut buf = [0u8; 512];
+        let len = _read(fd, &mut buf).unwrap();
+        _close(fd).unwrap();
+        let high = migrations();
+
+        let schedstat = core::str::from_utf8(&buf[..len]).unwrap();
+        let mut lines = schedstat.lines();
+        assert_eq!(lines.next(), Some("CPU  Ready  Migrations"));
+        let reported: Vec<(usize, u64)> = lines
+            .map(|line| {
+                let mut columns = line.split_whitespace().map(|n| n.parse::<u64>().unwrap());
+                let cpu = columns.next().unwrap() as usize;
+                let _ready = columns.next().unwrap();
+                (cpu, columns.next().unwrap())
+            })
+            .collect();
+        assert_eq!(reported.len(), cpus);
+        assert!(reported.iter().enumerate().all(|(i, (cpu, _))| i == *cpu));
+        let sum: u64 = reported.iter().map(|(_, migrations)| migrations).sum();
+        assert!((low..=high).contains(&sum));
+    }
+
+    #[test_case]
+    fn kill_other_threads() {
+        static NEVER: AtomicUsize = AtomicUsize::new(0);
+
+        let result = Arc::new(hyperion_scheduler::lock::Once::new());
+        let result_send = result.clone();
+        hyperion_scheduler::schedule(move || {
+            // threads blocked in a killable wait
+            for _ in 0..2 {
+                hyperion_scheduler::spawn(|| loop {
+                    _ = hyperion_scheduler::futex::wait_killable(&NEVER, 0, None);
+                    hyperion_scheduler::exit_if_killed();
+                });
+            }
+
+            let killed = hyperion_scheduler::kill_other_threads();
+            let threads = process().threads.load(Ordering::SeqCst);
+            result_send.call_once(|| (killed, threads));
+            hyperion_scheduler::done();
+        });
+
+        assert_eq!(*result.wait(), (Ok(()), 1));
+    }
+
+    #[test_case]
+    fn fatal_signals() {
+        static NEVER: AtomicUsize = AtomicUsize::new(0);
+
+        let child = Arc::new(hyperion_scheduler::lock::Once::new());
+        let child_send = child.clone();
+        hyperion_scheduler::schedule(move || {
+            // like returning from a syscall, the threads exit once their waits end
+            let (send, recv) = hyperion_scheduler::ipc::pipe::pipe().split();
+            hyperion_scheduler::spawn(move || {
+                _ = recv.recv_slice(&mut [0u8; 8]);
+                hyperion_scheduler::exit_if_killed();
+            });
+            hyperion_scheduler::spawn(|| {
+                _ = _nanosleep(i64::MAX);
+                hyperion_scheduler::exit_if_killed();
+            });
+
+            child_send.call_once(process);
+            _ = _futex_wait(&NEVER, 0, Timeout::Never);
+            drop(send);
+            hyperion_scheduler::exit_if_killed();
+        });
+
+        // let the threads block first
+        let child = child.wait().clone();
+        hyperion_scheduler::sleep(Duration::milliseconds(20));
+
+        // only root can signal the processes of other users
+        let pid = child.pid;
+        assert_eq!(
+            as_user(1001, move || hyperion_kernel_impl::send(pid, Signal::KILL)),
+            Err(Error::PERMISSION_DENIED)
+        );
+
+        hyperion_kernel_impl::send(child.pid, Signal::KILL).unwrap();
+
+        loop {
+            let threads = child.threads.load(Ordering::SeqCst);
+            if threads == 0 {
+                break;
+            }
+            futex::wait(&child.threads, threads);
+        }
+        assert_eq!(
+            child.exit_code.get().copied(),
+            Some(ExitCode::from_signal(Signal::KILL.0))
+        );
+    }
+
+    #[test_case]
+    fn inter_processor_interrupts() {
+        use hyperion_drivers::acpi::apic::{self, Ipi, IpiTarget};
+
+        // the page map of the running process is loaded on this CPU
+        let cpu = hyperion_cpu_id::cpu_id();
+        let active = process().address_space.page_map.active_cpus();
+        assert_ne!(active & (1 << cpu), 0);
+
+        // sending waits for the delivery, none of these should do anything to a live process
+        for ipi in Ipi::ALL {
+            apic::send_ipi(IpiTarget::Current, ipi);
+            apic::send_ipi(IpiTarget::Cpu(cpu), ipi);
+        }
+        assert!(process().exit_code.get().is_none());
+
+        // the handlers run on the receiving CPUs, each one completes a full TLB flush
+        for cpu in 0..hyperion_cpu_id::cpu_count() {
+            let flushes = || hyperion_arch::vmm::tlb_flushes(cpu);
+            let until = flushes() + 2 + (flushes() & 1);
+            apic::send_ipi(IpiTarget::Cpu(cpu), Ipi::TlbShootdown);
+
+            let mut spins = 0u64;
+            while flushes() < until && spins < 100_000_000 {
+                core::hint::spin_loop();
+                spins += 1;
+            }
+            assert!(flushes() >= until, "CPU {cpu} didn't handle the IPI");
+        }
+    }
 }
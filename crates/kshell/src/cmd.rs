@@ -1,9 +1,10 @@
 use alloc::{boxed::Box, string::String, sync::Arc, vec::Vec};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use hyperion_futures::mpmc::Sender;
 use hyperion_kernel_impl::{FileDescData, FileDescriptor};
 use hyperion_scheduler::lock::Lazy;
+use hyperion_vfs::path::PathBuf;
 
 //
 
@@ -18,6 +19,8 @@ pub static LOG_DEV: Lazy<Arc<dyn FileDescriptor>> =
 pub struct Command {
     program: String,
     args: Vec<String>,
+    envs: Vec<(String, String)>,
+    cwd: PathBuf,
 
     on_close: Option<Sender<()>>,
 
@@ -31,6 +34,8 @@ impl Command {
         Self {
             program: program.into(),
             args: Vec::new(),
+            envs: Vec::new(),
+            cwd: PathBuf::new("/"),
 
             on_close: None,
 
@@ -50,6 +55,16 @@ impl Command {
         self
     }
 
+    pub fn env(&mut self, key: impl Into<String>, value: impl Into<String>) -> &mut Self {
+        self.envs.push((key.into(), value.into()));
+        self
+    }
+
+    pub fn current_dir(&mut self, cwd: PathBuf) -> &mut Self {
+        self.cwd = cwd;
+        self
+    }
+
     pub fn on_close(&mut self, tx: Sender<()>) -> &mut Self {
         self.on_close = Some(tx);
         self
@@ -73,6 +88,8 @@ impl Command {
     pub fn spawn(&mut self) -> Result<()> {
         let program = self.program.clone();
         let args = self.args.clone();
+        let envs = self.envs.clone();
+        let cwd = self.cwd.clone();
 
         let on_close = self.on_close.clone();
 
@@ -81,13 +98,16 @@ impl Command {
         let stderr = self.stderr.clone().unwrap_or_else(|| LOG_DEV.clone());
 
         hyperion_kernel_impl::exec(
-            program,
+            program.clone(),
             args,
+            envs,
+            cwd,
             stdin,
             stdout,
             stderr,
             on_close.map(|sender| Box::new(move || _ = sender.send(())) as _),
-        );
+        )
+        .map_err(|err| anyhow!("couldn't run `{program}`: {err}"))?;
 
         Ok(())
     }
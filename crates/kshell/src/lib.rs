@@ -4,12 +4,11 @@
 
 extern crate alloc;
 
-use alloc::sync::Arc;
+use alloc::format;
 
 use futures_util::StreamExt;
 use hyperion_kernel_impl::VFS_ROOT;
 use hyperion_log::*;
-use hyperion_scheduler::lock::Mutex;
 use hyperion_vfs::ramdisk::StaticRoFile;
 
 use crate::{shell::Shell, term::Term};
@@ -42,6 +41,38 @@ include!(concat!(env!("OUT_DIR"), "/asset.rs"));
 
 //
 
+/// a position independent executable, for testing the ELF loader relocations
+pub fn sample_pie() -> &'static [u8] {
+    load_elf!("SAMPLE_ELF_pie")
+}
+
+/// prints its cli args, for testing what `exec` passes to programs
+pub fn sample_args() -> &'static [u8] {
+    load_elf!("SAMPLE_ELF_args")
+}
+
+/// every coreutil in one executable, the program is picked by the file name
+pub fn coreutils() -> &'static [u8] {
+    load_elf!("COREUTILS")
+}
+
+/// the dynamic linker, installed as `/lib/ld.so`
+pub fn ld_so() -> &'static [u8] {
+    load_elf!("LD_SO")
+}
+
+/// a shared object, installed as `/lib/libsample.so`
+pub fn sample_so() -> &'static [u8] {
+    load_elf!("SAMPLE_SO")
+}
+
+/// a dynamically linked executable that needs [`ld_so`] and [`sample_so`]
+pub fn sample_dynamic() -> &'static [u8] {
+    load_elf!("SAMPLE_ELF_dynamic")
+}
+
+//
+
 pub async fn kshell() {
     // hyperion_futures::executor::spawn(spinner());
 
@@ -49,31 +80,58 @@ pub async fn kshell() {
 
     for asset in ASSETS {
         let (path, bytes): (&str, &[u8]) = *asset;
-        VFS_ROOT.install_dev(path, StaticRoFile::new(bytes));
+        if path.starts_with("/bin/") {
+            VFS_ROOT.install_dev(path, StaticRoFile::executable(bytes));
+        } else {
+            VFS_ROOT.install_dev(path, StaticRoFile::new(bytes));
+        }
     }
 
-    VFS_ROOT.install_dev("/bin/run", StaticRoFile::new(load_elf!("SAMPLE_ELF")));
-    VFS_ROOT.install_dev("/bin/fbtest", StaticRoFile::new(load_elf!("FBTEST")));
-
-    // everything is the same file
-    let bin = Arc::new(Mutex::new(StaticRoFile::new(load_elf!("COREUTILS"))));
-    VFS_ROOT.install_dev_ref("/bin/cat", bin.clone());
-    VFS_ROOT.install_dev_ref("/bin/cp", bin.clone());
-    VFS_ROOT.install_dev_ref("/bin/date", bin.clone());
-    VFS_ROOT.install_dev_ref("/bin/echo", bin.clone());
-    VFS_ROOT.install_dev_ref("/bin/hello", bin.clone());
-    VFS_ROOT.install_dev_ref("/bin/ls", bin.clone());
-    VFS_ROOT.install_dev_ref("/bin/mem", bin.clone());
-    VFS_ROOT.install_dev_ref("/bin/mkdir", bin.clone());
-    VFS_ROOT.install_dev_ref("/bin/nproc", bin.clone());
-    VFS_ROOT.install_dev_ref("/bin/ps", bin.clone());
-    VFS_ROOT.install_dev_ref("/bin/random", bin.clone());
-    VFS_ROOT.install_dev_ref("/bin/sleep", bin.clone());
-    VFS_ROOT.install_dev_ref("/bin/tail", bin.clone());
-    VFS_ROOT.install_dev_ref("/bin/top", bin.clone());
-    VFS_ROOT.install_dev_ref("/bin/touch", bin.clone());
-
-    VFS_ROOT.install_dev_ref("/bin/coreutils", bin);
+    VFS_ROOT.install_dev(
+        "/bin/run",
+        StaticRoFile::executable(load_elf!("SAMPLE_ELF")),
+    );
+    VFS_ROOT.install_dev("/bin/pie", StaticRoFile::executable(sample_pie()));
+    VFS_ROOT.install_dev("/bin/fbtest", StaticRoFile::executable(load_elf!("FBTEST")));
+    VFS_ROOT.install_dev("/bin/dynamic", StaticRoFile::executable(sample_dynamic()));
+    VFS_ROOT.install_dev("/lib/ld.so", StaticRoFile::executable(ld_so()));
+    VFS_ROOT.install_dev("/lib/libsample.so", StaticRoFile::new(sample_so()));
+
+    // everything is the same binary, but each file has its own owner and permissions
+    let coreutils = coreutils();
+    for name in [
+        "cat",
+        "chmod",
+        "chown",
+        "cp",
+        "date",
+        "echo",
+        "hello",
+        "id",
+        "kill",
+        "ls",
+        "mem",
+        "mkdir",
+        "mv",
+        "nice",
+        "nproc",
+        "ps",
+        "random",
+        "renice",
+        "rm",
+        "rmdir",
+        "sleep",
+        "tail",
+        "timeout",
+        "top",
+        "touch",
+        "coreutils",
+    ] {
+        VFS_ROOT.install_dev(
+            format!("/bin/{name}").as_str(),
+            StaticRoFile::executable(coreutils),
+        );
+    }
 
     let term = Term::new();
     let mut shell = Shell::new(term);
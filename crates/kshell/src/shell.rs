@@ -4,7 +4,7 @@ use alloc::{
     string::{String, ToString},
     sync::Arc,
 };
-use core::{fmt::Write, str, sync::atomic::Ordering};
+use core::{fmt::Write, str};
 
 use anyhow::anyhow;
 use futures_util::{stream::select, Stream};
@@ -16,7 +16,11 @@ use hyperion_events::keyboard::{
 use hyperion_futures::{keyboard::keyboard_events, mpmc};
 use hyperion_kernel_impl::{FileDescData, FileDescriptor};
 use hyperion_scheduler::{ipc::pipe::pipe, proc::Pid, spawn};
-use hyperion_vfs::{self, path::PathBuf};
+use hyperion_syscall::signal::Signal;
+use hyperion_vfs::{
+    self,
+    path::{Path, PathBuf},
+};
 use spin::Mutex;
 
 use super::{term::Term, *};
@@ -198,6 +202,7 @@ impl Shell {
             "kbl" => self.kbl_cmd(args)?,
             "help" => self.help_cmd(args)?,
             "kill" => self.kill_cmd(args)?,
+            "cd" => self.cd_cmd(args)?,
             "exit" => return Ok(None),
             "clear" => self.term.clear(),
             "lspci" => self.lspci_cmd(args)?,
@@ -232,6 +237,11 @@ impl Shell {
 
             let program = if program.starts_with('/') {
                 program.to_string()
+            } else if program.starts_with('.') {
+                Path::from_str(program)
+                    .to_absolute(&self.current_dir)
+                    .as_str()
+                    .to_string()
             } else {
                 format!("/bin/{program}")
             };
@@ -242,11 +252,17 @@ impl Shell {
             is_doom |= program.ends_with("doom");
 
             let mut cmd = Command::new(program);
-            cmd.args(args).stdin(stdin).stderr(stderr.clone());
+            cmd.args(args)
+                .env("PATH", "/bin")
+                .env("HOME", "/")
+                .current_dir(self.current_dir.clone())
+                .stdin(stdin)
+                .stderr(stderr.clone());
 
             let stdout = if let Some(output_file) = redirects.last() {
                 // stdout is redirected to a file
-                let stdout_tx = FileDescData::open(output_file.trim())
+                let output_file = Path::from_str(output_file.trim()).to_absolute(&self.current_dir);
+                let stdout_tx = FileDescData::open(output_file.as_str())
                     .map_err(|err| anyhow!("couldn't open file `{output_file:?}`: {err}"))?;
                 stdin = NULL_DEV.clone();
                 Arc::new(stdout_tx)
@@ -388,26 +404,47 @@ impl Shell {
     fn help_cmd(&mut self, _: Option<&str>) -> anyhow::Result<()> {
         _ = writeln!(
             self.term,
-            "available built-in shell commands:\nkbl, help, kill, exit, clear, lspci"
+            "available built-in shell commands:\nkbl, help, kill, cd, exit, clear, lspci"
         );
 
         Ok(())
     }
 
     fn kill_cmd(&mut self, args: Option<&str>) -> anyhow::Result<()> {
-        let Some(arg) = args else {
-            return Err(anyhow!("missing arg pid"));
+        let mut args = args.unwrap_or("").split_whitespace();
+
+        let (signal, pid) = match (args.next(), args.next(), args.next()) {
+            (Some(pid), None, _) => (Signal::TERM, pid),
+            (Some(signal), Some(pid), None) => {
+                let signal = signal
+                    .strip_prefix('-')
+                    .and_then(Signal::parse)
+                    .ok_or_else(|| anyhow!("invalid signal `{signal}`"))?;
+                (signal, pid)
+            }
+            _ => return Err(anyhow!("usage: kill [-SIGNAL] pid")),
         };
 
-        let Ok(pid) = arg.parse::<usize>() else {
+        let Ok(pid) = pid.parse::<usize>() else {
             return Err(anyhow!("invalid arg pid"));
         };
 
-        let Some(proc) = Pid::new(pid).find() else {
-            return Err(anyhow!("couldn't find the process"));
-        };
+        hyperion_kernel_impl::send(Pid::new(pid), signal)
+            .map_err(|err| anyhow!("couldn't signal the process: {err}"))?;
+
+        Ok(())
+    }
+
+    fn cd_cmd(&mut self, args: Option<&str>) -> anyhow::Result<()> {
+        let path = Path::from_str(args.unwrap_or("/").trim())
+            .to_absolute(&self.current_dir)
+            .into_owned();
+
+        VFS_ROOT
+            .find_dir(path.as_str(), false, false)
+            .map_err(|err| anyhow!("couldn't open directory `{}`: {err}", path.as_str()))?;
 
-        proc.should_terminate.store(true, Ordering::SeqCst);
+        self.current_dir = path;
 
         Ok(())
     }
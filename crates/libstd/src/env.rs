@@ -1,6 +1,9 @@
 use core::{fmt, slice};
 
-use core_alloc::{string::String, vec::Vec};
+use core_alloc::{borrow::ToOwned, string::String, vec, vec::Vec};
+use hyperion_syscall::err::Result;
+
+use crate::sync::Mutex;
 
 //
 
@@ -11,6 +14,56 @@ pub fn args() -> Args {
     }
 }
 
+/// all environment variables of the current process
+#[must_use]
+pub fn vars() -> Vec<(String, String)> {
+    VARS.lock().clone()
+}
+
+/// get an environment variable
+#[must_use]
+pub fn var(key: &str) -> Option<String> {
+    VARS.lock()
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.clone())
+}
+
+/// set an environment variable of this process
+///
+/// new processes only get it if [`vars`] is passed to them, like with `exec_with_env`
+pub fn set_var(key: &str, value: &str) {
+    let mut vars = VARS.lock();
+    if let Some((_, v)) = vars.iter_mut().find(|(k, _)| k == key) {
+        *v = value.to_owned();
+    } else {
+        vars.push((key.to_owned(), value.to_owned()));
+    }
+}
+
+/// remove an environment variable
+pub fn remove_var(key: &str) {
+    VARS.lock().retain(|(k, _)| k != key);
+}
+
+/// the current working directory
+pub fn current_dir() -> Result<String> {
+    let mut buf = vec![0u8; 64];
+    loop {
+        let len = hyperion_syscall::getcwd(&mut buf)?;
+        if len <= buf.len() {
+            buf.truncate(len);
+            return Ok(String::from_utf8(buf).expect("cwd should be UTF-8"));
+        }
+        buf.resize(len, 0);
+    }
+}
+
+/// change the current working directory
+pub fn set_current_dir(path: &str) -> Result<()> {
+    hyperion_syscall::chdir(path)
+}
+
 pub(crate) unsafe fn init_args(hyperion_cli_args_ptr: usize) {
     let stack_args = CliArgs {
         hyperion_cli_args_ptr,
@@ -25,6 +78,23 @@ pub(crate) unsafe fn init_args(hyperion_cli_args_ptr: usize) {
     unsafe { ARGS = args };
 }
 
+pub(crate) unsafe fn init_vars(hyperion_cli_envs_ptr: usize) {
+    if hyperion_cli_envs_ptr == 0 {
+        return;
+    }
+
+    // the env list has the same layout as the cli args
+    let stack_envs = CliArgs {
+        hyperion_cli_args_ptr: hyperion_cli_envs_ptr,
+    };
+
+    *VARS.lock() = stack_envs
+        .iter()
+        .filter_map(|env| env.split_once('='))
+        .map(|(key, value)| (key.to_owned(), value.to_owned()))
+        .collect();
+}
+
 //
 
 #[derive(Clone)]
@@ -66,6 +136,8 @@ impl ExactSizeIterator for Args {
 
 static mut ARGS: &[&str] = &[];
 
+static VARS: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
 #[derive(Clone, Copy)]
 struct CliArgs {
     hyperion_cli_args_ptr: usize,
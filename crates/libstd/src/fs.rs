@@ -1,14 +1,14 @@
 use core::mem::ManuallyDrop;
 
-use core_alloc::{borrow::Cow, string::String};
+use core_alloc::{borrow::Cow, vec, vec::Vec};
 use hyperion_syscall::{
     close,
     err::{Error, Result},
-    fs::{FileDesc, FileOpenFlags, Metadata},
-    metadata, open, read, write,
+    fs::{DirEntryRecord, FileDesc, FileKind, FileOpenFlags, Metadata},
+    open, read, read_dir as read_dir_records, rename_path, rmdir, stat, unlink, write,
 };
 
-use crate::io::{self, BufReader};
+use crate::io;
 
 //
 
@@ -32,34 +32,66 @@ pub fn create_dir_all(path: &str) -> Result<()> {
     Ok(())
 }
 
+/// get the metadata of a file or a directory
+pub fn metadata(path: &str) -> Result<Metadata> {
+    let mut meta = Metadata::zeroed();
+    stat(path, &mut meta)?;
+    Ok(meta)
+}
+
+/// remove a file
+pub fn remove_file(path: &str) -> Result<()> {
+    unlink(path)
+}
+
+/// remove an empty directory
+pub fn remove_dir(path: &str) -> Result<()> {
+    rmdir(path)
+}
+
+/// move a file or a directory, fails if `to` already exists
+pub fn rename(from: &str, to: &str) -> Result<()> {
+    rename_path(from, to)
+}
+
 //
 
+/// open a directory for reading its entries
+pub fn read_dir(path: &str) -> Result<Dir> {
+    Dir::open(path)
+}
+
 pub struct Dir {
-    file: BufReader<File>,
-    cur: String,
+    file: File,
+    buf: Vec<u8>,
+    pos: usize,
+    len: usize,
 }
 
 impl Dir {
     pub fn open(path: &str) -> Result<Self> {
         Ok(Self {
-            file: BufReader::new(OpenOptions::new().read(true).is_dir(true).open(path)?),
-            cur: String::new(),
+            file: OpenOptions::new().read(true).is_dir(true).open(path)?,
+            buf: vec![0; 0x1000],
+            pos: 0,
+            len: 0,
         })
     }
 
     pub fn next_entry(&mut self) -> Option<DirEntry> {
-        self.cur.clear();
-        self.file.read_line(&mut self.cur).ok()?;
+        if self.pos >= self.len {
+            self.len = read_dir_records(self.file.as_desc(), &mut self.buf).ok()?;
+            self.pos = 0;
+        }
 
-        let mut iter = self.cur.trim().split(' ');
-        let file_name = iter.next()?;
-        let size = iter.next()?;
-        let is_dir = iter.remainder()?;
+        let (record, len) = DirEntryRecord::decode(&self.buf[self.pos..self.len])?;
+        self.pos += len;
 
         Some(DirEntry {
-            is_dir: is_dir == "d",
-            size: size.parse().unwrap(),
-            file_name: Cow::Borrowed(file_name),
+            id: record.id,
+            kind: record.kind,
+            is_dir: record.kind == FileKind::DIRECTORY,
+            file_name: Cow::Borrowed(record.name),
         })
     }
 }
@@ -76,16 +108,19 @@ impl Iterator for Dir {
 
 #[derive(Debug)]
 pub struct DirEntry<'a> {
-    pub is_dir: bool, // TODO: mode flags later
-    pub size: usize,
+    /// a unique id of the node, like an inode number
+    pub id: u64,
+    pub kind: FileKind,
+    pub is_dir: bool,
     pub file_name: Cow<'a, str>,
 }
 
 impl DirEntry<'_> {
     fn into_owned(self) -> DirEntry<'static> {
         DirEntry {
+            id: self.id,
+            kind: self.kind,
             is_dir: self.is_dir,
-            size: self.size,
             file_name: Cow::Owned(self.file_name.into_owned()),
         }
     }
@@ -152,7 +187,7 @@ impl File {
 
     pub fn metadata(&self) -> Result<Metadata> {
         let mut meta = Metadata::zeroed();
-        metadata(self.desc, &mut meta)?;
+        hyperion_syscall::metadata(self.desc, &mut meta)?;
         Ok(meta)
     }
 }
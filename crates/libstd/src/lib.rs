@@ -1,12 +1,6 @@
 #![no_std]
 #![allow(internal_features)]
-#![feature(
-    new_zeroed_alloc,
-    str_split_remainder,
-    lang_items,
-    never_type,
-    naked_functions
-)]
+#![feature(new_zeroed_alloc, lang_items, never_type, naked_functions)]
 
 //
 
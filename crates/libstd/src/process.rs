@@ -1,6 +1,8 @@
 use core::{convert::Infallible, fmt};
 
-use hyperion_syscall::exit;
+use hyperion_syscall::{
+    err, exit, kill, signal::Signal, system, waitpid_with, WaitFlags, WaitStatus,
+};
 
 use crate::eprintln;
 
@@ -82,3 +84,101 @@ impl Default for ExitCode {
         Self::SUCCESS
     }
 }
+
+//
+
+/// a spawned child process
+#[derive(Debug)]
+pub struct Child {
+    pid: usize,
+    status: Option<ExitStatus>,
+}
+
+impl Child {
+    /// launch a child process, see [`system`]
+    pub fn spawn(path: &str, args: &[&str]) -> err::Result<Self> {
+        let pid = system(path, args)?;
+        Ok(Self { pid, status: None })
+    }
+
+    #[must_use]
+    pub fn id(&self) -> usize {
+        self.pid
+    }
+
+    /// terminate the child with [`Signal::KILL`], does nothing if it already exited
+    pub fn kill(&mut self) -> err::Result<()> {
+        if self.status.is_some() {
+            return Ok(());
+        }
+        match kill(self.pid, Signal::KILL) {
+            // an exited child is gone before it is reaped, pids are not reused
+            Err(err::Error::NO_SUCH_PROCESS) => Ok(()),
+            result => result,
+        }
+    }
+
+    /// block until the child exits
+    pub fn wait(&mut self) -> err::Result<ExitStatus> {
+        loop {
+            if let Some(status) = self.wait_with(WaitFlags::empty())? {
+                return Ok(status);
+            }
+        }
+    }
+
+    /// get the exit status if the child has already exited, without blocking
+    pub fn try_wait(&mut self) -> err::Result<Option<ExitStatus>> {
+        self.wait_with(WaitFlags::NOHANG)
+    }
+
+    fn wait_with(&mut self, flags: WaitFlags) -> err::Result<Option<ExitStatus>> {
+        // the child was reaped already, it cannot be waited again
+        if let Some(status) = self.status {
+            return Ok(Some(status));
+        }
+
+        let status = waitpid_with(self.pid, flags)?.map(|(_, status)| ExitStatus(status));
+        self.status = status;
+        Ok(status)
+    }
+}
+
+/// how a child process exited
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExitStatus(WaitStatus);
+
+impl ExitStatus {
+    /// the child exited normally with exit code 0
+    #[must_use]
+    pub fn success(self) -> bool {
+        self.0 == WaitStatus::Exited(0)
+    }
+
+    /// the exit code, `None` if the child was terminated by a signal
+    #[must_use]
+    pub fn code(self) -> Option<i64> {
+        match self.0 {
+            WaitStatus::Exited(code) => Some(code),
+            _ => None,
+        }
+    }
+
+    /// the signal that terminated the child
+    #[must_use]
+    pub fn signal(self) -> Option<Signal> {
+        match self.0 {
+            WaitStatus::Signaled(signal) => Some(signal),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            WaitStatus::Signaled(signal) => write!(f, "signal: {signal}"),
+            status => write!(f, "exit status: {}", status.code()),
+        }
+    }
+}
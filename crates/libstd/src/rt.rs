@@ -22,7 +22,7 @@ fn lang_start<T: Termination>(
 }
 
 #[no_mangle]
-extern "C" fn _start(hyperion_cli_args_ptr: usize, _a2: usize) -> ! {
+extern "C" fn _start(hyperion_cli_args_ptr: usize, hyperion_cli_envs_ptr: usize) -> ! {
     // rustc generates the real `main` function, that fn
     // simply calls `lang_start` with the correct args
     extern "Rust" {
@@ -32,6 +32,7 @@ extern "C" fn _start(hyperion_cli_args_ptr: usize, _a2: usize) -> ! {
     // init cli args from stack, move them to the heap
     // crate::println!("init cli args");
     unsafe { env::init_args(hyperion_cli_args_ptr) };
+    unsafe { env::init_vars(hyperion_cli_envs_ptr) };
 
     // call `lang_start`
     // crate::println!("calling main");
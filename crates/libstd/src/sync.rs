@@ -1,9 +1,10 @@
 use core::{
-    sync::atomic::{AtomicUsize, Ordering},
+    ptr,
+    sync::atomic::{AtomicPtr, AtomicUsize, Ordering},
     time::Duration,
 };
 
-use hyperion_syscall::{futex_wait, futex_wake};
+use hyperion_syscall::{futex_requeue, futex_wait, futex_wait_timeout, futex_wake, Timeout};
 use lock_api::{GuardSend, RawMutex};
 
 //
@@ -64,6 +65,10 @@ pub struct Condvar {
     // This is used by `.wait()` to not miss any notifications after
     // unlocking the mutex and before waiting for notifications.
     futex: AtomicUsize,
+    // The number of threads in `.wait()`, they keep `mutex` alive.
+    waiters: AtomicUsize,
+    // The mutex used with this condvar, `.notify_all()` requeues the waiters onto it.
+    mutex: AtomicPtr<Futex>,
 }
 
 impl Condvar {
@@ -72,6 +77,8 @@ impl Condvar {
     pub const fn new() -> Self {
         Self {
             futex: AtomicUsize::new(0),
+            waiters: AtomicUsize::new(0),
+            mutex: AtomicPtr::new(ptr::null_mut()),
         }
     }
 
@@ -85,7 +92,15 @@ impl Condvar {
 
     pub fn notify_all(&self) {
         self.futex.fetch_add(1, Ordering::Relaxed);
-        futex_wake(&self.futex, usize::MAX);
+
+        if self.waiters.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+
+        // wake up one waiter and requeue the rest onto the mutex,
+        // they wake up one by one as the mutex gets unlocked
+        let mutex = unsafe { &*self.mutex.load(Ordering::SeqCst) };
+        futex_requeue(&self.futex, 1, &mutex.futex, usize::MAX);
     }
 
     pub fn wait<'a, T>(&self, mutex: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
@@ -96,6 +111,17 @@ impl Condvar {
         mutex
     }
 
+    /// returns `true` if the timeout was reached before a notification
+    pub fn wait_timeout<'a, T>(
+        &self,
+        mutex: MutexGuard<'a, T>,
+        timeout: Duration,
+    ) -> (MutexGuard<'a, T>, bool) {
+        let timed_out = unsafe { !self._wait_timeout(MutexGuard::mutex(&mutex).raw(), timeout) };
+
+        (mutex, timed_out)
+    }
+
     unsafe fn _wait(&self, mutex: &Futex) {
         unsafe { self.wait_optional_timeout(mutex, None) };
     }
@@ -108,20 +134,31 @@ impl Condvar {
         // Examine the notification counter _before_ we unlock the mutex.
         let futex_value = self.futex.load(Ordering::Relaxed);
 
+        self.waiters.fetch_add(1, Ordering::SeqCst);
+        self.mutex
+            .store(mutex as *const Futex as *mut Futex, Ordering::SeqCst);
+
         // Unlock the mutex before going to sleep.
         unsafe { mutex.unlock() };
 
         // Wait, but only if there hasn't been any
         // notification since we unlocked the mutex.
-        // let r = ..
-        futex_wait(&self.futex, futex_value); // TODO: timeout
-
-        _ = timeout;
-        let r = false;
+        let r = match timeout {
+            Some(timeout) => {
+                let nanos = u64::try_from(timeout.as_nanos()).unwrap_or(u64::MAX);
+                futex_wait_timeout(&self.futex, futex_value, Timeout::After(nanos)).is_ok()
+            }
+            None => {
+                futex_wait(&self.futex, futex_value);
+                true
+            }
+        };
 
         // Lock the mutex again.
         mutex.lock();
 
+        self.waiters.fetch_sub(1, Ordering::SeqCst);
+
         r
     }
 }
@@ -5,24 +5,40 @@
 
 extern crate alloc;
 
-use alloc::{string::String, vec::Vec};
+use alloc::{format, string::String, vec, vec::Vec};
 use core::{
     alloc::Layout,
+    fmt,
     mem::{self, MaybeUninit},
+    ops::Range,
     ptr, slice,
 };
 
 use elf::{
-    abi::{PF_R, PF_W, PF_X, PT_LOAD, PT_TLS},
+    abi::{
+        DT_JMPREL, DT_NULL, DT_PLTREL, DT_PLTRELSZ, DT_RELA, DT_RELASZ, DT_SYMTAB, EM_X86_64,
+        ET_DYN, ET_EXEC, PF_R, PF_W, PF_X, PT_DYNAMIC, PT_INTERP, PT_LOAD, PT_PHDR, PT_TLS,
+        R_X86_64_64, R_X86_64_GLOB_DAT, R_X86_64_JUMP_SLOT, R_X86_64_NONE, R_X86_64_RELATIVE,
+        STB_WEAK,
+    },
+    dynamic::DynamicTable,
     endian::AnyEndian,
+    file::Class,
+    relocation::{Rela, RelaIterator},
     segment::ProgramHeader,
+    symbol::SymbolTable,
     ElfBytes,
 };
 use elf_wrap::*;
-use hyperion_arch::syscall;
+use hyperion_arch::{stack::USER_HEAP_TOP, syscall};
 use hyperion_log::*;
-use hyperion_mem::{is_higher_half, vmm::PageMapImpl};
-use hyperion_scheduler::{exit, proc::Process, process, task, ExitCode};
+use hyperion_mem::vmm::PageMapImpl;
+use hyperion_random::RngCore;
+use hyperion_scheduler::{proc::Process, process, task};
+use hyperion_syscall::{
+    auxv::{AuxEntry, AuxType},
+    err::Error,
+};
 use x86_64::{structures::paging::PageTableFlags, VirtAddr};
 
 //
@@ -33,28 +49,286 @@ mod elf_wrap;
 
 pub struct Loader<'a> {
     parser: ElfBytes<'a, AnyEndian>,
+
+    /// load address of a position independent executable, 0 for the others
+    base: u64,
 }
 
 //
 
-pub struct NoEntryPoint;
+/// why an ELF file cannot be loaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadError {
+    /// the ELF header or the program headers are malformed
+    InvalidHeader,
+    /// not a 64 bit x86_64 executable or position independent executable
+    Unsupported,
+    /// a segment points outside of the file or is smaller in memory than in the file
+    InvalidSegment,
+    /// a segment alignment is not a power of two or does not match its file offset
+    MisalignedSegment,
+    /// two loadable segments use the same pages
+    OverlappingSegments,
+    /// a segment is not in the user half of the address space
+    OutOfRange,
+    /// the entry point is missing or not in an executable segment
+    InvalidEntryPoint,
+    /// a relocation is malformed, unsupported or needs a missing symbol
+    InvalidRelocation,
+    /// the segments could not be allocated
+    OutOfMemory,
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::InvalidHeader => "invalid ELF header",
+            Self::Unsupported => "unsupported ELF type",
+            Self::InvalidSegment => "invalid ELF segment",
+            Self::MisalignedSegment => "misaligned ELF segment",
+            Self::OverlappingSegments => "overlapping ELF segments",
+            Self::OutOfRange => "ELF segment out of the user address space",
+            Self::InvalidEntryPoint => "invalid ELF entry point",
+            Self::InvalidRelocation => "invalid ELF relocation",
+            Self::OutOfMemory => "out of memory",
+        })
+    }
+}
+
+impl From<LoadError> for Error {
+    fn from(value: LoadError) -> Self {
+        match value {
+            LoadError::OutOfMemory => Error::OUT_OF_MEMORY,
+            _ => Error::INVALID_EXECUTABLE,
+        }
+    }
+}
+
+pub type Result<T, E = LoadError> = core::result::Result<T, E>;
+
+//
+
+/// the lowest load address of position independent executables
+const PIE_BASE: u64 = 0x40_0000;
+
+/// the size of the range of random load addresses above [`PIE_BASE`]
+const PIE_RANDOM_RANGE: u64 = 0x4000_0000;
+
+/// the first page is the null pointer guard page
+const USER_BOTTOM: u64 = 0x1000;
 
 //
 
 impl<'a> Loader<'a> {
-    pub fn new(elf_bytes: &'a [u8]) -> Self {
-        Self {
-            parser: ElfBytes::minimal_parse(elf_bytes).expect("TODO: error handling"),
+    /// parse and validate an ELF file, nothing is loaded yet
+    pub fn new(elf_bytes: &'a [u8]) -> Result<Self> {
+        let mut this = Self {
+            parser: ElfBytes::minimal_parse(elf_bytes).map_err(|_| LoadError::InvalidHeader)?,
+            base: 0,
+        };
+        this.validate_header()?;
+
+        // position independent executables are loaded at a random address
+        if this.is_pie() {
+            let random = hyperion_random::next_fast_rng().next_u64() % PIE_RANDOM_RANGE;
+            this.set_base(PIE_BASE + random);
+        }
+
+        this.validate()?;
+        Ok(this)
+    }
+
+    /// load a position independent executable at `base` instead of a random address
+    ///
+    /// does nothing to executables that have a fixed position
+    pub fn with_base(mut self, base: u64) -> Result<Self> {
+        self.set_base(base);
+        self.validate()?;
+        Ok(self)
+    }
+
+    /// the load address of a position independent executable, 0 for the others
+    pub fn base(&self) -> u64 {
+        self.base
+    }
+
+    pub fn is_pie(&self) -> bool {
+        self.parser.ehdr.e_type == ET_DYN
+    }
+
+    fn set_base(&mut self, base: u64) {
+        if !self.is_pie() {
+            return;
+        }
+
+        // every segment has to stay aligned
+        let align = self
+            .parser
+            .segments()
+            .into_iter()
+            .flatten()
+            .filter(|segment| segment.p_type == PT_LOAD)
+            .filter_map(|segment| segment_align(&segment))
+            .fold(0x1000, u64::max);
+
+        self.base = VirtAddr::new(base).align_down(align).as_u64();
+    }
+
+    /// the runtime address of a link time address
+    fn addr(&self, link_addr: u64) -> VirtAddr {
+        VirtAddr::new(self.base + link_addr)
+    }
+
+    /// the program headers, [`Self::validate`] makes sure that there are some
+    fn segments(&self) -> impl Iterator<Item = ProgramHeader> + '_ {
+        self.parser.segments().into_iter().flatten()
+    }
+
+    /// the pages that a segment is loaded to
+    fn segment_pages(&self, segment: &ProgramHeader) -> Range<VirtAddr> {
+        let v_addr = self
+            .addr(segment.p_vaddr)
+            .align_down(segment_align(segment).unwrap_or(0x1000))
+            .align_down(0x1000u64);
+        let v_end = (self.addr(segment.p_vaddr) + segment.p_memsz).align_up(0x1000u64);
+        v_addr..v_end
+    }
+
+    /// check the ELF header and the segment alignments, which don't depend on the load address
+    fn validate_header(&self) -> Result<()> {
+        let ehdr = &self.parser.ehdr;
+        if ehdr.class != Class::ELF64
+            || ehdr.e_machine != EM_X86_64
+            || (ehdr.e_type != ET_EXEC && ehdr.e_type != ET_DYN)
+        {
+            return Err(LoadError::Unsupported);
+        }
+
+        let Some(segments) = self.parser.segments() else {
+            return Err(LoadError::InvalidHeader);
+        };
+
+        for segment in segments.iter() {
+            if segment.p_type != PT_LOAD && segment.p_type != PT_TLS {
+                continue;
+            }
+
+            let align = segment_align(&segment).ok_or(LoadError::MisalignedSegment)?;
+            if segment.p_type == PT_LOAD && (segment.p_vaddr ^ segment.p_offset) & (align - 1) != 0
+            {
+                return Err(LoadError::MisalignedSegment);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// check everything that could make loading the ELF fail or escape the user address space
+    fn validate(&self) -> Result<()> {
+        self.validate_header()?;
+
+        let ehdr = &self.parser.ehdr;
+        let Some(segments) = self.parser.segments() else {
+            return Err(LoadError::InvalidHeader);
+        };
+
+        let mut loaded: Vec<Range<VirtAddr>> = Vec::new();
+        let mut tls = false;
+        for segment in segments.iter() {
+            if segment.p_type == PT_INTERP && self.interpreter().is_none() {
+                return Err(LoadError::InvalidHeader);
+            }
+            if segment.p_type != PT_LOAD && segment.p_type != PT_TLS {
+                continue;
+            }
+
+            if segment.p_filesz > segment.p_memsz || self.parser.segment_data(&segment).is_err() {
+                return Err(LoadError::InvalidSegment);
+            }
+
+            // the whole segment has to be between the null guard page and the user stacks
+            let end = self
+                .base
+                .checked_add(segment.p_vaddr)
+                .and_then(|start| start.checked_add(segment.p_memsz))
+                .and_then(|end| end.checked_next_multiple_of(0x1000))
+                .ok_or(LoadError::OutOfRange)?;
+            if self.base + segment.p_vaddr < USER_BOTTOM || end > USER_HEAP_TOP {
+                return Err(LoadError::OutOfRange);
+            }
+
+            if segment.p_type == PT_TLS {
+                // only one master TLS copy per process
+                if mem::replace(&mut tls, true) {
+                    return Err(LoadError::InvalidSegment);
+                }
+                continue;
+            }
+
+            let pages = self.segment_pages(&segment);
+            if pages.start.as_u64() < USER_BOTTOM {
+                return Err(LoadError::OutOfRange);
+            }
+            if loaded
+                .iter()
+                .any(|other| other.start < pages.end && pages.start < other.end)
+            {
+                return Err(LoadError::OverlappingSegments);
+            }
+            loaded.push(pages);
+        }
+
+        if loaded.is_empty() {
+            return Err(LoadError::InvalidSegment);
+        }
+
+        // the entry point has to be executable
+        let entry = ehdr.e_entry;
+        if entry == 0
+            || !segments.iter().any(|segment| {
+                segment.p_type == PT_LOAD
+                    && segment.p_flags & PF_X != 0
+                    && (segment.p_vaddr..segment.p_vaddr + segment.p_memsz).contains(&entry)
+            })
+        {
+            return Err(LoadError::InvalidEntryPoint);
         }
+
+        Ok(())
     }
 
-    pub fn load(&self) {
-        // TODO: at least some safety with malicious ELFs
+    /// map the segments to the current process and apply the relocations
+    ///
+    /// the ELF was already validated, so this only fails if it runs out of memory
+    /// or has invalid relocations
+    pub fn load(&self) -> Result<()> {
+        self.trace_tls_sections();
+
+        let process = process();
+
+        for segment in self.segments() {
+            self.alloc_segment(&process, segment)?;
+        }
+
+        for segment in self.segments() {
+            self.load_segment(&process, segment)?;
+        }
 
-        let (sections, sections_strtab) = self.parser.section_headers_with_strtab().unwrap();
+        // relocations are applied before the segments become read-only
+        self.relocate()?;
 
-        let sections = sections.unwrap();
-        let sections_strtab = sections_strtab.unwrap();
+        for segment in self.segments() {
+            self.finish_segment(&process, segment);
+        }
+
+        Ok(())
+    }
+
+    fn trace_tls_sections(&self) {
+        let Ok((Some(sections), Some(sections_strtab))) = self.parser.section_headers_with_strtab()
+        else {
+            return;
+        };
 
         for section in sections
             .into_iter()
@@ -78,68 +352,214 @@ impl<'a> Loader<'a> {
                 trace!("{section:?}");
             }
         }
+    }
 
-        let segments = self.parser.segments().expect("TODO:");
+    /// apply the `DT_RELA` and `DT_JMPREL` relocations from `PT_DYNAMIC` of a position
+    /// independent executable, section headers are optional and not trusted
+    ///
+    /// dynamically linked executables are relocated by their interpreter
+    fn relocate(&self) -> Result<()> {
+        if !self.is_pie() || self.interpreter().is_some() {
+            return Ok(());
+        }
 
-        let process = process();
+        let Some(dynamic) = self.segments().find(|segment| segment.p_type == PT_DYNAMIC) else {
+            // nothing to relocate
+            return Ok(());
+        };
+        let (endian, class) = (self.parser.ehdr.endianness, self.parser.ehdr.class);
+        let dynamic = self
+            .parser
+            .segment_data(&dynamic)
+            .map_err(|_| LoadError::InvalidRelocation)?;
+
+        let (mut rela, mut rela_size) = (None, 0);
+        let (mut jmprel, mut jmprel_size) = (None, 0);
+        let (mut pltrel, mut symtab) = (DT_RELA as u64, None);
+        for entry in DynamicTable::new(endian, class, dynamic).iter() {
+            match entry.d_tag {
+                DT_NULL => break,
+                DT_RELA => rela = Some(entry.d_ptr()),
+                DT_RELASZ => rela_size = entry.d_val(),
+                DT_JMPREL => jmprel = Some(entry.d_ptr()),
+                DT_PLTRELSZ => jmprel_size = entry.d_val(),
+                DT_PLTREL => pltrel = entry.d_val(),
+                DT_SYMTAB => symtab = Some(entry.d_ptr()),
+                _ => {}
+            }
+        }
 
-        for segment in segments.iter() {
-            self.alloc_segment(&process, segment);
+        // x86_64 only uses `Elf64_Rela`
+        if jmprel.is_some() && pltrel != DT_RELA as u64 {
+            return Err(LoadError::InvalidRelocation);
         }
 
-        for segment in segments.iter() {
-            self.load_segment(&process, segment);
+        // `PT_DYNAMIC` has no symbol count, the table ends at the end of its segment at the latest
+        let dynsyms = symtab
+            .map(|addr| self.file_data(addr, None))
+            .transpose()?
+            .map(|data| SymbolTable::new(endian, class, data));
+
+        for (addr, size) in [(rela, rela_size), (jmprel, jmprel_size)] {
+            let Some(addr) = addr else {
+                continue;
+            };
+            let relas = self.file_data(addr, Some(size))?;
+            for rela in RelaIterator::new(endian, class, relas) {
+                self.relocate_one(&rela, dynsyms.as_ref())?;
+            }
         }
 
-        for segment in segments.iter() {
-            self.finish_segment(&process, segment);
+        Ok(())
+    }
+
+    /// the file bytes at a link time address, up to the end of the containing `PT_LOAD` segment
+    /// if `len` is `None`
+    fn file_data(&self, addr: u64, len: Option<u64>) -> Result<&'a [u8]> {
+        let segment = self
+            .segments()
+            .find(|segment| {
+                segment.p_type == PT_LOAD
+                    && segment.p_vaddr <= addr
+                    && addr - segment.p_vaddr < segment.p_filesz
+            })
+            .ok_or(LoadError::InvalidRelocation)?;
+        let data = self
+            .parser
+            .segment_data(&segment)
+            .map_err(|_| LoadError::InvalidRelocation)?;
+
+        let data = &data[(addr - segment.p_vaddr) as usize..];
+        match len {
+            Some(len) => data.get(..len as usize).ok_or(LoadError::InvalidRelocation),
+            None => Ok(data),
         }
+    }
+
+    fn relocate_one(&self, rela: &Rela, dynsyms: Option<&SymbolTable<AnyEndian>>) -> Result<()> {
+        let value = match rela.r_type {
+            R_X86_64_NONE => return Ok(()),
+            R_X86_64_RELATIVE => self.base.wrapping_add_signed(rela.r_addend),
+            R_X86_64_64 => self
+                .symbol(rela, dynsyms)?
+                .wrapping_add_signed(rela.r_addend),
+            R_X86_64_GLOB_DAT | R_X86_64_JUMP_SLOT => self.symbol(rela, dynsyms)?,
+            other => {
+                error!("unsupported ELF relocation type {other}");
+                return Err(LoadError::InvalidRelocation);
+            }
+        };
+
+        // relocations can only write to the loaded segments
+        let target = rela
+            .r_offset
+            .checked_add(8)
+            .map(|end| rela.r_offset..end)
+            .filter(|target| {
+                self.segments().any(|segment| {
+                    segment.p_type == PT_LOAD
+                        && segment.p_vaddr <= target.start
+                        && target.end <= segment.p_vaddr + segment.p_memsz
+                })
+            })
+            .ok_or(LoadError::InvalidRelocation)?;
+        let target = self.addr(target.start);
+
+        // relocation targets don't have to be aligned
+        unsafe { target.as_mut_ptr::<u64>().write_unaligned(value) };
+        Ok(())
+    }
 
-        // TODO: reloactions
+    /// the runtime address of the symbol of a relocation
+    fn symbol(&self, rela: &Rela, dynsyms: Option<&SymbolTable<AnyEndian>>) -> Result<u64> {
+        let Some(symbol) = dynsyms.and_then(|symtab| symtab.get(rela.r_sym as usize).ok()) else {
+            error!("ELF relocation symbol {} not found", rela.r_sym);
+            return Err(LoadError::InvalidRelocation);
+        };
+
+        if !symbol.is_undefined() {
+            Ok(self.base + symbol.st_value)
+        } else if symbol.st_bind() == STB_WEAK {
+            // missing weak symbols are null
+            Ok(0)
+        } else {
+            // TODO: dynamic linking
+            error!("ELF relocation needs an undefined symbol");
+            Err(LoadError::InvalidRelocation)
+        }
     }
 
     // pub fn load_tls(&self) {}
 
-    fn alloc_segment(&self, proc: &Process, segment: ProgramHeader) {
-        if segment.p_type != PT_LOAD && segment.p_type != PT_TLS {
-            return;
+    /// the path to the interpreter (the dynamic linker) from `PT_INTERP`
+    pub fn interpreter(&self) -> Option<&'a str> {
+        let segment = self
+            .parser
+            .segments()?
+            .iter()
+            .find(|segment| segment.p_type == PT_INTERP)?;
+
+        let path = self.parser.segment_data(&segment).ok()?;
+        let path = path.split(|b| *b == 0).next()?;
+        core::str::from_utf8(path).ok()
+    }
+
+    /// the runtime address of the program headers, if they are loaded
+    fn phdr(&self) -> Option<VirtAddr> {
+        let segments = self.parser.segments()?;
+        let phoff = self.parser.ehdr.e_phoff;
+
+        if let Some(phdr) = segments.iter().find(|segment| segment.p_type == PT_PHDR) {
+            return Some(self.addr(phdr.p_vaddr));
         }
 
-        let align = segment.p_align;
-        let v_addr = VirtAddr::new(segment.p_vaddr)
-            .align_down(align)
-            .align_down(0x1000u64);
-        let v_end = (VirtAddr::new(segment.p_vaddr) + segment.p_memsz).align_up(0x1000u64);
-        let v_size = v_end - v_addr;
+        // the first segment usually contains the ELF header and the program headers
+        segments
+            .iter()
+            .find(|segment| {
+                segment.p_type == PT_LOAD
+                    && (segment.p_offset..segment.p_offset + segment.p_filesz).contains(&phoff)
+            })
+            .map(|segment| self.addr(segment.p_vaddr + (phoff - segment.p_offset)))
+    }
 
-        if is_higher_half(v_end.as_u64()) {
-            error!("ELF segments cannot be mapped to higher half");
-            exit(ExitCode::CANNOT_EXECUTE);
+    fn alloc_segment(&self, proc: &Process, segment: ProgramHeader) -> Result<()> {
+        if segment.p_type != PT_LOAD && segment.p_type != PT_TLS {
+            return Ok(());
         }
 
+        let Range {
+            start: v_addr,
+            end: v_end,
+        } = self.segment_pages(&segment);
+        let v_size = v_end - v_addr;
+
         proc.alloc_at(v_size as usize / 0x1000, v_addr, PageTableFlags::WRITABLE)
-            .unwrap_or_else(|_| {
-                error!("could not load ELF: out of VMEM, killing process");
-                exit(ExitCode::CANNOT_EXECUTE);
-            });
+            .map_err(|err| {
+                error!("could not load ELF: {err:?}");
+                LoadError::OutOfMemory
+            })
     }
 
-    fn load_segment(&self, proc: &Process, segment: ProgramHeader) {
+    fn load_segment(&self, proc: &Process, segment: ProgramHeader) -> Result<()> {
         if segment.p_type != PT_LOAD && segment.p_type != PT_TLS {
-            return;
+            return Ok(());
         }
 
-        let align = segment.p_align;
-        let v_addr = VirtAddr::new(segment.p_vaddr)
-            .align_down(align)
-            .align_down(0x1000u64);
-        let align_down_offs = segment.p_vaddr - v_addr.as_u64();
-        let v_end = (VirtAddr::new(segment.p_vaddr) + segment.p_memsz).align_up(0x1000u64);
+        let align = segment_align(&segment).ok_or(LoadError::MisalignedSegment)?;
+        let Range {
+            start: v_addr,
+            end: v_end,
+        } = self.segment_pages(&segment);
+        let align_down_offs = self.addr(segment.p_vaddr) - v_addr;
         let v_size = v_end - v_addr;
 
         // debug!("segment phys alloc: {phys:#x} mapped to {alloc:#x}");
 
-        let segment_data = self.parser.segment_data(&segment).expect("TODO:");
+        let segment_data = self
+            .parser
+            .segment_data(&segment)
+            .map_err(|_| LoadError::InvalidSegment)?;
         let segment_alloc: &mut [MaybeUninit<u8>] =
             unsafe { slice::from_raw_parts_mut(v_addr.as_mut_ptr(), v_size as usize) };
 
@@ -169,20 +589,18 @@ impl<'a> Loader<'a> {
         // the scheduler will create copies for each thread
         if segment.p_type == PT_TLS {
             let master_tls = (
-                VirtAddr::new(segment.p_vaddr),
-                // Layout::from_size_align(v_size as _, align as _).unwrap(),
-                Layout::from_size_align(align as _, v_size as _).unwrap(),
+                self.addr(segment.p_vaddr),
+                Layout::from_size_align(v_size as _, align as _)
+                    .map_err(|_| LoadError::MisalignedSegment)?,
             );
-            let mut loaded = false;
-            proc.master_tls.call_once(|| {
-                loaded = true;
-                master_tls
-            });
-
-            if !loaded {
-                todo!()
+            let mut proc_master_tls = proc.master_tls.write();
+            if proc_master_tls.is_some() {
+                return Err(LoadError::InvalidSegment);
             }
+            *proc_master_tls = Some(master_tls);
         }
+
+        Ok(())
     }
 
     fn finish_segment(&self, proc: &Process, segment: ProgramHeader) {
@@ -190,11 +608,10 @@ impl<'a> Loader<'a> {
             return;
         }
 
-        let align = segment.p_align;
-        let v_addr = VirtAddr::new(segment.p_vaddr)
-            .align_down(align)
-            .align_down(0x1000u64);
-        let v_end = (VirtAddr::new(segment.p_vaddr) + segment.p_memsz).align_up(0x1000u64);
+        let Range {
+            start: v_addr,
+            end: v_end,
+        } = self.segment_pages(&segment);
         let flags = Self::flags(segment.p_flags);
 
         // println!("remap as {flags:?}");
@@ -235,13 +652,22 @@ impl<'a> Loader<'a> {
         }
     }
 
-    pub fn finish(self) -> Result<EntryPoint, NoEntryPoint> {
-        let entry = self.parser.ehdr.e_entry;
-        if entry == 0 {
-            Err(NoEntryPoint)
-        } else {
-            Ok(EntryPoint { entry })
+    pub fn finish(self) -> EntryPoint {
+        // validated in `Self::new`
+        let entry = self.base + self.parser.ehdr.e_entry;
+
+        let ehdr = &self.parser.ehdr;
+        let mut auxv = vec![
+            AuxEntry::new(AuxType::PHENT, ehdr.e_phentsize as _),
+            AuxEntry::new(AuxType::PHNUM, ehdr.e_phnum as _),
+            AuxEntry::new(AuxType::PAGESZ, 0x1000),
+            AuxEntry::new(AuxType::ENTRY, entry as _),
+        ];
+        if let Some(phdr) = self.phdr() {
+            auxv.push(AuxEntry::new(AuxType::PHDR, phdr.as_u64() as _));
         }
+
+        EntryPoint { entry, auxv }
     }
 }
 
@@ -249,53 +675,69 @@ impl<'a> Loader<'a> {
 
 pub struct EntryPoint {
     entry: u64,
+    auxv: Vec<AuxEntry>,
 }
 
 impl EntryPoint {
-    pub fn enter(&self, name: String, args: Vec<String>) {
+    /// start from the interpreter entry point instead,
+    /// the interpreter then jumps to the original entry point from the auxiliary vector
+    pub fn set_interpreter(&mut self, interpreter: EntryPoint, base: u64) {
+        self.entry = interpreter.entry;
+        self.auxv.push(AuxEntry::new(AuxType::BASE, base as _));
+    }
+
+    pub fn enter(&self, name: String, args: Vec<String>, envs: Vec<(String, String)>) {
         // TODO: this is HIGHLY unsafe atm.
 
         let entry = self.entry;
-        trace!("spawning \"{name}\" with args {args:?}");
+        trace!("spawning \"{name}\" with args {args:?} and envs {envs:?}");
 
         let env_args: Vec<&str> = [name.as_str()] // TODO: actually load binaries from vfs
             .into_iter()
             .chain(args.iter().flat_map(|args| args.split(' ')))
             .collect();
 
-        let (stack_top, argv) = Self::init_stack(&env_args);
+        let env_vars: Vec<String> = envs
+            .iter()
+            .map(|(key, value)| format!("{key}={value}"))
+            .collect();
+        let env_vars: Vec<&str> = env_vars.iter().map(String::as_str).collect();
+
+        let (stack_top, argv, envp, auxv) = Self::init_stack(&env_args, &env_vars, &self.auxv);
 
-        // now `name`, `args` and `env_args` can be freed, because they are copied into the stack
-        drop(env_args);
-        drop((name, args));
+        // now `name`, `args`, `envs`, `env_args` and `env_vars` can be freed, because they are copied into the stack
+        drop((env_args, env_vars));
+        drop((name, args, envs));
 
         task().init_tls();
 
-        trace!("Entering userland at 0x{entry:016x} with stack 0x{stack_top:016x} and argv:{argv:#016x}");
-        syscall::userland(VirtAddr::new(entry), stack_top, argv.as_u64(), 0);
+        trace!("Entering userland at 0x{entry:016x} with stack 0x{stack_top:016x}, argv:{argv:#016x}, envp:{envp:#016x} and auxv:{auxv:#016x}");
+        syscall::userland(
+            VirtAddr::new(entry),
+            stack_top,
+            argv.as_u64(),
+            envp.as_u64(),
+            auxv.as_u64(),
+        );
     }
 
-    pub fn init_stack(args: &[&str]) -> (VirtAddr, VirtAddr) {
+    pub fn init_stack(
+        args: &[&str],
+        envs: &[&str],
+        auxv: &[AuxEntry],
+    ) -> (VirtAddr, VirtAddr, VirtAddr, VirtAddr) {
         let mut stack_top = hyperion_scheduler::task().user_stack.lock().top;
 
-        let args_len = args.iter().map(|arg| arg.len()).sum::<usize>();
-        let padding = args_len.next_multiple_of(8) - args_len; // for alignment
-        for _ in 0..padding {
-            push(&mut stack_top, 0u8);
+        // the auxiliary vector goes to the top,
+        // then the environment variables and then the cli args
+        push(&mut stack_top, AuxEntry::NULL);
+        for entry in auxv.iter().rev() {
+            push(&mut stack_top, *entry);
         }
+        let auxv = stack_top;
 
-        for arg in args.iter().rev() {
-            for byte in arg.as_bytes().iter().rev() {
-                push(&mut stack_top, *byte);
-            }
-        }
-
-        for arg in args.iter().rev() {
-            push(&mut stack_top, arg.as_bytes().len());
-        }
-
-        push(&mut stack_top, args.len() as u64);
-        let argv = stack_top;
+        let envp = push_str_list(&mut stack_top, envs);
+        let argv = push_str_list(&mut stack_top, args);
 
         stack_top = stack_top.align_down(0x10u64); // align the stack to 16
 
@@ -307,15 +749,52 @@ impl EntryPoint {
         // so this has to be 'emulated'
         push(&mut stack_top, 0u64);
 
-        (stack_top, argv)
+        (stack_top, argv, envp, auxv)
     }
 }
 
 //
 
+/// push a list of strings to the stack
+///
+/// the layout is: the number of strings, each string length and then all the string bytes
+///
+/// returns a pointer to the number of strings
+pub fn push_str_list(top: &mut VirtAddr, list: &[&str]) -> VirtAddr {
+    let list_len = list.iter().map(|s| s.len()).sum::<usize>();
+    let padding = list_len.next_multiple_of(8) - list_len; // for alignment
+    for _ in 0..padding {
+        push(top, 0u8);
+    }
+
+    for s in list.iter().rev() {
+        for byte in s.as_bytes().iter().rev() {
+            push(top, *byte);
+        }
+    }
+
+    for s in list.iter().rev() {
+        push(top, s.as_bytes().len());
+    }
+
+    push(top, list.len() as u64);
+    *top
+}
+
 /// push items to the stack
 pub fn push<T: Sized>(top: &mut VirtAddr, v: T) {
     *top -= mem::size_of::<T>();
     assert!(top.is_aligned(mem::size_of::<T>() as u64));
     unsafe { top.as_mut_ptr::<T>().write_volatile(v) };
 }
+
+/// the alignment of a segment, `None` if it isn't a power of two
+///
+/// 0 and 1 mean that the segment doesn't need to be aligned, so it is page aligned like the rest
+fn segment_align(segment: &ProgramHeader) -> Option<u64> {
+    match segment.p_align {
+        0 | 1 => Some(0x1000),
+        align if align.is_power_of_two() => Some(align),
+        _ => None,
+    }
+}
@@ -46,7 +46,10 @@ pub fn gen_int_handlers(input: proc_macro::TokenStream) -> proc_macro::TokenStre
         let ident = syn::Ident::new(&format!("int_handler_{i}"), Span::call_site());
         quote! {
             pub extern #ext fn #ident(frame: InterruptStackFrame) {
-                interrupt_handler(#i, frame.instruction_pointer.as_u64() as usize);
+                interrupt_handler(#i, Interrupted {
+                    ip: frame.instruction_pointer.as_u64() as usize,
+                    user: frame.code_segment & 3 == 3,
+                });
             }
         }
     });
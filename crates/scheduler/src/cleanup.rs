@@ -1,7 +1,11 @@
+use alloc::sync::Arc;
+use core::sync::atomic::AtomicU8;
+
+use hyperion_cpu_id::cpu_id;
 use hyperion_instant::Instant;
 use x86_64::PhysAddr;
 
-use crate::{futex, schedule, sleep, task::Task};
+use crate::{futex, process, schedule, sleep, task::Task};
 
 //
 
@@ -12,6 +16,11 @@ pub struct CleanupTask {
 
 impl CleanupTask {
     pub fn run(self) {
+        // threads of the same process keep the same page map loaded
+        if !Arc::ptr_eq(&self.task.process, &process()) {
+            self.task.address_space.page_map.mark_inactive(cpu_id());
+        }
+
         self.cleanup.run(self.task);
     }
 }
@@ -21,7 +30,13 @@ impl CleanupTask {
 #[derive(Debug, Clone, Copy)]
 pub enum Cleanup {
     Sleep { deadline: Instant },
-    Wait { addr: PhysAddr, val: usize },
+    Wait {
+        addr: PhysAddr,
+        val: usize,
+        deadline: Option<Instant>,
+        killable: bool,
+        woken_by: *const AtomicU8,
+    },
     Drop,
     Ready,
 }
@@ -37,7 +52,13 @@ impl Cleanup {
     pub fn run(self, task: Task) {
         match self {
             Self::Sleep { deadline } => sleep::push(deadline, task),
-            Self::Wait { addr, val } => futex::cleanup(addr, val, task),
+            Self::Wait {
+                addr,
+                val,
+                deadline,
+                killable,
+                woken_by,
+            } => futex::cleanup(addr, val, deadline, killable, woken_by, task),
             Self::Drop => {}
             Self::Ready => {
                 schedule(task);
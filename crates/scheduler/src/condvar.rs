@@ -1,11 +1,16 @@
 use core::{
-    sync::atomic::{AtomicUsize, Ordering},
+    ptr,
+    sync::atomic::{AtomicPtr, AtomicUsize, Ordering},
     time::Duration,
 };
 
+use hyperion_instant::Instant;
 use lock_api::{MutexGuard, RawMutex};
 
-use crate::{futex, lock::Futex};
+use crate::{
+    futex::{self, Killed, WaitError},
+    lock::Futex,
+};
 
 //
 
@@ -16,6 +21,10 @@ pub struct Condvar {
     // This is used by `.wait()` to not miss any notifications after
     // unlocking the mutex and before waiting for notifications.
     futex: AtomicUsize,
+    // The number of threads in `.wait()`, they keep `mutex` alive.
+    waiters: AtomicUsize,
+    // The mutex used with this condvar, `.notify_all()` requeues the waiters onto it.
+    mutex: AtomicPtr<Futex>,
 }
 
 impl Condvar {
@@ -23,6 +32,8 @@ impl Condvar {
     pub const fn new() -> Self {
         Self {
             futex: AtomicUsize::new(0),
+            waiters: AtomicUsize::new(0),
+            mutex: AtomicPtr::new(ptr::null_mut()),
         }
     }
 
@@ -36,7 +47,15 @@ impl Condvar {
 
     pub fn notify_all(&self) {
         self.futex.fetch_add(1, Ordering::Relaxed);
-        futex::wake(&self.futex, usize::MAX)
+
+        if self.waiters.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+
+        // wake up one waiter and requeue the rest onto the mutex,
+        // they wake up one by one as the mutex gets unlocked
+        let mutex = unsafe { &*self.mutex.load(Ordering::SeqCst) };
+        futex::requeue(&self.futex, 1, mutex.futex(), usize::MAX)
     }
 
     pub fn wait<'a, T>(&self, mutex: MutexGuard<'a, Futex, T>) -> MutexGuard<'a, Futex, T> {
@@ -47,32 +66,71 @@ impl Condvar {
         mutex
     }
 
+    /// like [`Self::wait`], but the wait also ends if the thread gets killed
+    pub fn wait_killable<'a, T>(
+        &self,
+        mutex: MutexGuard<'a, Futex, T>,
+    ) -> (MutexGuard<'a, Futex, T>, Result<(), Killed>) {
+        let result = unsafe { self.wait_optional_timeout(MutexGuard::mutex(&mutex).raw(), None, true) };
+
+        (mutex, result.map_err(|_| Killed))
+    }
+
+    /// returns `true` if the timeout was reached before a notification
+    pub fn wait_timeout<'a, T>(
+        &self,
+        mutex: MutexGuard<'a, Futex, T>,
+        timeout: Duration,
+    ) -> (MutexGuard<'a, Futex, T>, bool) {
+        let timed_out = unsafe { !self._wait_timeout(MutexGuard::mutex(&mutex).raw(), timeout) };
+
+        (mutex, timed_out)
+    }
+
     unsafe fn _wait(&self, mutex: &Futex) {
-        unsafe { self.wait_optional_timeout(mutex, None) };
+        _ = unsafe { self.wait_optional_timeout(mutex, None, false) };
     }
 
     unsafe fn _wait_timeout(&self, mutex: &Futex, timeout: Duration) -> bool {
-        unsafe { self.wait_optional_timeout(mutex, Some(timeout)) }
+        unsafe { self.wait_optional_timeout(mutex, Some(timeout), false) }.is_ok()
     }
 
-    unsafe fn wait_optional_timeout(&self, mutex: &Futex, timeout: Option<Duration>) -> bool {
+    unsafe fn wait_optional_timeout(
+        &self,
+        mutex: &Futex,
+        timeout: Option<Duration>,
+        killable: bool,
+    ) -> Result<(), WaitError> {
         // Examine the notification counter _before_ we unlock the mutex.
         let futex_value = self.futex.load(Ordering::Relaxed);
 
+        self.waiters.fetch_add(1, Ordering::SeqCst);
+        self.mutex
+            .store(mutex as *const Futex as *mut Futex, Ordering::SeqCst);
+
         // Unlock the mutex before going to sleep.
         unsafe { mutex.unlock() };
 
         // Wait, but only if there hasn't been any
         // notification since we unlocked the mutex.
-        // let r = ..
-        futex::wait(&self.futex, futex_value); // TODO: timeout
-
-        _ = timeout;
-        let r = false;
+        let deadline = timeout.map(|timeout| {
+            Instant::new(
+                Instant::now()
+                    .nanosecond()
+                    .saturating_add(timeout.as_nanos()),
+            )
+        });
+        let r = if killable {
+            futex::wait_killable(&self.futex, futex_value, deadline)
+        } else {
+            futex::wait_until(&self.futex, futex_value, deadline).map_err(WaitError::from)
+        };
 
         // Lock the mutex again.
         mutex.lock();
 
+        self.waiters.fetch_sub(1, Ordering::SeqCst);
+
         r
     }
 }
@@ -2,64 +2,182 @@ use alloc::collections::{BTreeMap, VecDeque};
 use core::{
     mem::ManuallyDrop,
     ptr::NonNull,
-    sync::atomic::{AtomicUsize, Ordering},
+    sync::atomic::{AtomicU64, AtomicU8, AtomicUsize, Ordering},
 };
 
+use hyperion_arch::int;
+use hyperion_instant::Instant;
 use hyperion_mem::{to_higher_half, vmm::PageMapImpl};
 use x86_64::{PhysAddr, VirtAddr};
 
 use crate::{
     cleanup::Cleanup,
-    process,
+    proc::Process,
+    process, push_ready, sleep, task,
     task::{switch_because, Task, TaskState},
-    wait_next_task_while, READY,
+    wait_next_task_while,
 };
 
 //
 
+/// the deadline of a futex wait was reached before it was woken up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimedOut;
+
+/// the waiting thread was killed before it was woken up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Killed;
+
+/// why a killable futex wait ended before it was woken up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitError {
+    TimedOut,
+    Killed,
+}
+
+impl From<TimedOut> for WaitError {
+    fn from(_: TimedOut) -> Self {
+        Self::TimedOut
+    }
+}
+
+impl From<Killed> for WaitError {
+    fn from(_: Killed) -> Self {
+        Self::Killed
+    }
+}
+
+//
+
 /// if the value at `addr` is eq `val`, go to sleep
 pub fn wait(addr: &AtomicUsize, val: usize) {
+    _ = wait_until(addr, val, None);
+}
+
+/// if the value at `addr` is eq `val`, go to sleep until woken up or until `deadline`
+pub fn wait_until(
+    addr: &AtomicUsize,
+    val: usize,
+    deadline: Option<Instant>,
+) -> Result<(), TimedOut> {
+    wait_inner(addr, val, deadline, false).map_err(|_| TimedOut)
+}
+
+/// like [`wait_until`], but the wait also ends if the thread gets killed,
+/// so that it can return from the syscall and exit
+pub fn wait_killable(
+    addr: &AtomicUsize,
+    val: usize,
+    deadline: Option<Instant>,
+) -> Result<(), WaitError> {
+    wait_inner(addr, val, deadline, true)
+}
+
+fn wait_inner(
+    addr: &AtomicUsize,
+    val: usize,
+    deadline: Option<Instant>,
+    killable: bool,
+) -> Result<(), WaitError> {
+    if killable && task().is_killed() {
+        return Err(WaitError::Killed);
+    }
+
     if addr.load(Ordering::SeqCst) != val {
-        return;
+        return Ok(());
+    }
+
+    let is_reached = || deadline.is_some_and(|deadline| deadline.is_reached());
+    if is_reached() {
+        return Err(WaitError::TimedOut);
     }
 
-    let next = wait_next_task_while(|| should_cancel(addr, val).then_some(()));
+    let next = wait_next_task_while(|| {
+        if should_cancel(addr, val) {
+            Some(Ok(()))
+        } else if is_reached() {
+            Some(Err(WaitError::TimedOut))
+        } else if killable && task().is_killed() {
+            Some(Err(WaitError::Killed))
+        } else {
+            None
+        }
+    });
+
+    let next = match next {
+        Ok(next) => next,
+        Err(result) => return result,
+    };
 
-    if let Ok(next) = next {
-        let addr: NonNull<AtomicUsize> = addr.into();
+    // set by the timeout or the kill before this task is woken up
+    let woken_by = AtomicU8::new(WOKEN);
 
-        let addr = process()
-            .address_space
-            .page_map
-            .virt_to_phys(VirtAddr::from_ptr(addr.as_ptr()))
-            .unwrap();
+    switch_because(
+        next,
+        TaskState::Sleeping,
+        Cleanup::Wait {
+            addr: phys_addr(addr),
+            val,
+            deadline,
+            killable,
+            woken_by: &woken_by,
+        },
+    );
 
-        switch_because(next, TaskState::Sleeping, Cleanup::Wait { addr, val })
+    match woken_by.load(Ordering::SeqCst) {
+        TIMED_OUT => Err(WaitError::TimedOut),
+        KILLED => Err(WaitError::Killed),
+        _ => Ok(()),
     }
 }
 
 /// wake up threads waiting for events on this `addr`
 pub fn wake(addr: &AtomicUsize, num: usize) {
-    let addr = process()
-        .address_space
-        .page_map
-        .virt_to_phys(VirtAddr::from_ptr(addr.as_ptr()))
-        .unwrap();
+    WAITING.pop(phys_addr(addr).as_u64() as usize, num);
+}
 
-    WAITING.pop(addr.as_u64() as usize, num);
+/// wake up `num_wake` threads waiting on `addr`
+/// and move up to `num_requeue` of the rest to wait on `to` instead
+///
+/// a condvar broadcast can wake one thread and requeue the rest onto the mutex
+pub fn requeue(addr: &AtomicUsize, num_wake: usize, to: &AtomicUsize, num_requeue: usize) {
+    WAITING.requeue(
+        phys_addr(addr).as_u64() as usize,
+        num_wake,
+        phys_addr(to).as_u64() as usize,
+        num_requeue,
+    );
+}
+
+/// wake up the killable waits of the killed threads of `process`
+pub fn wake_killed(process: &Process) {
+    WAITING.wake_killed(process);
 }
 
 /// post switch cleanup
-pub fn cleanup(addr: PhysAddr, val: usize, task: Task) {
+pub fn cleanup(
+    addr: PhysAddr,
+    val: usize,
+    deadline: Option<Instant>,
+    killable: bool,
+    woken_by: *const AtomicU8,
+    task: Task,
+) {
     let var = unsafe { &*to_higher_half(addr).as_ptr::<AtomicUsize>() };
 
-    let cancel = WAITING.push(addr.as_u64() as usize, task, || {
+    // the thread could have been killed after it last checked
+    let killed = killable && task.is_killed();
+
+    let waiter = Waiter::new(task, killable, woken_by);
+    let id = waiter.id;
+
+    let waiting = WAITING.push(addr.as_u64() as usize, waiter, deadline.is_some(), || {
         // cancel the wait if var == val
-        should_cancel(var, val)
+        should_cancel(var, val) || killed
     });
 
-    if let Some(task) = cancel {
-        READY.push(task);
+    if let (true, Some(deadline)) = (waiting, deadline) {
+        sleep::on_deadline(deadline, move || WAITING.time_out(id));
     }
 }
 
@@ -67,61 +185,216 @@ fn should_cancel(var: &AtomicUsize, val: usize) -> bool {
     var.load(Ordering::SeqCst) != val
 }
 
+fn phys_addr(addr: &AtomicUsize) -> PhysAddr {
+    let addr: NonNull<AtomicUsize> = addr.into();
+
+    process()
+        .address_space
+        .page_map
+        .virt_to_phys(VirtAddr::from_ptr(addr.as_ptr()))
+        .unwrap()
+}
+
 //
 
 static WAITING: Waiters = Waiters::new();
 
+const WOKEN: u8 = 0;
+const TIMED_OUT: u8 = 1;
+const KILLED: u8 = 2;
+
 //
 
 struct Waiters {
-    addrs: spin::Mutex<BTreeMap<usize, VecDeque<Waiter>>>,
+    inner: spin::Mutex<WaitersInner>,
+}
+
+struct WaitersInner {
+    addrs: BTreeMap<usize, VecDeque<Waiter>>,
+    /// the current address of each waiter that has a deadline,
+    /// requeueing moves waiters to other addresses
+    timed: BTreeMap<u64, usize>,
 }
 
 impl Waiters {
     pub const fn new() -> Self {
         Self {
-            addrs: spin::Mutex::new(BTreeMap::new()),
+            inner: spin::Mutex::new(WaitersInner {
+                addrs: BTreeMap::new(),
+                timed: BTreeMap::new(),
+            }),
         }
     }
 
-    pub fn push(&self, addr: usize, task: Task, unless: impl FnOnce() -> bool) -> Option<Task> {
-        let mut addrs = self.addrs.lock();
+    /// returns false if the wait got cancelled and the waiter was woken up right away
+    pub fn push(
+        &self,
+        addr: usize,
+        waiter: Waiter,
+        timed: bool,
+        unless: impl FnOnce() -> bool,
+    ) -> bool {
+        // the timeouts lock this from the timer interrupt
+        int::without(|| {
+            let mut inner = self.inner.lock();
 
-        if unless() {
-            return Some(task);
-        }
+            if unless() {
+                drop(inner);
+                drop(waiter);
+                return false;
+            }
 
-        addrs.entry(addr).or_default().push_back(Waiter::new(task));
+            if timed {
+                inner.timed.insert(waiter.id, addr);
+            }
+            inner.addrs.entry(addr).or_default().push_back(waiter);
 
-        None
+            true
+        })
     }
 
     pub fn pop(&self, addr: usize, count: usize) {
-        let mut addrs = self.addrs.lock();
+        let woken = int::without(|| {
+            let mut inner = self.inner.lock();
+            let woken = inner.take(addr, count);
+            for waiter in &woken {
+                inner.timed.remove(&waiter.id);
+            }
+            woken
+        });
+
+        // dropping the waiters wakes them up
+        drop(woken);
+    }
+
+    pub fn requeue(&self, addr: usize, num_wake: usize, to: usize, num_requeue: usize) {
+        let woken = int::without(|| {
+            let mut inner = self.inner.lock();
+            let woken = inner.take(addr, num_wake);
+            for waiter in &woken {
+                inner.timed.remove(&waiter.id);
+            }
+
+            let moved = inner.take(addr, num_requeue);
+            for waiter in &moved {
+                if let Some(waiter_addr) = inner.timed.get_mut(&waiter.id) {
+                    *waiter_addr = to;
+                }
+            }
+            if !moved.is_empty() {
+                inner.addrs.entry(to).or_default().extend(moved);
+            }
+
+            woken
+        });
+
+        drop(woken);
+    }
+
+    /// wake up a waiter because its deadline was reached, unless it was already woken up
+    pub fn time_out(&self, id: u64) {
+        let waiter = int::without(|| {
+            let mut inner = self.inner.lock();
+            let addr = inner.timed.remove(&id)?;
+            let waiting_on_addr = inner.addrs.get_mut(&addr)?;
+            let i = waiting_on_addr.iter().position(|waiter| waiter.id == id)?;
+            let waiter = waiting_on_addr.remove(i);
+            if waiting_on_addr.is_empty() {
+                inner.addrs.remove(&addr);
+            }
+            waiter
+        });
+
+        if let Some(waiter) = waiter {
+            waiter.woken_by(TIMED_OUT);
+        }
+    }
+
+    /// wake up the killable waiters that are killed threads of `process`
+    pub fn wake_killed(&self, process: &Process) {
+        let is_killed = |waiter: &Waiter| {
+            waiter.killable
+                && core::ptr::eq(&*waiter.task.process, process)
+                && waiter.task.is_killed()
+        };
+
+        let killed = int::without(|| {
+            let mut inner = self.inner.lock();
+            let mut killed = VecDeque::new();
+            inner.addrs.retain(|_, waiting_on_addr| {
+                let mut i = 0;
+                while i < waiting_on_addr.len() {
+                    if is_killed(&waiting_on_addr[i]) {
+                        killed.extend(waiting_on_addr.remove(i));
+                    } else {
+                        i += 1;
+                    }
+                }
+                !waiting_on_addr.is_empty()
+            });
+            for waiter in &killed {
+                inner.timed.remove(&waiter.id);
+            }
+            killed
+        });
+
+        for waiter in killed {
+            waiter.woken_by(KILLED);
+        }
+    }
+}
+
+impl WaitersInner {
+    /// take the last `count` waiters on `addr`
+    fn take(&mut self, addr: usize, count: usize) -> VecDeque<Waiter> {
+        let Some(waiting_on_addr) = self.addrs.get_mut(&addr) else {
+            return VecDeque::new();
+        };
 
-        if let Some(waiting_on_addr) = addrs.get_mut(&addr) {
-            let new_len = waiting_on_addr.len().saturating_sub(count);
-            waiting_on_addr.truncate(new_len);
+        let new_len = waiting_on_addr.len().saturating_sub(count);
+        let taken = waiting_on_addr.split_off(new_len);
+        if waiting_on_addr.is_empty() {
+            self.addrs.remove(&addr);
         }
+        taken
     }
 }
 
 //
 
 struct Waiter {
+    id: u64,
+    /// the wait ends if the task gets killed
+    killable: bool,
+    /// points to the stack of the sleeping task
+    woken_by: *const AtomicU8,
     task: ManuallyDrop<Task>,
 }
 
+// SAFETY: `woken_by` is only used while the task is sleeping
+unsafe impl Send for Waiter {}
+
 impl Waiter {
-    pub const fn new(task: Task) -> Self {
+    pub fn new(task: Task, killable: bool, woken_by: *const AtomicU8) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
         Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            killable,
+            woken_by,
             task: ManuallyDrop::new(task),
         }
     }
+
+    /// tell the task why it was woken up, and then wake it up
+    fn woken_by(self, reason: u8) {
+        // SAFETY: the waiting task is still sleeping, so its stack is still there
+        unsafe { (*self.woken_by).store(reason, Ordering::SeqCst) };
+    }
 }
 
 impl Drop for Waiter {
     fn drop(&mut self) {
-        READY.push(unsafe { ManuallyDrop::take(&mut self.task) })
+        push_ready(unsafe { ManuallyDrop::take(&mut self.task) })
     }
 }
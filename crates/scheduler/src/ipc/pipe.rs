@@ -1,6 +1,12 @@
 use alloc::sync::Arc;
 
-use crate::{condvar::Condvar, lock::Mutex};
+use hyperion_events::readiness::{Listeners, Readiness};
+use ringbuf::{Consumer, HeapRb, Producer, Rb};
+
+use crate::{
+    condvar::Condvar,
+    lock::{Mutex, MutexGuard},
+};
 
 //
 
@@ -36,6 +42,11 @@ impl<T> Sender<T> {
         self.inner.send(item)
     }
 
+    /// send without blocking
+    pub fn try_send(&self, item: T) -> Result<Option<T>, Closed> {
+        self.inner.try_send(item)
+    }
+
     pub fn wait_closed(&self) {
         self.inner.recv_closed();
     }
@@ -43,6 +54,20 @@ impl<T> Sender<T> {
     pub fn close(&mut self) {
         self.inner.close()
     }
+
+    /// `send` wouldn't block
+    pub fn is_ready(&self) -> bool {
+        self.inner.is_send_ready()
+    }
+
+    /// see [`Readiness::listen`]
+    pub fn listen(&self, listeners: &mut Listeners) {
+        self.inner.readiness.listen(listeners);
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.inner.is_closed()
+    }
 }
 
 impl<T: Copy> Sender<T> {
@@ -54,6 +79,11 @@ impl<T: Copy> Sender<T> {
     pub fn send_slice(&self, data: &[T]) -> Result<(), Closed> {
         self.inner.send_slice(data)
     }
+
+    /// send as much as fits without blocking
+    pub fn try_send_slice(&self, data: &[T]) -> Result<usize, Closed> {
+        self.inner.try_send_slice(data)
+    }
 }
 
 impl<T> Drop for Sender<T> {
@@ -73,6 +103,11 @@ impl<T> Receiver<T> {
         self.inner.recv()
     }
 
+    /// receive without blocking
+    pub fn try_recv(&self) -> Result<Option<T>, Closed> {
+        self.inner.try_recv()
+    }
+
     pub fn wait_closed(&self) {
         self.inner.send_closed();
     }
@@ -80,6 +115,20 @@ impl<T> Receiver<T> {
     pub fn close(&mut self) {
         self.inner.close()
     }
+
+    /// `recv` wouldn't block
+    pub fn is_ready(&self) -> bool {
+        self.inner.is_recv_ready()
+    }
+
+    /// see [`Readiness::listen`]
+    pub fn listen(&self, listeners: &mut Listeners) {
+        self.inner.readiness.listen(listeners);
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.inner.is_closed()
+    }
 }
 
 impl<T: Copy> Receiver<T> {
@@ -91,6 +140,11 @@ impl<T: Copy> Receiver<T> {
     pub fn recv_slice(&self, buf: &mut [T]) -> Result<usize, Closed> {
         self.inner.recv_slice(buf)
     }
+
+    /// receive what is available without blocking
+    pub fn try_recv_slice(&self, buf: &mut [T]) -> Result<usize, Closed> {
+        self.inner.try_recv_slice(buf)
+    }
 }
 
 impl<T> Drop for Receiver<T> {
@@ -101,6 +155,16 @@ impl<T> Drop for Receiver<T> {
 
 //
 
+/// wait for the other end, a killed thread gets [`Closed`]
+/// and exits before it returns to user space
+fn wait<'a>(condvar: &Condvar, closed: MutexGuard<'a, bool>) -> Result<MutexGuard<'a, bool>, Closed> {
+    let (closed, killed) = condvar.wait_killable(closed);
+    killed.map_err(|_| Closed)?;
+    Ok(closed)
+}
+
+//
+
 pub type Pipe = Channel<u8>;
 
 impl Pipe {
@@ -122,12 +186,18 @@ pub struct Channel<T> {
     /// the actual data channel
     pub send: Mutex<ringbuf::HeapProducer<T>>,
     pub recv: Mutex<ringbuf::HeapConsumer<T>>,
+    /// lockless view of the buffer for readiness checks,
+    /// blocked senders and receivers keep holding the locks above
+    rb: Arc<HeapRb<T>>,
 
     pub send_wait: Condvar,
     pub recv_wait: Condvar,
 
     pub send_closed: Mutex<bool>,
     pub recv_closed: Mutex<bool>,
+
+    /// wakes up the pollers of this channel
+    pub readiness: Readiness,
     // pub n_send: AtomicUsize,
     // pub n_recv: AtomicUsize,
 }
@@ -135,18 +205,23 @@ pub struct Channel<T> {
 impl<T> Channel<T> {
     pub fn new(capacity: usize) -> Self {
         // TODO: custom allocator
-        let (send, recv) = ringbuf::HeapRb::new(capacity).split();
+        let rb = Arc::new(HeapRb::new(capacity));
+        // SAFETY: there is only one producer and one consumer, like `HeapRb::split`
+        let (send, recv) = unsafe { (Producer::new(rb.clone()), Consumer::new(rb.clone())) };
         let (send, recv) = (Mutex::new(send), Mutex::new(recv));
 
         Self {
             send,
             recv,
+            rb,
 
             send_wait: Condvar::new(),
             recv_wait: Condvar::new(),
 
             send_closed: Mutex::new(false),
             recv_closed: Mutex::new(false),
+
+            readiness: Readiness::new(),
             // n_send: AtomicUsize::new(0),
             // n_recv: AtomicUsize::new(0),
         }
@@ -167,23 +242,46 @@ impl<T> Channel<T> {
 
             if let Err(overflow) = stream.push(item) {
                 self.send_wait.notify_one();
-                r_closed = self.recv_wait.wait(r_closed);
+                r_closed = wait(&self.recv_wait, r_closed)?;
 
                 // keep trying to send the item
                 item = overflow;
             } else {
                 self.send_wait.notify_one();
+                self.readiness.notify();
                 return Ok(());
             };
         }
     }
 
+    /// send without blocking, `Ok(Some(item))` gives the item back if the channel is full
+    ///
+    /// another sender blocking on a full channel also means that the channel is full
+    pub fn try_send(&self, item: T) -> Result<Option<T>, Closed> {
+        if *self.recv_closed.lock() {
+            return Err(Closed);
+        }
+
+        let Some(mut stream) = self.send.try_lock() else {
+            return Ok(Some(item));
+        };
+
+        if let Err(overflow) = stream.push(item) {
+            return Ok(Some(overflow));
+        }
+
+        self.send_wait.notify_one();
+        self.readiness.notify();
+        Ok(None)
+    }
+
     pub fn recv(&self) -> Result<T, Closed> {
         let mut stream = self.recv.lock();
         let mut s_closed = self.send_closed.lock();
         loop {
             if let Some(item) = stream.pop() {
                 self.recv_wait.notify_one();
+                self.readiness.notify();
                 return Ok(item);
             } else {
                 if *s_closed {
@@ -191,11 +289,32 @@ impl<T> Channel<T> {
                 }
 
                 self.recv_wait.notify_one();
-                s_closed = self.send_wait.wait(s_closed);
+                s_closed = wait(&self.send_wait, s_closed)?;
             }
         }
     }
 
+    /// receive without blocking, `Ok(None)` if there is nothing to receive
+    ///
+    /// another receiver blocking on an empty channel also means that there is nothing to receive
+    pub fn try_recv(&self) -> Result<Option<T>, Closed> {
+        let Some(mut stream) = self.recv.try_lock() else {
+            return Ok(None);
+        };
+
+        if let Some(item) = stream.pop() {
+            self.recv_wait.notify_one();
+            self.readiness.notify();
+            return Ok(Some(item));
+        }
+
+        if *self.send_closed.lock() {
+            return Err(Closed);
+        }
+
+        Ok(None)
+    }
+
     /// wait for the sender to be closed
     pub fn send_closed(&self) {
         let mut s_closed = self.send_closed.lock();
@@ -220,11 +339,27 @@ impl<T> Channel<T> {
         }
     }
 
-    fn close(&self) {
+    /// items can be received without blocking
+    pub fn is_recv_ready(&self) -> bool {
+        self.rb.len() != 0 || self.is_closed()
+    }
+
+    /// items can be sent without blocking
+    pub fn is_send_ready(&self) -> bool {
+        self.rb.free_len() != 0 || self.is_closed()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        *self.send_closed.lock() || *self.recv_closed.lock()
+    }
+
+    /// close both ends, everything blocked on this channel wakes up
+    pub fn close(&self) {
         *self.send_closed.lock() = true;
         *self.recv_closed.lock() = true;
-        self.send_wait.notify_one();
-        self.recv_wait.notify_one();
+        self.send_wait.notify_all();
+        self.recv_wait.notify_all();
+        self.readiness.notify();
     }
 }
 
@@ -250,13 +385,59 @@ where
             data = &data[sent..];
 
             self.send_wait.notify_one();
+            if sent != 0 {
+                self.readiness.notify();
+            }
 
             if data.is_empty() {
                 return Ok(());
             }
 
-            r_closed = self.recv_wait.wait(r_closed);
+            r_closed = wait(&self.recv_wait, r_closed)?;
+        }
+    }
+
+    /// send as much as fits without blocking, `Ok(0)` if the channel is full
+    pub fn try_send_slice(&self, data: &[T]) -> Result<usize, Closed> {
+        if *self.recv_closed.lock() {
+            return Err(Closed);
+        }
+
+        let Some(mut stream) = self.send.try_lock() else {
+            return Ok(0);
+        };
+
+        let sent = stream.push_slice(data);
+        if sent != 0 {
+            self.send_wait.notify_one();
+            self.readiness.notify();
         }
+
+        Ok(sent)
+    }
+
+    /// receive what is available without blocking, `Ok(0)` if the channel is empty
+    pub fn try_recv_slice(&self, buf: &mut [T]) -> Result<usize, Closed> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let Some(mut stream) = self.recv.try_lock() else {
+            return Ok(0);
+        };
+
+        let count = stream.pop_slice(buf);
+        if count != 0 {
+            self.recv_wait.notify_one();
+            self.readiness.notify();
+            return Ok(count);
+        }
+
+        if *self.send_closed.lock() {
+            return Err(Closed);
+        }
+
+        Ok(0)
     }
 
     pub fn recv_slice(&self, buf: &mut [T]) -> Result<usize, Closed> {
@@ -270,6 +451,9 @@ where
             let count = stream.pop_slice(buf);
 
             self.recv_wait.notify_one();
+            if count != 0 {
+                self.readiness.notify();
+            }
 
             if count != 0 {
                 return Ok(count);
@@ -279,7 +463,7 @@ where
                 return Err(Closed);
             }
 
-            s_closed = self.send_wait.wait(s_closed);
+            s_closed = wait(&self.send_wait, s_closed)?;
         }
     }
 }
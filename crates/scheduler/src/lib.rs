@@ -3,32 +3,37 @@
 
 //
 
-use alloc::sync::Arc;
+use alloc::{boxed::Box, collections::BTreeMap, sync::Arc};
 use core::{
     any::type_name_of_val,
     cell::{Cell, UnsafeCell},
     convert::Infallible,
-    mem::{offset_of, swap},
+    mem::swap,
     ops::Deref,
     ptr,
-    sync::atomic::{AtomicBool, AtomicPtr, AtomicU64, Ordering},
+    sync::atomic::{AtomicBool, AtomicPtr, AtomicU64, AtomicUsize, Ordering},
 };
 
 use arcstr::ArcStr;
-use crossbeam_queue::SegQueue;
 use hyperion_arch::{cpu::ints, int, stack::AddressSpace, vmm::PageMap};
-use hyperion_cpu_id::Tls;
-use hyperion_driver_acpi::{apic, hpet::HPET};
+use hyperion_cpu_id::{cpu_count, cpu_id, Tls};
+use hyperion_driver_acpi::{
+    apic::{self, Ipi, IpiTarget},
+    hpet::HPET,
+};
 use hyperion_instant::Instant;
+use hyperion_interrupts::Interrupted;
 use hyperion_log::*;
 use hyperion_mem::vmm::PageMapImpl;
+use hyperion_syscall::{limit::Resource, signal::Signal};
 use spin::{Mutex, Once};
 use time::Duration;
 use x86_64::VirtAddr;
 
 use crate::{
     cleanup::{Cleanup, CleanupTask},
-    proc::{Pid, Process},
+    futex::{Killed, WaitError},
+    proc::{Pid, Process, NICE_0_WEIGHT, NO_TID},
     task::{switch_because, Task, TaskInner, TaskState},
 };
 
@@ -50,38 +55,73 @@ mod page_fault;
 
 //
 
-pub static READY: SegQueue<Task> = SegQueue::new();
 pub static RUNNING: AtomicBool = AtomicBool::new(false);
 pub static ROUND_ROBIN: AtomicBool = AtomicBool::new(false);
 
+/// the process of the first kernel thread
+static INIT: Once<Arc<Process>> = Once::new();
+
 //
 
+/// how a process ended, either with an exit code or terminated by a signal
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
-pub struct ExitCode(pub i64);
+pub struct ExitCode {
+    /// the exit code, `128 + signal` if terminated by a signal
+    pub code: i64,
+    /// the signal that terminated the process, 0 if it exited normally
+    pub signal: usize,
+}
 
 impl ExitCode {
-    pub const CANNOT_EXECUTE: Self = Self(126);
-    pub const COMMAND_NOT_FOUND: Self = Self(127);
-    pub const FATAL_SIGSEGV: Self = Self(139);
-    pub const INVALID_SYSCALL: Self = Self(140);
+    pub const CANNOT_EXECUTE: Self = Self::new(126);
+    pub const COMMAND_NOT_FOUND: Self = Self::new(127);
+    /// like a SIGKILL from running out of memory
+    pub const OUT_OF_MEMORY: Self = Self::from_signal(9);
+    pub const FATAL_SIGSEGV: Self = Self::from_signal(11);
+    pub const INVALID_SYSCALL: Self = Self::from_signal(31);
+
+    /// the exit code of a process that exited normally
+    pub const fn new(code: i64) -> Self {
+        Self { code, signal: 0 }
+    }
+
+    /// the exit code of a process terminated by a signal
+    pub const fn from_signal(signal: usize) -> Self {
+        Self {
+            code: 128 + signal as i64,
+            signal,
+        }
+    }
+
+    /// test if the process was terminated by a signal
+    pub const fn is_fatal(self) -> bool {
+        self.signal != 0
+    }
 }
 
 //
 
 pub fn idle() -> impl Iterator<Item = Duration> {
     tls_iter().map(|tls| {
-        fn _assert_sync<T: Sync>(_: T) {}
-        fn _assert(tls: SchedulerTls) {
-            _assert_sync(tls.idle_time);
-        }
+        let idle_time = tls_field(tls, |tls| unsafe { ptr::addr_of!((*tls).idle_time) });
+        Duration::nanoseconds(idle_time.load(Ordering::Relaxed) as _)
+    })
+}
 
-        let tls = tls.get();
-        // SAFETY: idle_time field is Sync
-        let idle_time =
-            unsafe { &*((tls as usize + offset_of!(SchedulerTls, idle_time)) as *const AtomicU64) };
-        // let idle_time = &unsafe { &*tls }.idle_time;
+/// ready queue statistics of one CPU
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunQueueStats {
+    /// tasks waiting in the queue
+    pub ready: usize,
+    /// tasks this CPU has stolen from the other CPUs
+    pub migrations: u64,
+}
 
-        Duration::nanoseconds(idle_time.load(Ordering::Relaxed) as _)
+/// ready queue statistics of each CPU, indexed by the CPU id
+pub fn run_queue_stats() -> impl Iterator<Item = RunQueueStats> {
+    run_queues().iter().map(|queue| RunQueueStats {
+        ready: queue.len(),
+        migrations: queue.migrations.load(Ordering::Relaxed),
     })
 }
 
@@ -104,8 +144,17 @@ pub fn init(thread: impl FnOnce() + Send + 'static) -> ! {
         exit(ExitCode::FATAL_SIGSEGV);
     });
 
+    // other CPUs interrupt this one when the process running here gets killed or stopped
+    // threads interrupted in the kernel exit on their way back to user space instead
+    Ipi::Kill.handler().store(|from| {
+        wait_if_stopped(from);
+        if from.user {
+            exit_if_killed();
+        }
+    });
+
     // init periodic APIC timer interrutpts (optionally for RR-scheduling)
-    apic::APIC_TIMER_HANDLER.store(|| {
+    apic::APIC_TIMER_HANDLER.store(|from| {
         hyperion_events::timer::wake();
 
         if tls().idle.load(Ordering::Acquire) {
@@ -113,12 +162,15 @@ pub fn init(thread: impl FnOnce() + Send + 'static) -> ! {
             return;
         }
 
-        // FIXME: inter-processor interrupts instead
-        if let Some(code) = process().exit_code.get() {
-            exit(*code);
+        wait_if_stopped(from);
+        if from.user {
+            exit_if_killed();
         }
 
-        if ROUND_ROBIN.load(Ordering::Relaxed) {
+        // preempt the current task once it has had more than its fair share,
+        // on every tick with round robin scheduling, or once its affinity doesn't allow this CPU
+        if should_preempt() || ROUND_ROBIN.load(Ordering::Relaxed) || !task().can_run_on(cpu_id())
+        {
             yield_now();
         }
     });
@@ -126,7 +178,6 @@ pub fn init(thread: impl FnOnce() + Send + 'static) -> ! {
     // init `Once` in TLS
     _ = crate::task();
 
-    static INIT: Once<Arc<Process>> = Once::new();
     let init = INIT
         .call_once(|| {
             let process = Process::new(
@@ -156,13 +207,25 @@ pub fn init(thread: impl FnOnce() + Send + 'static) -> ! {
 pub fn yield_now() {
     update_cpu_usage();
 
-    let Some(next) = next_task() else {
+    // a task that may not run on this CPU has to leave, even if there is nothing else to run
+    let next = if task().can_run_on(cpu_id()) {
+        next_task()
+    } else {
+        Some(next_task().or_else(steal_task).unwrap_or_else(idle_thread))
+    };
+    let Some(next) = next else {
         // no tasks -> keep the current task running
         return;
     };
     switch_because(next, TaskState::Ready, Cleanup::Ready);
 }
 
+/// a kernel thread that just waits for the next task on this CPU and exits
+fn idle_thread() -> Task {
+    let init = INIT.get().expect("the scheduler is running").clone();
+    Task::thread(init, || {})
+}
+
 pub fn yield_now_wait() {
     update_cpu_usage();
 
@@ -187,6 +250,16 @@ pub fn sleep_until(deadline: Instant) {
     switch_because(next, TaskState::Sleeping, Cleanup::Sleep { deadline });
 }
 
+/// like [`sleep_until`], but the sleep also ends if the thread gets killed
+pub fn sleep_until_killable(deadline: Instant) -> Result<(), Killed> {
+    // nothing wakes this up, only the deadline or a kill
+    let never = AtomicUsize::new(0);
+    match futex::wait_killable(&never, 0, Some(deadline)) {
+        Err(WaitError::Killed) => Err(Killed),
+        Ok(()) | Err(WaitError::TimedOut) => Ok(()),
+    }
+}
+
 /// destroy the current thread
 /// and switch to another thread
 pub fn done() -> ! {
@@ -203,10 +276,10 @@ pub fn done() -> ! {
 pub fn exit(code: ExitCode) -> ! {
     update_cpu_usage();
 
-    // FIXME: trigger an IPI on all cpu's running for this process
-    process().exit_code.call_once(|| code); // won't set the exit code again if exit is called twice
+    process().kill(code); // won't set the exit code again if exit is called twice
 
     force_close_thread();
+    process().zombify();
 
     switch_because(wait_next_task(), TaskState::Dropping, Cleanup::Drop);
     unreachable!("a destroyed thread cannot continue executing");
@@ -224,6 +297,7 @@ pub fn spawn_userspace(fn_ptr: u64, fn_arg: u64) {
             stack_top,
             stack_top.as_u64(),
             fn_arg,
+            0,
         );
     });
 }
@@ -234,17 +308,64 @@ pub fn fork(f: impl FnOnce() + Send + 'static) -> Pid {
     schedule(task().fork(f))
 }
 
+/// replace the active process image
+///
+/// clears the user memory and runs the closure in a new main thread,
+/// the active thread is destroyed
+pub fn exec(f: impl FnOnce() + Send + 'static) -> ! {
+    process().clear_user_memory();
+
+    // the new thread is spawned before this one is destroyed,
+    // so that the process doesn't get closed in between
+    spawn(f);
+    done();
+}
+
 /// spawn a new process running this closure or a function or a task
 pub fn schedule(new: impl Into<Task>) -> Pid {
     let task = new.into();
     let pid = task.pid;
-    READY.push(task);
+    push_ready(task);
     pid
 }
 
 /// spawn a new thread on the same process
 pub fn spawn(new: impl FnOnce() + Send + 'static) {
-    READY.push(Task::thread(process(), new));
+    push_ready(Task::thread(process(), new));
+}
+
+/// queue a task on the CPU it last ran on, new tasks are queued on this CPU
+/// tasks that may not run on their last CPU go to the first CPU they are allowed on
+fn push_ready(task: Task) {
+    let cpus = cpu_count();
+    let cpu = [task.cpu.load(Ordering::Relaxed), cpu_id()]
+        .into_iter()
+        .chain(0..cpus)
+        .find(|cpu| *cpu < cpus && task.can_run_on(*cpu))
+        .unwrap_or_else(|| cpu_id());
+
+    let queue = &run_queues()[cpu];
+    let from = run_queues().get(task.cpu.load(Ordering::Relaxed));
+
+    // a busy CPU won't get to the task soon, so an idle CPU that can steal it is woken up instead
+    let this = cpu_id();
+    let kick = [cpu]
+        .into_iter()
+        .chain(0..cpus)
+        .filter(|cpu| *cpu != this)
+        .find(|cpu| is_idle(*cpu) && task.can_run_on(*cpu));
+
+    queue.push(task, from);
+
+    if let Some(kick) = kick {
+        apic::send_ipi(IpiTarget::Cpu(kick), Ipi::Wakeup);
+    }
+}
+
+/// the affinity mask of every online CPU
+pub fn online_cpus() -> u64 {
+    1u64.checked_shl(cpu_count() as u32)
+        .map_or(proc::ALL_CPUS, |bit| bit - 1)
 }
 
 fn force_close_thread() {
@@ -283,7 +404,17 @@ fn reset_cpu_timer() {
 fn update_cpu_usage() {
     let elapsed = cpu_time_elapsed();
 
-    task().nanos.fetch_add(elapsed, Ordering::Relaxed);
+    let task = task();
+    task.nanos.fetch_add(elapsed, Ordering::Relaxed);
+    task.vruntime
+        .fetch_add(elapsed * NICE_0_WEIGHT / task.weight(), Ordering::Relaxed);
+
+    // going over the cpu time limit of the whole process is a SIGXCPU, which terminates it
+    let proc = &task.process;
+    let nanos = proc.nanos.fetch_add(elapsed, Ordering::Relaxed) + elapsed;
+    if nanos / 1_000_000_000 >= proc.limit(Resource::CPU_TIME) && proc.exit_code.get().is_none() {
+        proc.kill(ExitCode::from_signal(Signal::XCPU.0));
+    }
 }
 
 fn update_cpu_idle() {
@@ -310,7 +441,8 @@ fn wait_next_task_while<E>(mut should_abort: impl FnMut() -> Option<E>) -> Resul
     update_cpu_usage();
 
     loop {
-        if let Some(task) = next_task() {
+        // balance the load by taking work from the other CPUs when this one runs out
+        if let Some(task) = next_task().or_else(steal_task) {
             return Ok(task);
         }
 
@@ -324,7 +456,111 @@ fn wait_next_task_while<E>(mut should_abort: impl FnMut() -> Option<E>) -> Resul
 }
 
 fn next_task() -> Option<Task> {
-    READY.pop()
+    let this = cpu_id();
+    loop {
+        let task = run_queue().pop()?;
+        if task.can_run_on(this) {
+            return Some(task);
+        }
+
+        // the affinity was changed while the task was waiting
+        push_ready(task);
+    }
+}
+
+/// test if a ready task on this CPU has used less cpu time than the current task
+fn should_preempt() -> bool {
+    update_cpu_usage();
+
+    let current = task().vruntime.load(Ordering::Relaxed);
+    run_queue()
+        .next_vruntime()
+        .is_some_and(|next| next < current)
+}
+
+/// take a task from the CPU with the most ready tasks
+fn steal_task() -> Option<Task> {
+    let this = cpu_id();
+    let others = || {
+        run_queues()
+            .iter()
+            .enumerate()
+            .filter(move |(cpu, _)| *cpu != this)
+            .map(|(_, queue)| queue)
+    };
+    let busiest = others().max_by_key(|queue| queue.len())?;
+
+    // the busiest CPU might only have tasks pinned to it
+    let (from, task) = busiest
+        .steal(this)
+        .map(|task| (busiest, task))
+        .or_else(|| others().find_map(|queue| Some((queue, queue.steal(this)?))))?;
+
+    let queue = run_queue();
+    queue.migrate(&task, from);
+    queue.migrations.fetch_add(1, Ordering::Relaxed);
+    Some(task)
+}
+
+/// exit the process if it was killed, or just this thread if another thread is killing the rest
+pub fn exit_if_killed() {
+    if tls().idle.load(Ordering::Acquire) {
+        // the idle loop isn't running any process
+        return;
+    }
+
+    if let Some(code) = process().exit_code.get() {
+        exit(*code);
+    }
+
+    if task().is_killed() {
+        done();
+    }
+}
+
+/// park the current thread while its process is stopped,
+/// threads interrupted in the kernel stop on their way back to user space instead
+fn wait_if_stopped(from: Interrupted) {
+    if !from.user {
+        return;
+    }
+
+    let proc = process();
+    if let Some(ext) = proc.ext.get() {
+        ext.wait_if_stopped(&proc);
+    }
+}
+
+/// kill every other thread of the current process and wait until they are gone
+///
+/// fails if this thread got killed meanwhile, it should return to [`exit_if_killed`]
+pub fn kill_other_threads() -> Result<(), Killed> {
+    let this = task();
+    let proc = process();
+
+    if proc
+        .only_thread
+        .compare_exchange(NO_TID, this.tid.num(), Ordering::SeqCst, Ordering::SeqCst)
+        .is_err()
+    {
+        // another thread is already killing the rest, this one included
+        return Err(Killed);
+    }
+
+    proc.interrupt_threads();
+
+    let result = loop {
+        let threads = proc.threads.load(Ordering::SeqCst);
+        if threads <= 1 {
+            break Ok(());
+        }
+        if let Err(WaitError::Killed) = futex::wait_killable(&proc.threads, threads, None) {
+            break Err(Killed);
+        }
+    };
+
+    proc.only_thread.store(NO_TID, Ordering::SeqCst);
+    result
 }
 
 fn wait() {
@@ -355,6 +591,89 @@ struct SchedulerTls {
     switch_last_active: AtomicPtr<TaskInner>,
 }
 
+/// the tasks ready to run on one CPU, the other CPUs steal from it when they run out
+struct RunQueue {
+    /// ready tasks ordered by their virtual runtime and then by the order they were pushed in
+    ready: Mutex<BTreeMap<(u64, u64), Task>>,
+    /// the virtual runtime of the latest task taken from this queue
+    min_vruntime: AtomicU64,
+    /// tasks stolen from the other CPUs
+    migrations: AtomicU64,
+}
+
+impl RunQueue {
+    const fn new() -> Self {
+        Self {
+            ready: Mutex::new(BTreeMap::new()),
+            min_vruntime: AtomicU64::new(0),
+            migrations: AtomicU64::new(0),
+        }
+    }
+
+    /// queue a task, `from` is the queue of the CPU it last ran on
+    fn push(&self, task: Task, from: Option<&RunQueue>) {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+
+        if let Some(from) = from.filter(|from| !ptr::eq(*from, self)) {
+            self.migrate(&task, from);
+        }
+
+        // new and woken up tasks start from where the others are,
+        // so that they don't get to make up for the time they didn't run
+        let min_vruntime = self.min_vruntime.load(Ordering::Relaxed);
+        let vruntime = task
+            .vruntime
+            .fetch_max(min_vruntime, Ordering::Relaxed)
+            .max(min_vruntime);
+
+        let key = (vruntime, NEXT.fetch_add(1, Ordering::Relaxed));
+        // the timer interrupt also wakes up tasks
+        int::without(|| self.ready.lock().insert(key, task));
+    }
+
+    /// the virtual runtimes of different CPUs are not comparable, so a task moving from `from`
+    /// to this queue keeps how far ahead of the minimum virtual runtime it was
+    fn migrate(&self, task: &Task, from: &RunQueue) {
+        let lead = task
+            .vruntime
+            .load(Ordering::Relaxed)
+            .saturating_sub(from.min_vruntime.load(Ordering::Relaxed));
+        task.vruntime.store(
+            self.min_vruntime.load(Ordering::Relaxed) + lead,
+            Ordering::Relaxed,
+        );
+    }
+
+    /// take the task with the lowest virtual runtime
+    fn pop(&self) -> Option<Task> {
+        let (_, task) = int::without(|| self.ready.lock().pop_first())?;
+        self.min_vruntime
+            .fetch_max(task.vruntime.load(Ordering::Relaxed), Ordering::Relaxed);
+        Some(task)
+    }
+
+    /// take the task that would run last and is allowed to run on `cpu`
+    fn steal(&self, cpu: usize) -> Option<Task> {
+        int::without(|| {
+            let mut ready = self.ready.lock();
+            let key = *ready
+                .iter()
+                .rev()
+                .find(|(_, task)| task.can_run_on(cpu))?
+                .0;
+            ready.remove(&key)
+        })
+    }
+
+    fn next_vruntime(&self) -> Option<u64> {
+        int::without(|| self.ready.lock().first_key_value().map(|((vruntime, _), _)| *vruntime))
+    }
+
+    fn len(&self) -> usize {
+        int::without(|| self.ready.lock().len())
+    }
+}
+
 impl SchedulerTls {
     fn set_cleanup_task(&self, task: CleanupTask) {
         let old = self.after.replace(Some(task));
@@ -387,6 +706,35 @@ fn tls_iter() -> impl Iterator<Item = &'static UnsafeCell<SchedulerTls>> {
     Tls::inner(tls()).iter()
 }
 
+/// the ready queues of all CPUs, indexed by the CPU id
+static RUN_QUEUES: Once<Box<[RunQueue]>> = Once::new();
+
+fn run_queues() -> &'static [RunQueue] {
+    RUN_QUEUES.call_once(|| (0..cpu_count()).map(|_| RunQueue::new()).collect())
+}
+
+/// the ready queue of this CPU
+fn run_queue() -> &'static RunQueue {
+    &run_queues()[cpu_id()]
+}
+
+/// test if a CPU is waiting for tasks
+fn is_idle(cpu: usize) -> bool {
+    tls_iter().nth(cpu).is_some_and(|tls| {
+        tls_field(tls, |tls| unsafe { ptr::addr_of!((*tls).idle) }).load(Ordering::SeqCst)
+    })
+}
+
+/// a field of any CPU's TLS struct, only `Sync` fields can be shared with the other CPUs
+fn tls_field<T: Sync>(
+    tls: &'static UnsafeCell<SchedulerTls>,
+    field: fn(*const SchedulerTls) -> *const T,
+) -> &'static T {
+    // SAFETY: the TLS structs are never freed and the field is Sync,
+    // a reference to the whole struct is never made because it isn't
+    unsafe { &*field(tls.get()) }
+}
+
 fn tls_try() -> Option<&'static SchedulerTls> {
     TLS.get().map(|s| s.deref())
 }
@@ -31,6 +31,11 @@ impl Futex {
             lock: AtomicUsize::new(UNLOCKED),
         }
     }
+
+    /// the futex word, waiters can be requeued onto it
+    pub(crate) fn futex(&self) -> &AtomicUsize {
+        &self.lock
+    }
 }
 
 unsafe impl lock_api::RawMutex for Futex {
@@ -28,6 +28,12 @@ pub fn page_fault_handler(instr: usize, addr: usize, user: Privilege) -> PageFau
     let pid = current.pid;
     let tid = current.tid;
 
+    // handled user page faults allocate a page, either lazily or for copy-on-write
+    if user == Privilege::User && current.check_resident(1).is_err() {
+        warn!("OUT OF MEMORY (PID:{pid} TID:{tid}) resident pages limit reached #{addr:#x} @{instr:#x}");
+        exit(ExitCode::OUT_OF_MEMORY);
+    }
+
     current
         .process
         .address_space
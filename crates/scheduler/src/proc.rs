@@ -8,16 +8,22 @@ use core::{
     alloc::Layout,
     any::Any,
     fmt,
-    sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    sync::atomic::{AtomicI32, AtomicU64, AtomicUsize, Ordering},
 };
 
 use arcstr::ArcStr;
 use hyperion_arch::stack::{AddressSpace, USER_HEAP_TOP};
+use hyperion_cpu_id::cpu_id;
+use hyperion_driver_acpi::apic::{self, Ipi, IpiTarget};
 use hyperion_mem::vmm::{MapTarget, PageMapImpl};
+use hyperion_syscall::{
+    err::Error,
+    limit::{Limit, Resource},
+};
 use spin::{Mutex, Once, RwLock};
 use x86_64::{structures::paging::PageTableFlags, VirtAddr};
 
-use crate::ExitCode;
+use crate::{futex, ExitCode};
 
 //
 
@@ -75,12 +81,39 @@ pub struct Process {
     /// process id
     pub pid: Pid,
 
+    /// parent process id, the process that created this one
+    pub ppid: Pid,
+
+    /// kernel processes are created without a parent, like the init process,
+    /// they never wait for their children, so no zombies are kept for them
+    pub kernel: bool,
+
+    /// process group id, signals can be sent to whole process groups
+    pub pgid: AtomicUsize,
+
+    /// session id, the pid of the session leader
+    pub sid: AtomicUsize,
+
+    /// the foreground process group of the controlling terminal,
+    /// only used if this process is a session leader
+    pub foreground: AtomicUsize,
+
+    /// user id, 0 is root and bypasses file permission checks
+    pub uid: AtomicUsize,
+
+    /// group id
+    pub gid: AtomicUsize,
+
     /// next thread id
     pub next_tid: AtomicUsize,
 
     /// number of threads in this process
     pub threads: AtomicUsize,
 
+    /// the only thread allowed to keep running, the rest are killed,
+    /// [`NO_TID`] if all threads may run
+    pub only_thread: AtomicUsize,
+
     /// process name
     pub name: RwLock<ArcStr>,
 
@@ -98,9 +131,20 @@ pub struct Process {
     /// process heap beginning, the end of the user process
     pub heap_bottom: AtomicUsize,
 
+    /// resource limits, inherited by new processes
+    pub limits: Limits,
+
+    /// the nice value, from [`NICE_MIN`] (the highest priority) to [`NICE_MAX`],
+    /// inherited by new processes
+    pub nice: AtomicI32,
+
+    /// the CPUs the threads of this process may run on, bit n is CPU n,
+    /// inherited by new processes
+    pub affinity: AtomicU64,
+
     /// TLS object data, each thread allocates one into the userspace
     /// and the $fs segment register should be set to point to it
-    pub master_tls: Once<(VirtAddr, Layout)>,
+    pub master_tls: RwLock<Option<(VirtAddr, Layout)>>,
 
     /// extra process info added by the kernel (like file descriptors)
     pub ext: Once<Box<dyn ProcessExt + 'static>>,
@@ -108,14 +152,18 @@ pub struct Process {
     /// exit code if the process already exit
     pub exit_code: crate::lock::Once<ExitCode>,
 
-    pub should_terminate: AtomicBool,
+    /// child processes and the exit codes of the exited ones,
+    /// exited children are kept as zombies until they are waited for
+    pub children: Mutex<BTreeMap<Pid, Option<ExitCode>>>,
+
+    /// incremented when a child exits or gets stopped, waiting for children waits on this
+    pub child_events: AtomicUsize,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AllocErr {
     OutOfVirtMem,
-    // TODO:
-    // OutOfMem,
+    OutOfMem,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -126,26 +174,176 @@ pub enum FreeErr {
 
 impl Process {
     pub fn new(pid: Pid, name: ArcStr, address_space: AddressSpace) -> Arc<Self> {
+        // processes created before the scheduler is running have no parent
+        let parent = crate::running().then(crate::process);
+        let ppid = parent.as_ref().map_or(Pid::new(0), |parent| parent.pid);
+
+        // the process group and the session are inherited from the parent
+        let pgid = parent.as_ref().map_or(pid, |parent| parent.pgid());
+        let sid = parent.as_ref().map_or(pid, |parent| parent.sid());
+        let limits = parent
+            .as_ref()
+            .map_or_else(Limits::new, |parent| parent.limits.clone());
+        let nice = parent.as_ref().map_or(0, |parent| parent.nice());
+        let affinity = parent
+            .as_ref()
+            .map_or(ALL_CPUS, |parent| parent.affinity.load(Ordering::Relaxed));
+        // so are the user and group, processes without a parent run as root
+        let (uid, gid) = parent
+            .as_ref()
+            .map_or((0, 0), |parent| (parent.uid(), parent.gid()));
+
         let this = Arc::new(Self {
             pid,
+            ppid,
+            kernel: parent.is_none(),
+            pgid: AtomicUsize::new(pgid.num()),
+            sid: AtomicUsize::new(sid.num()),
+            foreground: AtomicUsize::new(pgid.num()),
+            uid: AtomicUsize::new(uid),
+            gid: AtomicUsize::new(gid),
             next_tid: AtomicUsize::new(0),
             threads: AtomicUsize::new(1),
+            only_thread: AtomicUsize::new(NO_TID),
             name: RwLock::new(name),
             nanos: AtomicU64::new(0),
             address_space,
             virt_mem: AtomicUsize::new(0),
             heap_bottom: AtomicUsize::new(0x1000),
-            master_tls: Once::new(),
+            limits,
+            nice: AtomicI32::new(nice),
+            affinity: AtomicU64::new(affinity),
+            master_tls: RwLock::new(None),
             ext: Once::new(),
             exit_code: crate::lock::Once::new(),
-            should_terminate: AtomicBool::new(false),
+            children: Mutex::new(BTreeMap::new()),
+            child_events: AtomicUsize::new(0),
         });
 
         PROCESSES.lock().insert(this.pid, Arc::downgrade(&this));
 
+        // kernel processes don't wait for their children, those are reaped right away
+        if let Some(parent) = parent.filter(|parent| !parent.kernel) {
+            parent.children.lock().insert(pid, None);
+        }
+
         this
     }
 
+    pub fn pgid(&self) -> Pid {
+        Pid::new(self.pgid.load(Ordering::SeqCst))
+    }
+
+    pub fn sid(&self) -> Pid {
+        Pid::new(self.sid.load(Ordering::SeqCst))
+    }
+
+    pub fn uid(&self) -> usize {
+        self.uid.load(Ordering::SeqCst)
+    }
+
+    pub fn gid(&self) -> usize {
+        self.gid.load(Ordering::SeqCst)
+    }
+
+    /// wake up the threads waiting for the children of this process
+    pub fn child_changed(&self) {
+        self.child_events.fetch_add(1, Ordering::SeqCst);
+        futex::wake(&self.child_events, usize::MAX);
+    }
+
+    /// set the exit code and interrupt the threads of this process,
+    /// so that they exit right away instead of on the next timer tick
+    pub fn kill(&self, code: ExitCode) {
+        self.exit_code.call_once(|| code);
+        self.interrupt_threads();
+    }
+
+    /// wake up the killed threads of this process from killable waits
+    /// and interrupt the other CPUs running this process
+    pub fn interrupt_threads(&self) {
+        futex::wake_killed(self);
+
+        let this = 1u64.checked_shl(cpu_id() as u32).unwrap_or(0);
+        let others = self.address_space.page_map.active_cpus() & !this;
+        if others != 0 {
+            apic::send_ipi(IpiTarget::Cpus(others), Ipi::Kill);
+        }
+    }
+
+    /// leave the exit code for the parent to reap, this process has exited
+    pub(crate) fn zombify(&self) {
+        let code = *self.exit_code.call_once(ExitCode::default);
+
+        let Some(parent) = self.ppid.find() else {
+            return;
+        };
+
+        // already reaped if the entry is gone
+        if let Some(status) = parent.children.lock().get_mut(&self.pid) {
+            *status = Some(code);
+        }
+        parent.child_changed();
+    }
+
+    pub fn nice(&self) -> i32 {
+        self.nice.load(Ordering::Relaxed)
+    }
+
+    /// set the nice value, clamped to `NICE_MIN..=NICE_MAX`
+    pub fn set_nice(&self, nice: i32) {
+        self.nice
+            .store(nice.clamp(NICE_MIN, NICE_MAX), Ordering::Relaxed);
+    }
+
+    /// the share of cpu time the nice value gives, relative to the weight of nice 0
+    pub fn weight(&self) -> u64 {
+        // each nice level is about 10% more or less cpu time than the next one, like in Linux
+        const WEIGHTS: [u64; 40] = [
+            88761, 71755, 56483, 46273, 36291, 29154, 23254, 18705, 14949, 11916, 9548, 7620, 6100,
+            4904, 3906, 3121, 2501, 1991, 1586, 1277, 1024, 820, 655, 526, 423, 335, 272, 215, 172,
+            137, 110, 87, 70, 56, 45, 36, 29, 23, 18, 15,
+        ];
+        WEIGHTS[(self.nice() - NICE_MIN) as usize]
+    }
+
+    /// test if this process runs as root
+    pub fn is_root(&self) -> bool {
+        self.uid() == 0
+    }
+
+    /// the soft limit of a resource
+    pub fn limit(&self, resource: Resource) -> u64 {
+        self.limits.get(resource).map_or(Limit::INFINITY, |limit| limit.cur)
+    }
+
+    /// count `n_bytes` more virtual memory, if the address space limit allows it
+    pub fn reserve_virt_mem(&self, n_bytes: usize) -> Result<(), AllocErr> {
+        let limit = self.limit(Resource::ADDRESS_SPACE);
+        self.virt_mem
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                used.checked_add(n_bytes)
+                    .filter(|used| *used as u64 <= limit)
+            })
+            .map(|_| {})
+            .map_err(|_| AllocErr::OutOfVirtMem)
+    }
+
+    /// test if `n_pages` more pages could be resident without going over the resident pages limit
+    pub fn check_resident(&self, n_pages: usize) -> Result<(), AllocErr> {
+        let resident = self
+            .address_space
+            .page_map
+            .info()
+            .phys_pages
+            .load(Ordering::Relaxed);
+
+        if resident.saturating_add(n_pages) as u64 > self.limit(Resource::RESIDENT) {
+            return Err(AllocErr::OutOfMem);
+        }
+        Ok(())
+    }
+
     pub fn alloc(&self, n_pages: usize, flags: PageTableFlags) -> Result<VirtAddr, AllocErr> {
         let n_bytes = n_pages * 0x1000;
 
@@ -191,6 +389,17 @@ impl Process {
         Ok(())
     }
 
+    /// unmap the whole user half, including the thread stacks, and reset the heap
+    ///
+    /// the calling thread should not return to user space afterwards
+    pub fn clear_user_memory(&self) {
+        self.address_space.page_map.clear_user();
+
+        self.heap_bottom.store(0x1000, Ordering::SeqCst);
+        self.virt_mem.store(0, Ordering::Relaxed);
+        *self.master_tls.write() = None;
+    }
+
     fn alloc_at_keep_heap_bottom(
         &self,
         n_pages: usize,
@@ -199,7 +408,8 @@ impl Process {
     ) -> Result<(), AllocErr> {
         let n_bytes = n_pages * 0x1000;
 
-        self.virt_mem.fetch_add(n_bytes, Ordering::Relaxed);
+        self.check_resident(n_pages)?;
+        self.reserve_virt_mem(n_bytes)?;
         self.address_space
             .page_map
             .map(at..at + n_bytes, MapTarget::LazyAlloc, flags);
@@ -208,6 +418,84 @@ impl Process {
     }
 }
 
+/// [`Process::only_thread`] when no thread is being singled out
+pub const NO_TID: usize = usize::MAX;
+
+/// an affinity mask that allows every CPU
+pub const ALL_CPUS: u64 = u64::MAX;
+
+/// the nice value of the highest priority
+pub const NICE_MIN: i32 = -20;
+
+/// the nice value of the lowest priority
+pub const NICE_MAX: i32 = 19;
+
+/// the weight of nice 0, see [`Process::weight`]
+pub const NICE_0_WEIGHT: u64 = 1024;
+
+/// setrlimit/getrlimit style resource limits of a process
+///
+/// the limits are atomic, so that the timer interrupt can read them without locking
+pub struct Limits {
+    cur: [AtomicU64; Resource::COUNT],
+    max: [AtomicU64; Resource::COUNT],
+    /// one limit is set at a time
+    set: Mutex<()>,
+}
+
+impl Limits {
+    pub const fn new() -> Self {
+        Self {
+            cur: [const { AtomicU64::new(Limit::INFINITY) }; Resource::COUNT],
+            max: [const { AtomicU64::new(Limit::INFINITY) }; Resource::COUNT],
+            set: Mutex::new(()),
+        }
+    }
+
+    pub fn get(&self, resource: Resource) -> Option<Limit> {
+        Some(Limit {
+            cur: self.cur.get(resource.0)?.load(Ordering::Relaxed),
+            max: self.max.get(resource.0)?.load(Ordering::Relaxed),
+        })
+    }
+
+    /// replace a limit with what `f` returns for the old limit
+    pub fn update(
+        &self,
+        resource: Resource,
+        f: impl FnOnce(Limit) -> Result<Limit, Error>,
+    ) -> Result<(), Error> {
+        let _set = self.set.lock();
+        let old = self.get(resource).ok_or(Error::INVALID_ARGUMENT)?;
+        let new = f(old)?;
+        self.cur[resource.0].store(new.cur, Ordering::Relaxed);
+        self.max[resource.0].store(new.max, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clone for Limits {
+    fn clone(&self) -> Self {
+        let _set = self.set.lock();
+        let copy = |limits: &[AtomicU64; Resource::COUNT]| {
+            limits
+                .each_ref()
+                .map(|limit| AtomicU64::new(limit.load(Ordering::Relaxed)))
+        };
+        Self {
+            cur: copy(&self.cur),
+            max: copy(&self.max),
+            set: Mutex::new(()),
+        }
+    }
+}
+
 impl Drop for Process {
     fn drop(&mut self) {
         // hyperion_log::debug!("dropping process '{}'", self.name.get_mut());
@@ -223,4 +511,8 @@ pub trait ProcessExt: Sync + Send {
     /// close everything before the actual process closes,
     /// because there might be no tasks to switch to (and that would keep this open)
     fn close(&self);
+
+    /// block the current thread of `proc` while `proc` is stopped,
+    /// called from interrupts that interrupted user space
+    fn wait_if_stopped(&self, _proc: &Process) {}
 }
@@ -8,51 +8,57 @@ use futures_util::{
 use hyperion_instant::Instant;
 use hyperion_sync::TakeOnce;
 
-use crate::{Task, READY};
+use crate::{push_ready, Task};
 
 //
 
-struct SleepWaker {
-    task: TakeOnce<Task>,
+struct DeadlineWaker<F: FnOnce()> {
+    f: TakeOnce<F>,
 
     // useless data just for an assert:
     #[cfg(debug_assertions)]
     deadline: Instant,
 }
 
-impl ArcWake for SleepWaker {
+impl<F: FnOnce() + Send> ArcWake for DeadlineWaker<F> {
     fn wake_by_ref(arc_self: &Arc<Self>) {
         #[cfg(debug_assertions)]
         assert!(arc_self.deadline.is_reached());
 
-        let Some(task) = arc_self.task.take() else {
+        let Some(f) = arc_self.f.take() else {
             return;
         };
 
-        READY.push(task);
+        f();
     }
 }
 
-impl Drop for SleepWaker {
+impl<F: FnOnce()> Drop for DeadlineWaker<F> {
     fn drop(&mut self) {
-        let Some(task) = self.task.take() else {
+        let Some(f) = self.f.take() else {
             return;
         };
 
         hyperion_log::error!("the waker wasn't woken before dropping it");
-        READY.push(task);
+        f();
     }
 }
 
 //
 
+/// wake up `task` once `deadline` is reached
 pub fn push(deadline: Instant, task: Task) {
+    on_deadline(deadline, move || push_ready(task));
+}
+
+/// run `f` from the timer interrupt once `deadline` is reached
+pub fn on_deadline(deadline: Instant, f: impl FnOnce() + Send + 'static) {
     let mut fut = hyperion_events::timer::sleep_until(deadline);
 
     // poll the future with a custom waker
-    let task = TakeOnce::new(task);
-    let waker = waker(Arc::new(SleepWaker {
-        task,
+    let f = TakeOnce::new(f);
+    let waker = waker(Arc::new(DeadlineWaker {
+        f,
         #[cfg(debug_assertions)]
         deadline,
     }));
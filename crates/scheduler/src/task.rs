@@ -5,7 +5,7 @@ use core::{
     fmt, mem,
     ops::Deref,
     ptr,
-    sync::atomic::{AtomicUsize, Ordering},
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
 };
 
 use arcstr::ArcStr;
@@ -15,6 +15,7 @@ use hyperion_arch::{
     stack::{AddressSpace, KernelStack, Stack, UserStack},
     vmm::PageMap,
 };
+use hyperion_cpu_id::cpu_id;
 use hyperion_log::*;
 use hyperion_mem::vmm::PageMapImpl;
 use hyperion_sync::TakeOnce;
@@ -25,9 +26,10 @@ use x86_64::{
 
 use crate::{
     cleanup::Cleanup,
-    done,
-    proc::{Pid, Process},
-    swap_current, task, tls,
+    done, exit, futex,
+    online_cpus,
+    proc::{Pid, Process, ALL_CPUS, NO_TID},
+    swap_current, task, tls, ExitCode,
 };
 
 //
@@ -37,6 +39,9 @@ pub static TASKS_SLEEPING: AtomicUsize = AtomicUsize::new(0);
 pub static TASKS_READY: AtomicUsize = AtomicUsize::new(0);
 pub static TASKS_DROPPING: AtomicUsize = AtomicUsize::new(0);
 
+/// [`TaskInner::cpu`] of a task that hasn't run yet
+pub const NO_CPU: usize = usize::MAX;
+
 //
 
 pub fn switch_because(next: Task, new_state: TaskState, cleanup: Cleanup) {
@@ -61,6 +66,10 @@ pub fn switch_because(next: Task, new_state: TaskState, cleanup: Cleanup) {
         drop(task);
     }
 
+    // TLB shootdowns for the next address space have to reach this CPU before it is loaded,
+    // the previous one is marked inactive after the switch
+    next.cpu.store(cpu_id(), Ordering::Relaxed);
+    next.address_space.page_map.mark_active(cpu_id());
     let prev = swap_current(next);
     let prev_ctx = prev.context.get();
     if prev.swap_state(new_state) != TaskState::Running {
@@ -141,6 +150,17 @@ pub struct TaskInner {
     /// task state, 'is the task waiting or what?'
     pub state: AtomicCell<TaskState>,
 
+    /// the CPU this task last ran on, it is queued back to the same CPU to keep its caches warm
+    pub cpu: AtomicUsize,
+
+    /// the CPUs this thread may run on, bit n is CPU n,
+    /// ignored if none of them are allowed by the process affinity
+    pub affinity: AtomicU64,
+
+    /// cpu time in nanoseconds scaled by the process weight,
+    /// the ready task with the lowest virtual runtime runs first
+    pub vruntime: AtomicU64,
+
     /// lazy initialized user-space stack
     pub user_stack: Mutex<Stack<UserStack>>,
 
@@ -165,11 +185,41 @@ pub struct TaskInner {
 }
 
 impl TaskInner {
+    /// the CPUs this thread actually may run on, both its own and the process affinity
+    /// are limited to the online CPUs and the thread affinity is ignored if nothing is left
+    pub fn allowed_cpus(&self) -> u64 {
+        let online = online_cpus();
+        let process = self.process.affinity.load(Ordering::Relaxed) & online;
+        let thread = self.affinity.load(Ordering::Relaxed) & process;
+
+        [thread, process, online]
+            .into_iter()
+            .find(|mask| *mask != 0)
+            .unwrap_or(ALL_CPUS)
+    }
+
+    /// test if this thread may run on `cpu`,
+    /// CPUs past the first 64 are only used by unpinned threads
+    pub fn can_run_on(&self, cpu: usize) -> bool {
+        let allowed = self.allowed_cpus();
+        match 1u64.checked_shl(cpu as u32) {
+            Some(bit) => allowed & bit != 0,
+            None => allowed == ALL_CPUS,
+        }
+    }
+
+    /// test if this thread should stop running, because the process exited
+    /// or because another thread is killing the rest
+    pub fn is_killed(&self) -> bool {
+        let only_thread = self.only_thread.load(Ordering::SeqCst);
+        self.exit_code.get().is_some() || (only_thread != NO_TID && only_thread != self.tid.num())
+    }
+
     pub fn init_tls(&self) {
         let fs = self
             .tls
             .try_call_once(|| {
-                let Some((addr, layout)) = self.master_tls.get().copied() else {
+                let Some((addr, layout)) = *self.master_tls.read() else {
                     // master tls doesn't exist, so don't copy it
                     return Err(());
                 };
@@ -196,7 +246,10 @@ impl TaskInner {
                         n_pages,
                         PageTableFlags::USER_ACCESSIBLE | PageTableFlags::WRITABLE,
                     )
-                    .unwrap();
+                    .unwrap_or_else(|err| {
+                        warn!("could not allocate TLS: {err:?}, killing process");
+                        exit(ExitCode::OUT_OF_MEMORY);
+                    });
 
                 let tcb_ptr = tls_copy + (n_pages - 1) * 0x1000;
                 let tls_ptr = tcb_ptr - tls_alloc;
@@ -253,7 +306,12 @@ impl Drop for TaskInner {
 
         // hyperion_log::debug!("dropping task {:?} of '{}'", self.tid, self.name.read());
 
-        self.threads.fetch_sub(1, Ordering::Relaxed);
+        // the last thread is gone, even if the process never called exit
+        if self.threads.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.process.zombify();
+        }
+        // exec waits for the other threads to be gone
+        futex::wake(&self.threads, usize::MAX);
 
         let k_stack = mem::take(&mut self.kernel_stack).into_inner();
         let u_stack = mem::take(&mut self.user_stack).into_inner();
@@ -295,6 +353,9 @@ impl Task {
             tid: Tid::next(&process),
             process,
             state: AtomicCell::new(TaskState::Ready),
+            cpu: AtomicUsize::new(NO_CPU),
+            affinity: AtomicU64::new(ALL_CPUS),
+            vruntime: AtomicU64::new(0),
             user_stack: Mutex::new(user_stack),
             kernel_stack: Mutex::new(kernel_stack),
             job: TakeOnce::new(f),
@@ -324,15 +385,22 @@ impl Task {
 
         let process = Process::new(Pid::next(), name, address_space);
 
+        // the forked address space has a copy of this task's TLS
+        *process.master_tls.write() = *self.master_tls.read();
+        let tls = self.tls.get().map_or_else(Once::new, |tls| Once::initialized(*tls));
+
         TASKS_READY.fetch_add(1, Ordering::Relaxed);
         Self(Arc::new(TaskInner {
             tid: Tid::next(&process),
             process,
             state: AtomicCell::new(TaskState::Ready),
+            cpu: AtomicUsize::new(NO_CPU),
+            affinity: AtomicU64::new(ALL_CPUS),
+            vruntime: AtomicU64::new(0),
             user_stack: Mutex::new(user_stack),
             kernel_stack: Mutex::new(kernel_stack),
             job: TakeOnce::new(f),
-            tls: Once::new(),
+            tls,
             context,
             is_valid: true,
         }))
@@ -364,6 +432,9 @@ impl Task {
             tid: Tid::next(&process),
             process,
             state: AtomicCell::new(TaskState::Ready),
+            cpu: AtomicUsize::new(NO_CPU),
+            affinity: AtomicU64::new(ALL_CPUS),
+            vruntime: AtomicU64::new(0),
             user_stack: Mutex::new(user_stack),
             kernel_stack: Mutex::new(kernel_stack),
             job: TakeOnce::new(f),
@@ -384,7 +455,6 @@ impl Task {
             "bootloader".into(),
             AddressSpace::new(PageMap::current()),
         );
-        process.should_terminate.store(true, Ordering::Release);
 
         let mut kernel_stack = process
             .address_space
@@ -406,6 +476,9 @@ impl Task {
             tid: Tid::next(&process),
             process,
             state: AtomicCell::new(TaskState::Running),
+            cpu: AtomicUsize::new(NO_CPU),
+            affinity: AtomicU64::new(ALL_CPUS),
+            vruntime: AtomicU64::new(0),
             user_stack: Mutex::new(user_stack),
             kernel_stack: Mutex::new(kernel_stack),
             job: TakeOnce::none(),
@@ -0,0 +1,61 @@
+/// the type of an [`AuxEntry`], the same numbers as in the ELF ABI
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct AuxType(pub usize);
+
+impl AuxType {
+    /// the end of the auxiliary vector
+    pub const NULL: Self = Self(0);
+    /// the address of the program headers of the program
+    pub const PHDR: Self = Self(3);
+    /// the size of one program header
+    pub const PHENT: Self = Self(4);
+    /// the number of program headers
+    pub const PHNUM: Self = Self(5);
+    /// the page size
+    pub const PAGESZ: Self = Self(6);
+    /// the load address of the interpreter (the dynamic linker)
+    pub const BASE: Self = Self(7);
+    /// the entry point of the program
+    pub const ENTRY: Self = Self(9);
+}
+
+/// one auxiliary vector entry, the vector ends with [`AuxType::NULL`]
+///
+/// the loader passes the auxiliary vector to the program entry point,
+/// next to the cli args and the environment variables
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct AuxEntry {
+    pub ty: AuxType,
+    pub val: usize,
+}
+
+impl AuxEntry {
+    pub const NULL: Self = Self::new(AuxType::NULL, 0);
+
+    #[must_use]
+    pub const fn new(ty: AuxType, val: usize) -> Self {
+        Self { ty, val }
+    }
+}
+
+/// iterate an auxiliary vector, without the [`AuxType::NULL`] entry
+///
+/// # Safety
+/// `auxv` has to point to an auxiliary vector that ends with [`AuxType::NULL`]
+pub unsafe fn iter(auxv: *const AuxEntry) -> impl Iterator<Item = AuxEntry> {
+    (0..)
+        .map(move |i| unsafe { auxv.add(i).read() })
+        .take_while(|entry| entry.ty != AuxType::NULL)
+}
+
+/// find the value of an auxiliary vector entry (getauxval)
+///
+/// # Safety
+/// see [`iter`]
+pub unsafe fn get(auxv: *const AuxEntry, ty: AuxType) -> Option<usize> {
+    unsafe { iter(auxv) }
+        .find(|entry| entry.ty == ty)
+        .map(|entry| entry.val)
+}
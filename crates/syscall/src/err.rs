@@ -60,6 +60,20 @@ impl_error! {
     pub const IS_A_PIPE: "file descriptor is a pipe/socket" = 24;
     pub const NOT_A_SOCKET: "file descriptor is not a socket" = 25;
 
+    pub const DIRECTORY_NOT_EMPTY: "directory not empty" = 26;
+
+    pub const NOT_CONNECTED: "socket is not connected" = 27;
+
+    pub const WOULD_BLOCK: "operation would block" = 28;
+
+    pub const TIMED_OUT: "operation timed out" = 29;
+
+    pub const LIMIT_EXCEEDED: "resource limit exceeded" = 30;
+
+    pub const INVALID_EXECUTABLE: "invalid executable" = 31;
+
+    pub const NO_CHILD_PROCESSES: "no child processes" = 32;
+
     pub const _: "unknown error" = _;
 }
 
@@ -1,3 +1,5 @@
+use core::{mem, ptr};
+
 use bitflags::bitflags;
 
 //
@@ -13,10 +15,27 @@ impl Seek {
 
 //
 
+/// [`crate::fcntl`] commands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Fcntl(pub usize);
+
+impl Fcntl {
+    /// get the [`FileOpenFlags::NONBLOCK`] and [`FileOpenFlags::CLOEXEC`] flags
+    pub const GET_FLAGS: Self = Fcntl(0);
+    /// set the [`FileOpenFlags::NONBLOCK`] and [`FileOpenFlags::CLOEXEC`] flags
+    pub const SET_FLAGS: Self = Fcntl(1);
+}
+//
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 #[repr(C)]
 pub struct FileDesc(pub usize);
 
+impl FileDesc {
+    /// an invalid file descriptor, marks unused slots
+    pub const NONE: Self = Self(usize::MAX);
+}
+
 //
 
 bitflags! {
@@ -49,15 +68,113 @@ pub struct FileOpenFlags: usize {
 
     /// create all parent directories
     const CREATE_DIRS = 0b1000_0000;
+
+    /// close the file automatically when the process image is replaced with `exec`
+    const CLOEXEC     = 0b1_0000_0000;
+
+    /// operations that would block return [`crate::err::Error::WOULD_BLOCK`] instead
+    const NONBLOCK    = 0b10_0000_0000;
 }
 }
 
+bitflags! {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UnlinkFlags: usize {
+    /// remove an empty directory instead of a file
+    const REMOVE_DIR  = 0b0000_0001;
+}
+}
+
+bitflags! {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct PollEvents: u16 {
+    /// reading wouldn't block
+    const IN          = 0b0000_0001;
+
+    /// writing wouldn't block
+    const OUT         = 0b0000_0010;
+
+    /// the other end was closed, only in `revents`
+    const HUP         = 0b0000_0100;
+
+    /// the file descriptor is invalid, only in `revents`
+    const NVAL        = 0b0000_1000;
+}
+}
+
+/// a single file descriptor for [`crate::poll`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct PollFd {
+    pub fd: FileDesc,
+    /// the events to wait for
+    pub events: PollEvents,
+    /// the events that happened, set by the kernel
+    pub revents: PollEvents,
+}
+
+impl PollFd {
+    #[must_use]
+    pub const fn new(fd: FileDesc, events: PollEvents) -> Self {
+        Self {
+            fd,
+            events,
+            revents: PollEvents::empty(),
+        }
+    }
+}
+
+//
+
+/// the type of a filesystem node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FileKind(pub usize);
+
+impl FileKind {
+    pub const FILE: Self = FileKind(0);
+    pub const DIRECTORY: Self = FileKind(1);
+    pub const DEVICE: Self = FileKind(2);
+    pub const SOCKET: Self = FileKind(3);
+    pub const PIPE: Self = FileKind(4);
+
+    /// the `ls -l` style type character
+    #[must_use]
+    pub const fn as_char(self) -> char {
+        match self {
+            Self::FILE => '-',
+            Self::DIRECTORY => 'd',
+            Self::DEVICE => 'c',
+            Self::SOCKET => 's',
+            Self::PIPE => 'p',
+            _ => '?',
+        }
+    }
+}
+
 //
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Metadata {
     pub len: usize,
     pub position: usize,
+
+    pub kind: FileKind,
+    /// unix style permission bits, like `0o644`
+    pub mode: usize,
+    /// owner user id
+    pub uid: usize,
+    /// owner group id
+    pub gid: usize,
+
+    /// creation time in nanoseconds since boot
+    pub created: u64,
+    /// last modification time in nanoseconds since boot
+    pub modified: u64,
+    /// last access time in nanoseconds since boot
+    pub accessed: u64,
+
+    /// name of the device driver, padded with zeros
+    pub driver: [u8; 16],
 }
 
 impl Metadata {
@@ -66,6 +183,17 @@ impl Metadata {
         Self {
             len: 0,
             position: 0,
+
+            kind: FileKind::FILE,
+            mode: 0,
+            uid: 0,
+            gid: 0,
+
+            created: 0,
+            modified: 0,
+            accessed: 0,
+
+            driver: [0; 16],
         }
     }
 
@@ -83,4 +211,130 @@ impl Metadata {
     pub fn position(&self) -> usize {
         self.position
     }
+
+    #[must_use]
+    pub fn is_dir(&self) -> bool {
+        self.kind == FileKind::DIRECTORY
+    }
+
+    #[must_use]
+    pub fn is_file(&self) -> bool {
+        self.kind == FileKind::FILE
+    }
+
+    #[must_use]
+    pub fn driver(&self) -> &str {
+        let len = self
+            .driver
+            .iter()
+            .position(|b| *b == 0)
+            .unwrap_or(self.driver.len());
+        core::str::from_utf8(&self.driver[..len]).unwrap_or("")
+    }
+
+    /// set the driver name, truncated to 16 bytes
+    pub fn set_driver(&mut self, driver: &str) {
+        let len = driver.len().min(self.driver.len());
+        self.driver = [0; 16];
+        self.driver[..len].copy_from_slice(&driver.as_bytes()[..len]);
+    }
+}
+
+//
+
+/// the fixed layout header of a [`crate::read_dir`] record
+///
+/// each record is the header, followed by `name_len` bytes of the name,
+/// padded to a multiple of 8 bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct DirEntryHeader {
+    /// a unique id of the node, like an inode number
+    pub id: u64,
+    pub kind: usize,
+    pub name_len: usize,
+}
+
+/// a single [`crate::read_dir`] record
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DirEntryRecord<'a> {
+    pub id: u64,
+    pub kind: FileKind,
+    pub name: &'a str,
+}
+
+impl<'a> DirEntryRecord<'a> {
+    /// the encoded size in bytes
+    #[must_use]
+    pub const fn record_len(&self) -> usize {
+        (mem::size_of::<DirEntryHeader>() + self.name.len()).next_multiple_of(8)
+    }
+
+    /// write the record to the start of `buf`
+    ///
+    /// returns the number of bytes written or `None` if it didn't fit
+    pub fn encode(&self, buf: &mut [u8]) -> Option<usize> {
+        let len = self.record_len();
+        let record = buf.get_mut(..len)?;
+
+        let header = DirEntryHeader {
+            id: self.id,
+            kind: self.kind.0,
+            name_len: self.name.len(),
+        };
+        let (header_bytes, rest) = record.split_at_mut(mem::size_of::<DirEntryHeader>());
+        // SAFETY: `header_bytes` is exactly the size of the header
+        unsafe { ptr::write_unaligned(header_bytes.as_mut_ptr().cast(), header) };
+
+        let (name, padding) = rest.split_at_mut(self.name.len());
+        name.copy_from_slice(self.name.as_bytes());
+        padding.fill(0);
+
+        Some(len)
+    }
+
+    /// read a record from the start of `buf`
+    ///
+    /// returns the record and its size in bytes or `None` if it is invalid
+    #[must_use]
+    pub fn decode(buf: &'a [u8]) -> Option<(Self, usize)> {
+        let header_bytes = buf.get(..mem::size_of::<DirEntryHeader>())?;
+        // SAFETY: `header_bytes` is exactly the size of the header and all bit patterns are valid
+        let header: DirEntryHeader = unsafe { ptr::read_unaligned(header_bytes.as_ptr().cast()) };
+
+        let name = buf
+            .get(mem::size_of::<DirEntryHeader>()..)?
+            .get(..header.name_len)?;
+        let record = Self {
+            id: header.id,
+            kind: FileKind(header.kind),
+            name: core::str::from_utf8(name).ok()?,
+        };
+
+        let len = record.record_len().min(buf.len());
+        Some((record, len))
+    }
+}
+
+/// iterator over the records of a [`crate::read_dir`] buffer
+#[derive(Debug, Clone)]
+pub struct DirEntryIter<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> DirEntryIter<'a> {
+    #[must_use]
+    pub const fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+}
+
+impl<'a> Iterator for DirEntryIter<'a> {
+    type Item = DirEntryRecord<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (record, len) = DirEntryRecord::decode(self.buf)?;
+        self.buf = &self.buf[len..];
+        Some(record)
+    }
 }
@@ -1,5 +1,5 @@
 #![no_std]
-#![feature(error_in_core)]
+#![feature(error_in_core, naked_functions)]
 
 //
 
@@ -10,18 +10,25 @@ use core::{
     sync::atomic::AtomicUsize,
 };
 
-use err::Result;
+use err::{Error, Result};
 
 use crate::{
-    fs::{FileDesc, FileOpenFlags, Metadata},
+    fs::{Fcntl, FileDesc, FileOpenFlags, Metadata, PollFd, UnlinkFlags},
+    limit::{Limit, Resource},
+    mem::{MapFlags, Prot},
     net::{Protocol, SocketDomain, SocketType},
+    signal::{SigMaskHow, Signal, SignalHandler, SignalSet},
 };
 
 //
 
+pub mod auxv;
 pub mod err;
 pub mod fs;
+pub mod limit;
+pub mod mem;
 pub mod net;
+pub mod signal;
 
 #[cfg(feature = "rustc-dep-of-std")]
 pub mod libc;
@@ -36,8 +43,8 @@ pub mod id {
     pub const NANOSLEEP_UNTIL: usize = 6;
 
     pub const SPAWN: usize = 8;
-    pub const PALLOC: usize = 9; // TODO: merge into map
-    pub const PFREE: usize = 10; // TODO: merge into unmap
+    pub const PALLOC: usize = 9; // deprecated: use MMAP
+    pub const PFREE: usize = 10; // deprecated: use MUNMAP
     pub const SEND: usize = 11;
     pub const RECV: usize = 12;
     pub const RENAME: usize = 13;
@@ -61,14 +68,62 @@ pub mod id {
     pub const FUTEX_WAIT: usize = 27;
     pub const FUTEX_WAKE: usize = 28;
 
-    pub const MAP_FILE: usize = 29; // TODO: merge into map
-    pub const UNMAP_FILE: usize = 30; // TODO: merge into unmap
+    pub const MAP_FILE: usize = 29; // deprecated: use MMAP
+    pub const UNMAP_FILE: usize = 30; // deprecated: use MUNMAP
     pub const METADATA: usize = 31;
     pub const SEEK: usize = 32;
 
     pub const SYSTEM: usize = 33;
     pub const FORK: usize = 34;
     pub const WAITPID: usize = 35;
+    pub const EXEC: usize = 36;
+
+    pub const CHDIR: usize = 37;
+    pub const GETCWD: usize = 38;
+
+    pub const UNLINK: usize = 39;
+    pub const RENAME_PATH: usize = 40;
+    pub const STAT: usize = 41;
+    pub const READ_DIR: usize = 42;
+
+    pub const SENDTO: usize = 43;
+    pub const RECVFROM: usize = 44;
+    pub const SENDMSG: usize = 45;
+    pub const RECVMSG: usize = 46;
+    pub const POLL: usize = 47;
+    pub const FCNTL: usize = 48;
+
+    pub const MMAP: usize = 49;
+    pub const MUNMAP: usize = 50;
+    pub const MPROTECT: usize = 51;
+
+    pub const KILL: usize = 52;
+    pub const SIGACTION: usize = 53;
+    pub const SIGPROCMASK: usize = 54;
+    pub const SIGRETURN: usize = 55;
+
+    pub const FUTEX_REQUEUE: usize = 56;
+
+    pub const SETPGID: usize = 57;
+    pub const GETPGID: usize = 58;
+    pub const SETSID: usize = 59;
+    pub const TCSETPGRP: usize = 60;
+    pub const TCGETPGRP: usize = 61;
+
+    pub const GETRLIMIT: usize = 62;
+    pub const SETRLIMIT: usize = 63;
+
+    pub const GETUID: usize = 64;
+    pub const SETUID: usize = 65;
+    pub const GETGID: usize = 66;
+    pub const SETGID: usize = 67;
+    pub const CHMOD: usize = 68;
+    pub const CHOWN: usize = 69;
+
+    pub const SET_PRIORITY: usize = 70;
+    pub const GET_PRIORITY: usize = 71;
+    pub const SCHED_SETAFFINITY: usize = 72;
+    pub const SCHED_GETAFFINITY: usize = 73;
 }
 
 //
@@ -82,6 +137,111 @@ pub struct LaunchConfig {
     pub stderr: FileDesc,
 }
 
+/// `KEY=VALUE` environment variables for a new process image
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct EnvList {
+    pub ptr: usize,
+    pub len: usize,
+}
+
+impl EnvList {
+    #[must_use]
+    pub fn new(envs: &[&str]) -> Self {
+        Self {
+            ptr: envs.as_ptr() as usize,
+            len: envs.len(),
+        }
+    }
+}
+
+/// how long a blocking syscall can wait
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timeout {
+    /// wait forever
+    Never,
+    /// wait for some nanoseconds from now
+    After(u64),
+    /// wait until [`timestamp()`] reaches this many nanoseconds
+    At(u64),
+}
+
+impl Timeout {
+    #[must_use]
+    pub const fn as_raw(self) -> (usize, usize) {
+        match self {
+            Self::Never => (0, 0),
+            Self::After(nanos) => (1, nanos as usize),
+            Self::At(nanos) => (2, nanos as usize),
+        }
+    }
+
+    #[must_use]
+    pub const fn from_raw(kind: usize, nanos: usize) -> Option<Self> {
+        match kind {
+            0 => Some(Self::Never),
+            1 => Some(Self::After(nanos as u64)),
+            2 => Some(Self::At(nanos as u64)),
+            _ => None,
+        }
+    }
+}
+
+bitflags::bitflags! {
+    /// [`waitpid_with`] flags
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct WaitFlags: usize {
+        /// also return if the process gets stopped
+        const UNTRACED = 1;
+        /// return immediately if no child has changed its state
+        const NOHANG = 2;
+    }
+}
+
+/// the PID for [`waitpid_with`] that waits for any child process
+pub const WAIT_ANY: usize = usize::MAX;
+
+/// how a waited process changed its state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitStatus {
+    /// the process exited with an exit code
+    Exited(i64),
+    /// the process was terminated by a signal
+    Signaled(Signal),
+    /// the process was stopped by a signal
+    Stopped(Signal),
+}
+
+impl WaitStatus {
+    /// the shell style exit code, `128 + signal` if terminated by a signal
+    #[must_use]
+    pub const fn code(self) -> i64 {
+        match self {
+            Self::Exited(code) => code,
+            Self::Signaled(signal) | Self::Stopped(signal) => 128 + signal.0 as i64,
+        }
+    }
+
+    #[must_use]
+    pub const fn as_raw(self) -> [usize; 2] {
+        match self {
+            Self::Exited(code) => [0, code as usize],
+            Self::Stopped(signal) => [1, signal.0],
+            Self::Signaled(signal) => [2, signal.0],
+        }
+    }
+
+    #[must_use]
+    pub const fn from_raw(raw: [usize; 2]) -> Option<Self> {
+        match raw {
+            [0, code] => Some(Self::Exited(code as i64)),
+            [1, signal] => Some(Self::Stopped(Signal(signal))),
+            [2, signal] => Some(Self::Signaled(Signal(signal))),
+            _ => None,
+        }
+    }
+}
+
 //
 
 macro_rules! syscall {
@@ -304,6 +464,76 @@ pub fn recv(socket: FileDesc, buf: &mut [u8], flags: usize) -> Result<usize> {
     unsafe { syscall_4(id::RECV, socket.0, buf, buf_len, flags) }
 }
 
+/// send a message to a bound `DGRAM` socket
+pub fn sendto(socket: FileDesc, data: &[u8], addr: &str) -> Result<usize> {
+    let (data, data_len) = (data.as_ptr() as usize, data.len());
+    let (addr, addr_len) = (addr.as_ptr() as usize, addr.len());
+    unsafe { syscall_5(id::SENDTO, socket.0, data, data_len, addr, addr_len) }
+}
+
+/// read a message from a socket and the bound address of the sender
+///
+/// the address is written to `addr` padded with zeros,
+/// returns the message length and the address length
+pub fn recvfrom(socket: FileDesc, buf: &mut [u8], addr: &mut [u8]) -> Result<(usize, usize)> {
+    let (buf, buf_len) = (buf.as_ptr() as usize, buf.len());
+    let (addr_ptr, addr_cap) = (addr.as_mut_ptr() as usize, addr.len());
+    let len = unsafe { syscall_5(id::RECVFROM, socket.0, buf, buf_len, addr_ptr, addr_cap) }?;
+    let addr_len = addr.iter().position(|b| *b == 0).unwrap_or(addr.len());
+    Ok((len, addr_len))
+}
+
+/// send a message and file descriptors to the connected peer
+///
+/// the receiver gets its own copies of the file descriptors,
+/// like `SCM_RIGHTS`
+pub fn sendmsg(socket: FileDesc, data: &[u8], fds: &[FileDesc]) -> Result<usize> {
+    let (data, data_len) = (data.as_ptr() as usize, data.len());
+    let (fds, fds_len) = (fds.as_ptr() as usize, fds.len());
+    unsafe { syscall_5(id::SENDMSG, socket.0, data, data_len, fds, fds_len) }
+}
+
+/// read a message and file descriptors sent with [`sendmsg`]
+///
+/// received file descriptors are written to `fds` and the rest is filled with
+/// [`FileDesc::NONE`], returns the message length and the file descriptor count
+pub fn recvmsg(socket: FileDesc, buf: &mut [u8], fds: &mut [FileDesc]) -> Result<(usize, usize)> {
+    let (buf, buf_len) = (buf.as_ptr() as usize, buf.len());
+    let (fds_ptr, fds_cap) = (fds.as_mut_ptr() as usize, fds.len());
+    let len = unsafe { syscall_5(id::RECVMSG, socket.0, buf, buf_len, fds_ptr, fds_cap) }?;
+    let fds_len = fds
+        .iter()
+        .position(|fd| *fd == FileDesc::NONE)
+        .unwrap_or(fds.len());
+    Ok((len, fds_len))
+}
+
+/// wait until any of the file descriptors is ready for its `events`
+///
+/// `revents` is set for each file descriptor, `None` timeout waits forever,
+/// returns the number of ready file descriptors or 0 if the timeout was reached
+pub fn poll(fds: &mut [PollFd], timeout_nanos: Option<u64>) -> Result<usize> {
+    let (fds, fds_len) = (fds.as_mut_ptr() as usize, fds.len());
+    let timeout = timeout_nanos.map_or(usize::MAX, |nanos| nanos as usize);
+    unsafe { syscall_3(id::POLL, fds, fds_len, timeout) }
+}
+
+/// file descriptor control, see [`Fcntl`] for the commands
+pub fn fcntl(file: FileDesc, cmd: Fcntl, arg: usize) -> Result<usize> {
+    unsafe { syscall_3(id::FCNTL, file.0, cmd.0, arg) }
+}
+
+/// make reads and writes return [`Error::WOULD_BLOCK`] instead of blocking
+pub fn set_nonblocking(file: FileDesc, nonblocking: bool) -> Result<()> {
+    let flags = FileOpenFlags::from_bits_truncate(fcntl(file, Fcntl::GET_FLAGS, 0)?);
+    let flags = if nonblocking {
+        flags | FileOpenFlags::NONBLOCK
+    } else {
+        flags - FileOpenFlags::NONBLOCK
+    };
+    fcntl(file, Fcntl::SET_FLAGS, flags.bits()).map(|_| {})
+}
+
 /// get the current process id
 #[must_use]
 pub fn get_pid() -> usize {
@@ -336,7 +566,15 @@ pub fn pipe() -> Result<[FileDesc; 2]> {
 ///
 /// the addr is translated so futexes in inter-process shmem should still work
 pub fn futex_wait(addr: &AtomicUsize, val: usize) {
-    unsafe { syscall_2(id::FUTEX_WAIT, addr as *const _ as usize, val) }.unwrap();
+    futex_wait_timeout(addr, val, Timeout::Never).unwrap();
+}
+
+/// futex wait with a timeout, see [`futex_wait`]
+///
+/// returns [`Error::TIMED_OUT`] if the timeout was reached before a wake up
+pub fn futex_wait_timeout(addr: &AtomicUsize, val: usize, timeout: Timeout) -> Result<()> {
+    let (kind, nanos) = timeout.as_raw();
+    unsafe { syscall_4(id::FUTEX_WAIT, addr as *const _ as usize, val, kind, nanos) }.map(|_| {})
 }
 
 /// wake `num` threads that are sleeping on this `addr`
@@ -346,6 +584,23 @@ pub fn futex_wake(addr: &AtomicUsize, num: usize) {
     unsafe { syscall_2(id::FUTEX_WAKE, addr as *const _ as usize, num) }.unwrap();
 }
 
+/// wake `num_wake` threads that are sleeping on `addr`
+/// and move up to `num_requeue` of the rest to sleep on `to`
+///
+/// see [`futex_wait`]
+pub fn futex_requeue(addr: &AtomicUsize, num_wake: usize, to: &AtomicUsize, num_requeue: usize) {
+    unsafe {
+        syscall_4(
+            id::FUTEX_REQUEUE,
+            addr as *const _ as usize,
+            num_wake,
+            to as *const _ as usize,
+            num_requeue,
+        )
+    }
+    .unwrap();
+}
+
 /// map file contents to memory (mmap)
 ///
 /// maps pages from the file at `align_down(offset, 0x1000)..align_up(offset+size, 0x1000)`
@@ -371,6 +626,76 @@ pub fn unmap_file(file: FileDesc, at: NonNull<()>, size: usize) -> Result<()> {
     unsafe { syscall_3(id::UNMAP_FILE, file.0, at, size) }.map(|_| {})
 }
 
+/// map anonymous memory or file contents to memory
+///
+/// `at` is only a hint unless [`MapFlags::FIXED`] is set, `offset` has to be page aligned
+/// and `file` is ignored if [`MapFlags::ANONYMOUS`] is set
+pub fn mmap(
+    at: Option<NonNull<()>>,
+    size: usize,
+    prot: Prot,
+    flags: MapFlags,
+    file: FileDesc,
+    offset: usize,
+) -> Result<NonNull<()>> {
+    let at = at.map_or(ptr::null_mut(), NonNull::as_ptr) as usize;
+    unsafe { syscall_5(id::MMAP, at, size, flags.pack(prot), file.0, offset) }
+        .map(|ptr| NonNull::new(ptr as _).unwrap())
+}
+
+/// unmap any page aligned range of memory, partially unmapped mappings are split
+pub fn munmap(at: NonNull<()>, size: usize) -> Result<()> {
+    unsafe { syscall_2(id::MUNMAP, at.as_ptr() as usize, size) }.map(|_| {})
+}
+
+/// change the page protections of a page aligned range of mapped memory
+pub fn mprotect(at: NonNull<()>, size: usize, prot: Prot) -> Result<()> {
+    unsafe {
+        syscall_3(
+            id::MPROTECT,
+            at.as_ptr() as usize,
+            size,
+            prot.bits() as usize,
+        )
+    }
+    .map(|_| {})
+}
+
+/// send a signal to a process
+///
+/// signal 0 only checks if the process exists
+pub fn kill(pid: usize, signal: Signal) -> Result<()> {
+    unsafe { syscall_2(id::KILL, pid, signal.0) }.map(|_| {})
+}
+
+/// send a signal to every process in a process group
+pub fn killpg(pgid: usize, signal: Signal) -> Result<()> {
+    // negative pids are process groups
+    unsafe { syscall_2(id::KILL, (pgid as isize).wrapping_neg() as usize, signal.0) }.map(|_| {})
+}
+
+/// set what happens when a signal is delivered to this process, returns the old handler
+///
+/// [`Signal::KILL`] and [`Signal::STOP`] can only use [`SignalHandler::Default`]
+pub fn sigaction(signal: Signal, handler: SignalHandler) -> Result<SignalHandler> {
+    unsafe {
+        syscall_3(
+            id::SIGACTION,
+            signal.0,
+            handler.as_raw(),
+            signal::trampoline as *const () as usize,
+        )
+    }
+    .map(|old| unsafe { SignalHandler::from_raw(old) })
+}
+
+/// change the blocked signal mask of this process, returns the old mask
+///
+/// blocked signals stay pending until they are unblocked
+pub fn sigprocmask(how: SigMaskHow, set: SignalSet) -> Result<SignalSet> {
+    unsafe { syscall_2(id::SIGPROCMASK, how.0, set.0 as usize) }.map(|old| SignalSet(old as u64))
+}
+
 /// file metadata (stat)
 pub fn metadata(file: FileDesc, metadata: &mut Metadata) -> Result<()> {
     unsafe { syscall_2(id::METADATA, file.0, metadata as *mut _ as usize) }.map(|_| {})
@@ -382,6 +707,9 @@ pub fn seek(file: FileDesc, offset: isize, origin: usize) -> Result<()> {
 }
 
 /// launch a process
+///
+/// returns [`Error::NOT_FOUND`] if the binary is missing
+/// and [`Error::INVALID_EXECUTABLE`] if it is not a valid ELF
 pub fn system(path: &str, args: &[&str]) -> Result<usize> {
     unsafe {
         syscall_5(
@@ -396,6 +724,8 @@ pub fn system(path: &str, args: &[&str]) -> Result<usize> {
 }
 
 /// launch a process with config
+///
+/// see [`system`]
 pub fn system_with(path: &str, args: &[&str], cfg: LaunchConfig) -> Result<usize> {
     unsafe {
         syscall_5(
@@ -409,13 +739,271 @@ pub fn system_with(path: &str, args: &[&str], cfg: LaunchConfig) -> Result<usize
     }
 }
 
+/// replace the current process image with a new binary (execve)
+///
+/// the new image inherits the environment variables of the current image,
+/// returns only if the binary could not be executed
+pub fn exec(path: &str, args: &[&str]) -> Error {
+    let result = unsafe {
+        syscall_5(
+            id::EXEC,
+            path.as_ptr() as usize,
+            path.len(),
+            args.as_ptr() as usize,
+            args.len(),
+            0,
+        )
+    };
+    result.expect_err("exec returned without an error")
+}
+
+/// replace the current process image with a new binary and `KEY=VALUE` environment variables (execve)
+///
+/// returns only if the binary could not be executed
+pub fn exec_with_env(path: &str, args: &[&str], envs: &[&str]) -> Error {
+    let envs = EnvList::new(envs);
+    let result = unsafe {
+        syscall_5(
+            id::EXEC,
+            path.as_ptr() as usize,
+            path.len(),
+            args.as_ptr() as usize,
+            args.len(),
+            &envs as *const EnvList as usize,
+        )
+    };
+    result.expect_err("exec returned without an error")
+}
+
 /// fork the current process and return the PID
 pub fn fork() -> usize {
     unsafe { syscall_0(id::FORK) }.unwrap()
 }
 
-/// wait for a PID to exit
-/// TODO: this should be like https://linux.die.net/man/2/waitpid in the future
+/// wait for a child process to exit and reap it, returns its exit code
+///
+/// the exit code is `128 + signal` if the child was terminated by a signal
 pub fn waitpid(pid: usize) -> usize {
-    unsafe { syscall_1(id::WAITPID, pid) }.unwrap()
+    unsafe { syscall_3(id::WAITPID, pid, 0, 0) }.unwrap()
+}
+
+/// wait for a child process to exit or, with [`WaitFlags::UNTRACED`], to get stopped
+///
+/// [`WAIT_ANY`] waits for any child, exited children are reaped,
+/// returns the PID of the child and how it changed, or `None` if [`WaitFlags::NOHANG`]
+/// was given and no child has changed yet
+///
+/// [`Error::NO_CHILD_PROCESSES`] if `pid` is not a child of this process
+pub fn waitpid_with(pid: usize, flags: WaitFlags) -> Result<Option<(usize, WaitStatus)>> {
+    let mut raw = [usize::MAX; 2];
+    let pid = unsafe { syscall_3(id::WAITPID, pid, flags.bits(), &mut raw as *mut _ as usize) }?;
+    if pid == 0 {
+        return Ok(None);
+    }
+    let status = WaitStatus::from_raw(raw).ok_or(Error::INVALID_ARGUMENT)?;
+    Ok(Some((pid, status)))
+}
+
+/// move a process into a process group (setpgid)
+///
+/// pid 0 is the current process and pgid 0 makes a new group with the same id as the process
+pub fn setpgid(pid: usize, pgid: usize) -> Result<()> {
+    unsafe { syscall_2(id::SETPGID, pid, pgid) }.map(|_| {})
+}
+
+/// get the process group of a process, pid 0 is the current process (getpgid)
+pub fn getpgid(pid: usize) -> Result<usize> {
+    unsafe { syscall_1(id::GETPGID, pid) }
+}
+
+/// start a new session and a new process group, returns the session id (setsid)
+///
+/// the current process becomes the session leader,
+/// which fails if it is already a process group leader
+pub fn setsid() -> Result<usize> {
+    unsafe { syscall_0(id::SETSID) }
+}
+
+/// get the soft and hard limits of a resource of this process (getrlimit)
+pub fn getrlimit(resource: Resource) -> Result<Limit> {
+    let mut limit = Limit::UNLIMITED;
+    unsafe { syscall_2(id::GETRLIMIT, resource.0, &mut limit as *mut Limit as usize) }?;
+    Ok(limit)
+}
+
+/// set the soft and hard limits of a resource of this process (setrlimit)
+///
+/// the soft limit cannot be above the hard limit and only root can raise the hard limit,
+/// the limits are inherited by new processes
+pub fn setrlimit(resource: Resource, limit: Limit) -> Result<()> {
+    unsafe {
+        syscall_3(
+            id::SETRLIMIT,
+            resource.0,
+            limit.cur as usize,
+            limit.max as usize,
+        )
+    }
+    .map(|_| {})
+}
+
+/// get the user id of this process (getuid)
+pub fn getuid() -> usize {
+    // SAFETY: this syscall cannot fail, look at the source
+    unsafe { syscall_0(id::GETUID).unwrap_unchecked() }
+}
+
+/// set the user id of this process (setuid)
+///
+/// only root can switch to another user, the user id is inherited by new processes
+pub fn setuid(uid: usize) -> Result<()> {
+    unsafe { syscall_1(id::SETUID, uid) }.map(|_| {})
+}
+
+/// get the group id of this process (getgid)
+pub fn getgid() -> usize {
+    // SAFETY: this syscall cannot fail, look at the source
+    unsafe { syscall_0(id::GETGID).unwrap_unchecked() }
+}
+
+/// set the group id of this process (setgid)
+///
+/// only root can switch to another group, the group id is inherited by new processes
+pub fn setgid(gid: usize) -> Result<()> {
+    unsafe { syscall_1(id::SETGID, gid) }.map(|_| {})
+}
+
+/// set the permission bits of a file or a directory, only the owner and root can do it
+pub fn chmod(path: &str, mode: usize) -> Result<()> {
+    unsafe { syscall_3(id::CHMOD, path.as_ptr() as usize, path.len(), mode) }.map(|_| {})
+}
+
+/// set the owner and the group of a file or a directory, only root can do it
+///
+/// `None` leaves the owner or the group unchanged
+pub fn chown(path: &str, uid: Option<usize>, gid: Option<usize>) -> Result<()> {
+    unsafe {
+        syscall_4(
+            id::CHOWN,
+            path.as_ptr() as usize,
+            path.len(),
+            uid.unwrap_or(usize::MAX),
+            gid.unwrap_or(usize::MAX),
+        )
+    }
+    .map(|_| {})
+}
+
+/// set the nice value of a process (setpriority), pid 0 is the current process
+///
+/// the value is clamped to `-20..=19` and a lower value is a higher priority,
+/// only root can raise the priority or change the processes of other users
+pub fn set_priority(pid: usize, nice: i32) -> Result<()> {
+    unsafe { syscall_2(id::SET_PRIORITY, pid, nice as isize as usize) }.map(|_| {})
+}
+
+/// get the nice value of a process (getpriority), pid 0 is the current process
+pub fn get_priority(pid: usize) -> Result<i32> {
+    // negative results would be errors, so the kernel returns `20 - nice`
+    unsafe { syscall_1(id::GET_PRIORITY, pid) }.map(|prio| 20 - prio as i32)
+}
+
+/// pin a process, or the current thread if pid is 0, to a set of CPUs (sched_setaffinity)
+///
+/// bit n of the mask is CPU n and at least one online CPU has to be included,
+/// a thread can only use the CPUs that its process allows
+pub fn sched_setaffinity(pid: usize, mask: u64) -> Result<()> {
+    unsafe { syscall_2(id::SCHED_SETAFFINITY, pid, mask as usize) }.map(|_| {})
+}
+
+/// get the CPUs a process, or the current thread if pid is 0, may run on (sched_getaffinity)
+pub fn sched_getaffinity(pid: usize) -> Result<u64> {
+    let mut mask = 0u64;
+    unsafe { syscall_2(id::SCHED_GETAFFINITY, pid, &mut mask as *mut u64 as usize) }?;
+    Ok(mask)
+}
+
+/// set the foreground process group of the controlling terminal of the current session
+pub fn tcsetpgrp(pgid: usize) -> Result<()> {
+    unsafe { syscall_1(id::TCSETPGRP, pgid) }.map(|_| {})
+}
+
+/// get the foreground process group of the controlling terminal of a session,
+/// sid 0 is the current session
+pub fn tcgetpgrp(sid: usize) -> Result<usize> {
+    unsafe { syscall_1(id::TCGETPGRP, sid) }
+}
+
+/// change the current working directory
+pub fn chdir(path: &str) -> Result<()> {
+    unsafe { syscall_2(id::CHDIR, path.as_ptr() as usize, path.len()) }.map(|_| {})
+}
+
+/// read the current working directory into `buf`
+///
+/// returns the full length of the path, which can be larger than `buf`
+pub fn getcwd(buf: &mut [u8]) -> Result<usize> {
+    unsafe { syscall_2(id::GETCWD, buf.as_mut_ptr() as usize, buf.len()) }
+}
+
+/// remove a file
+pub fn unlink(path: &str) -> Result<()> {
+    unsafe {
+        syscall_3(
+            id::UNLINK,
+            path.as_ptr() as usize,
+            path.len(),
+            UnlinkFlags::empty().bits(),
+        )
+    }
+    .map(|_| {})
+}
+
+/// remove an empty directory
+pub fn rmdir(path: &str) -> Result<()> {
+    unsafe {
+        syscall_3(
+            id::UNLINK,
+            path.as_ptr() as usize,
+            path.len(),
+            UnlinkFlags::REMOVE_DIR.bits(),
+        )
+    }
+    .map(|_| {})
+}
+
+/// move a file or a directory to another path, fails if the target already exists
+pub fn rename_path(from: &str, to: &str) -> Result<()> {
+    unsafe {
+        syscall_4(
+            id::RENAME_PATH,
+            from.as_ptr() as usize,
+            from.len(),
+            to.as_ptr() as usize,
+            to.len(),
+        )
+    }
+    .map(|_| {})
+}
+
+/// get the metadata of a file or a directory without opening it
+pub fn stat(path: &str, meta: &mut Metadata) -> Result<()> {
+    unsafe {
+        syscall_3(
+            id::STAT,
+            path.as_ptr() as usize,
+            path.len(),
+            meta as *mut Metadata as usize,
+        )
+    }
+    .map(|_| {})
+}
+
+/// read directory entries from a directory opened with [`FileOpenFlags::IS_DIR`]
+///
+/// fills `buf` with [`fs::DirEntryRecord`]s, use [`fs::DirEntryIter`] to read them
+///
+/// returns the number of bytes written, 0 means that there are no more entries
+pub fn read_dir(dir: FileDesc, buf: &mut [u8]) -> Result<usize> {
+    unsafe { syscall_3(id::READ_DIR, dir.0, buf.as_mut_ptr() as usize, buf.len()) }
 }
@@ -0,0 +1,95 @@
+use core::fmt;
+
+//
+
+/// a resource that has a [`Limit`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct Resource(pub usize);
+
+impl Resource {
+    /// virtual memory in bytes
+    pub const ADDRESS_SPACE: Self = Self(0);
+    /// physical memory in pages
+    pub const RESIDENT: Self = Self(1);
+    /// the highest file descriptor + 1
+    pub const OPEN_FILES: Self = Self(2);
+    pub const THREADS: Self = Self(3);
+    /// cpu time in seconds
+    pub const CPU_TIME: Self = Self(4);
+
+    pub const COUNT: usize = 5;
+
+    pub const ALL: [Self; Self::COUNT] = [
+        Self::ADDRESS_SPACE,
+        Self::RESIDENT,
+        Self::OPEN_FILES,
+        Self::THREADS,
+        Self::CPU_TIME,
+    ];
+
+    #[must_use]
+    pub const fn is_valid(self) -> bool {
+        self.0 < Self::COUNT
+    }
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::ADDRESS_SPACE => "Max address space",
+            Self::RESIDENT => "Max resident set",
+            Self::OPEN_FILES => "Max open files",
+            Self::THREADS => "Max threads",
+            Self::CPU_TIME => "Max cpu time",
+            _ => "Unknown",
+        }
+    }
+
+    #[must_use]
+    pub const fn unit(self) -> &'static str {
+        match self {
+            Self::ADDRESS_SPACE => "bytes",
+            Self::RESIDENT => "pages",
+            Self::OPEN_FILES => "files",
+            Self::THREADS => "threads",
+            Self::CPU_TIME => "seconds",
+            _ => "",
+        }
+    }
+}
+
+//
+
+/// a soft and a hard limit of a [`Resource`]
+///
+/// the soft limit is the one that is enforced,
+/// the hard limit is the ceiling for the soft limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct Limit {
+    pub cur: u64,
+    pub max: u64,
+}
+
+impl Limit {
+    pub const INFINITY: u64 = u64::MAX;
+
+    pub const UNLIMITED: Self = Self {
+        cur: Self::INFINITY,
+        max: Self::INFINITY,
+    };
+}
+
+/// a limit value or `unlimited`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LimitValue(pub u64);
+
+impl fmt::Display for LimitValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 == Limit::INFINITY {
+            f.pad("unlimited")
+        } else {
+            fmt::Display::fmt(&self.0, f)
+        }
+    }
+}
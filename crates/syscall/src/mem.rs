@@ -0,0 +1,51 @@
+use bitflags::bitflags;
+
+//
+
+bitflags! {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Prot: u8 {
+    /// the pages can be read
+    const READ        = 0b0000_0001;
+
+    /// the pages can be written
+    const WRITE       = 0b0000_0010;
+
+    /// the pages can be executed
+    const EXEC        = 0b0000_0100;
+}
+}
+
+bitflags! {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct MapFlags: usize {
+    /// writes are visible to every other mapping of the same file (or to forked processes)
+    const SHARED      = 0b0000_0001;
+
+    /// writes go to a private copy of the file contents
+    const PRIVATE     = 0b0000_0010;
+
+    /// map exactly at the given address, replacing any old mappings there
+    const FIXED       = 0b0000_0100;
+
+    /// zeroed memory not backed by any file, the file descriptor is ignored
+    const ANONYMOUS   = 0b0000_1000;
+}
+}
+
+impl MapFlags {
+    /// pack the flags and the protections into a single syscall argument
+    #[must_use]
+    pub const fn pack(self, prot: Prot) -> usize {
+        self.bits() << 8 | prot.bits() as usize
+    }
+
+    /// see [`Self::pack`]
+    #[must_use]
+    pub const fn unpack(packed: usize) -> (Self, Prot) {
+        (
+            Self::from_bits_retain(packed >> 8),
+            Prot::from_bits_retain(packed as u8),
+        )
+    }
+}
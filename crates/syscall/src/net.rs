@@ -12,7 +12,12 @@ impl SocketDomain {
 pub struct SocketType(pub usize);
 
 impl SocketType {
+    /// connection based byte stream
     pub const STREAM: Self = Self(0);
+    /// connectionless messages with preserved boundaries
+    pub const DGRAM: Self = Self(1);
+    /// connection based messages with preserved boundaries
+    pub const SEQPACKET: Self = Self(2);
 }
 
 //
@@ -0,0 +1,231 @@
+use core::{arch::naked_asm, fmt};
+
+use crate::id;
+
+//
+
+/// a signal number
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct Signal(pub usize);
+
+impl Signal {
+    pub const HUP: Self = Self(1);
+    pub const INT: Self = Self(2);
+    pub const QUIT: Self = Self(3);
+    pub const ILL: Self = Self(4);
+    pub const TRAP: Self = Self(5);
+    pub const ABRT: Self = Self(6);
+    pub const BUS: Self = Self(7);
+    pub const FPE: Self = Self(8);
+    /// terminate, cannot be caught, blocked or ignored
+    pub const KILL: Self = Self(9);
+    pub const USR1: Self = Self(10);
+    pub const SEGV: Self = Self(11);
+    pub const USR2: Self = Self(12);
+    pub const PIPE: Self = Self(13);
+    pub const ALRM: Self = Self(14);
+    pub const TERM: Self = Self(15);
+    /// a child process exited or stopped, ignored by default
+    pub const CHLD: Self = Self(17);
+    /// continue a stopped process
+    pub const CONT: Self = Self(18);
+    /// stop, cannot be caught, blocked or ignored
+    pub const STOP: Self = Self(19);
+    pub const TSTP: Self = Self(20);
+    pub const TTIN: Self = Self(21);
+    pub const TTOU: Self = Self(22);
+    /// the cpu time limit was reached
+    pub const XCPU: Self = Self(24);
+    /// an invalid syscall
+    pub const SYS: Self = Self(31);
+
+    /// signal numbers go from 1 to `COUNT - 1`
+    pub const COUNT: usize = 64;
+
+    const NAMES: [(Self, &'static str); 23] = [
+        (Self::HUP, "HUP"),
+        (Self::INT, "INT"),
+        (Self::QUIT, "QUIT"),
+        (Self::ILL, "ILL"),
+        (Self::TRAP, "TRAP"),
+        (Self::ABRT, "ABRT"),
+        (Self::BUS, "BUS"),
+        (Self::FPE, "FPE"),
+        (Self::KILL, "KILL"),
+        (Self::USR1, "USR1"),
+        (Self::SEGV, "SEGV"),
+        (Self::USR2, "USR2"),
+        (Self::PIPE, "PIPE"),
+        (Self::ALRM, "ALRM"),
+        (Self::TERM, "TERM"),
+        (Self::CHLD, "CHLD"),
+        (Self::CONT, "CONT"),
+        (Self::STOP, "STOP"),
+        (Self::TSTP, "TSTP"),
+        (Self::TTIN, "TTIN"),
+        (Self::TTOU, "TTOU"),
+        (Self::XCPU, "XCPU"),
+        (Self::SYS, "SYS"),
+    ];
+
+    #[must_use]
+    pub const fn is_valid(self) -> bool {
+        self.0 != 0 && self.0 < Self::COUNT
+    }
+
+    /// `KILL` and `STOP` cannot be caught, blocked or ignored
+    #[must_use]
+    pub const fn is_catchable(self) -> bool {
+        self.0 != Self::KILL.0 && self.0 != Self::STOP.0
+    }
+
+    /// parse a signal name like `TERM` or `SIGTERM`, or a signal number
+    #[must_use]
+    pub fn parse(s: &str) -> Option<Self> {
+        if let Ok(num) = s.parse() {
+            return Some(Self(num)).filter(|sig| sig.is_valid());
+        }
+
+        let name = s.strip_prefix("SIG").unwrap_or(s);
+        Self::NAMES
+            .iter()
+            .find(|(_, n)| n.eq_ignore_ascii_case(name))
+            .map(|(sig, _)| *sig)
+    }
+
+    #[must_use]
+    pub fn name(self) -> Option<&'static str> {
+        Self::NAMES
+            .iter()
+            .find(|(sig, _)| *sig == self)
+            .map(|(_, n)| *n)
+    }
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "SIG{name}"),
+            None => write!(f, "signal {}", self.0),
+        }
+    }
+}
+
+//
+
+/// a set of signals, like the blocked signal mask
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(transparent)]
+pub struct SignalSet(pub u64);
+
+impl SignalSet {
+    pub const EMPTY: Self = Self(0);
+
+    #[must_use]
+    pub const fn with(self, signal: Signal) -> Self {
+        Self(self.0 | 1 << signal.0)
+    }
+
+    #[must_use]
+    pub const fn contains(self, signal: Signal) -> bool {
+        self.0 & 1 << signal.0 != 0
+    }
+
+    #[must_use]
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// the lowest signal in this set
+    #[must_use]
+    pub const fn first(self) -> Option<Signal> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(Signal(self.0.trailing_zeros() as usize))
+        }
+    }
+}
+
+impl FromIterator<Signal> for SignalSet {
+    fn from_iter<T: IntoIterator<Item = Signal>>(iter: T) -> Self {
+        iter.into_iter().fold(Self::EMPTY, Self::with)
+    }
+}
+
+//
+
+/// what to do when a signal is delivered
+#[derive(Debug, Clone, Copy)]
+pub enum SignalHandler {
+    /// the default action of the signal: terminate, ignore or stop
+    Default,
+    /// discard the signal
+    Ignore,
+    /// call a function in the process
+    Handler(extern "C" fn(Signal)),
+}
+
+impl SignalHandler {
+    #[must_use]
+    pub fn as_raw(self) -> usize {
+        match self {
+            Self::Default => 0,
+            Self::Ignore => 1,
+            Self::Handler(f) => f as *const () as usize,
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `raw` has to be 0, 1 or a valid signal handler function pointer
+    #[must_use]
+    pub unsafe fn from_raw(raw: usize) -> Self {
+        match raw {
+            0 => Self::Default,
+            1 => Self::Ignore,
+            f => Self::Handler(unsafe { core::mem::transmute::<usize, extern "C" fn(Signal)>(f) }),
+        }
+    }
+}
+
+//
+
+/// how [`crate::sigprocmask`] changes the blocked signal mask
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SigMaskHow(pub usize);
+
+impl SigMaskHow {
+    /// add the signals to the mask
+    pub const BLOCK: Self = Self(0);
+    /// remove the signals from the mask
+    pub const UNBLOCK: Self = Self(1);
+    /// replace the mask
+    pub const SET: Self = Self(2);
+}
+
+//
+
+/// the entry point of every signal handler
+///
+/// the kernel jumps here with `rdi` = signal, `rsi` = handler and `rsp` = the signal frame,
+/// the FPU/SSE state is saved here because the handler may use it freely
+#[naked]
+pub(crate) unsafe extern "C" fn trampoline() {
+    unsafe {
+        naked_asm!(
+            "mov rbx, rsp",
+            "sub rsp, 512",
+            "fxsave64 [rsp]",
+            "call rsi",
+            "fxrstor64 [rsp]",
+            "mov rsp, rbx",
+            // the kernel restores everything from the signal frame at `rsp`
+            "mov rax, {sigreturn}",
+            "syscall",
+            "ud2",
+            sigreturn = const id::SIGRETURN,
+        );
+    }
+}
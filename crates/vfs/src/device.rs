@@ -3,10 +3,15 @@ use core::{
     any::Any,
     fmt,
     ops::{Deref, Range},
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
 };
 
 use hyperion_arch::vmm::PageMap;
-use hyperion_syscall::err::{Error, Result};
+use hyperion_events::readiness::Listeners;
+use hyperion_syscall::{
+    err::{Error, Result},
+    fs::{FileKind, Metadata, PollEvents},
+};
 use x86_64::{structures::paging::PageTableFlags, VirtAddr};
 
 use crate::tree::Node;
@@ -20,6 +25,47 @@ pub trait FileDevice: Send + Sync {
 
     fn as_any(&self) -> &dyn Any;
 
+    fn kind(&self) -> FileKind {
+        FileKind::DEVICE
+    }
+
+    /// unix style permission bits
+    fn mode(&self) -> usize {
+        0o666
+    }
+
+    /// changeable owner, group and mode, nodes without it are owned by root
+    fn permissions(&self) -> Option<&Permissions> {
+        None
+    }
+
+    /// a unique id of this node, like an inode number
+    ///
+    /// the id comes from [`Self::permissions`], nodes without it have the id 0
+    fn id(&self) -> u64 {
+        self.permissions().map_or(0, |perms| perms.id)
+    }
+
+    fn timestamps(&self) -> Option<&Timestamps> {
+        None
+    }
+
+    /// node kind, mode, owner, timestamps and the driver name
+    fn metadata(&self) -> Metadata {
+        let mut meta = Metadata::zeroed();
+        meta.len = self.len();
+        meta.kind = self.kind();
+        meta.mode = self.mode();
+        if let Some(perms) = self.permissions() {
+            perms.write_to(&mut meta);
+        }
+        if let Some(times) = self.timestamps() {
+            times.write_to(&mut meta);
+        }
+        meta.set_driver(self.driver());
+        meta
+    }
+
     fn len(&self) -> usize;
 
     /// truncate or add zeros to set the length
@@ -32,6 +78,20 @@ pub trait FileDevice: Send + Sync {
         self.len() == 0
     }
 
+    /// the operations that wouldn't block right now, for `poll`
+    ///
+    /// regular files never block
+    fn poll(&self) -> PollEvents {
+        PollEvents::IN | PollEvents::OUT
+    }
+
+    /// start listening for changes of [`Self::poll`], before checking it
+    ///
+    /// files that never block don't need to listen to anything
+    fn listen(&self, listeners: &mut Listeners) {
+        _ = listeners;
+    }
+
     /// allocate physical pages + map the file to it OR get the device physical address
     ///
     /// allocated pages are managed by this FileDevice, each [`Self::map_phys`] is paired with
@@ -39,6 +99,8 @@ pub trait FileDevice: Send + Sync {
     ///
     /// v_addr is for the map placement and its maximum size
     ///
+    /// offset is the page aligned byte offset into the file where the mapping starts
+    ///
     /// flags are the flags for each page
     ///
     /// returns the number of bytes actually mapped
@@ -46,9 +108,10 @@ pub trait FileDevice: Send + Sync {
         &mut self,
         vmm: &PageMap,
         v_addr: Range<VirtAddr>,
+        offset: usize,
         flags: PageTableFlags,
     ) -> Result<usize> {
-        _ = (vmm, v_addr, flags);
+        _ = (vmm, v_addr, offset, flags);
         Err(Error::PERMISSION_DENIED)
     }
 
@@ -62,6 +125,13 @@ pub trait FileDevice: Send + Sync {
         Err(Error::PERMISSION_DENIED)
     }
 
+    /// [`Self::read`] but [`Error::WOULD_BLOCK`] instead of blocking
+    ///
+    /// regular files never block
+    fn try_read(&self, offset: usize, buf: &mut [u8]) -> Result<usize> {
+        self.read(offset, buf)
+    }
+
     fn read_exact(&self, mut offset: usize, mut buf: &mut [u8]) -> Result<()> {
         while !buf.is_empty() {
             match self.read(offset, buf) {
@@ -109,6 +179,42 @@ pub trait DirectoryDevice: Send + Sync {
         "unknown"
     }
 
+    /// unix style permission bits
+    fn mode(&self) -> usize {
+        0o755
+    }
+
+    /// changeable owner, group and mode, nodes without it are owned by root
+    fn permissions(&self) -> Option<&Permissions> {
+        None
+    }
+
+    /// a unique id of this node, like an inode number
+    ///
+    /// the id comes from [`Self::permissions`], nodes without it have the id 0
+    fn id(&self) -> u64 {
+        self.permissions().map_or(0, |perms| perms.id)
+    }
+
+    fn timestamps(&self) -> Option<&Timestamps> {
+        None
+    }
+
+    /// node kind, mode, owner, timestamps and the driver name
+    fn metadata(&self) -> Metadata {
+        let mut meta = Metadata::zeroed();
+        meta.kind = FileKind::DIRECTORY;
+        meta.mode = self.mode();
+        if let Some(perms) = self.permissions() {
+            perms.write_to(&mut meta);
+        }
+        if let Some(times) = self.timestamps() {
+            times.write_to(&mut meta);
+        }
+        meta.set_driver(self.driver());
+        meta
+    }
+
     fn get_node(&mut self, name: &str) -> Result<Node> {
         _ = name;
         Err(Error::PERMISSION_DENIED)
@@ -119,6 +225,18 @@ pub trait DirectoryDevice: Send + Sync {
         Err(Error::PERMISSION_DENIED)
     }
 
+    /// remove a node from this directory and return it
+    fn remove_node(&mut self, name: &str) -> Result<Node> {
+        _ = name;
+        Err(Error::PERMISSION_DENIED)
+    }
+
+    /// rename a node in this directory, fails if `new_name` already exists
+    fn rename_node(&mut self, old_name: &str, new_name: &str) -> Result<()> {
+        _ = (old_name, new_name);
+        Err(Error::PERMISSION_DENIED)
+    }
+
     fn nodes(&mut self) -> Result<Box<dyn ExactSizeIterator<Item = DirEntry<'_>> + '_>> {
         Err(Error::PERMISSION_DENIED)
     }
@@ -126,6 +244,98 @@ pub trait DirectoryDevice: Send + Sync {
 
 //
 
+/// node creation, modification and access times in nanoseconds since boot
+#[derive(Debug, Default)]
+pub struct Timestamps {
+    pub created: AtomicU64,
+    pub modified: AtomicU64,
+    pub accessed: AtomicU64,
+}
+
+impl Timestamps {
+    /// all timestamps set to the current time
+    pub fn now() -> Self {
+        let now = now();
+        Self {
+            created: AtomicU64::new(now),
+            modified: AtomicU64::new(now),
+            accessed: AtomicU64::new(now),
+        }
+    }
+
+    pub fn modify(&self) {
+        let now = now();
+        self.modified.store(now, Ordering::Relaxed);
+        self.accessed.store(now, Ordering::Relaxed);
+    }
+
+    pub fn access(&self) {
+        self.accessed.store(now(), Ordering::Relaxed);
+    }
+
+    pub fn write_to(&self, meta: &mut Metadata) {
+        meta.created = self.created.load(Ordering::Relaxed);
+        meta.modified = self.modified.load(Ordering::Relaxed);
+        meta.accessed = self.accessed.load(Ordering::Relaxed);
+    }
+}
+
+/// node owner, group, unix style permission bits and the node id
+#[derive(Debug)]
+pub struct Permissions {
+    /// assigned when the node is created and never reused
+    pub id: u64,
+    pub uid: AtomicUsize,
+    pub gid: AtomicUsize,
+    pub mode: AtomicUsize,
+}
+
+impl Permissions {
+    /// owned by the current process user and group
+    pub fn new(mode: usize) -> Self {
+        let (uid, gid) = if hyperion_scheduler::running() {
+            let proc = hyperion_scheduler::process();
+            (proc.uid(), proc.gid())
+        } else {
+            (0, 0)
+        };
+
+        Self::with_owner(uid, gid, mode)
+    }
+
+    pub fn with_owner(uid: usize, gid: usize, mode: usize) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            uid: AtomicUsize::new(uid),
+            gid: AtomicUsize::new(gid),
+            mode: AtomicUsize::new(mode),
+        }
+    }
+
+    pub fn chmod(&self, mode: usize) {
+        self.mode.store(mode & 0o7777, Ordering::Relaxed);
+    }
+
+    pub fn chown(&self, uid: usize, gid: usize) {
+        self.uid.store(uid, Ordering::Relaxed);
+        self.gid.store(gid, Ordering::Relaxed);
+    }
+
+    pub fn write_to(&self, meta: &mut Metadata) {
+        meta.uid = self.uid.load(Ordering::Relaxed);
+        meta.gid = self.gid.load(Ordering::Relaxed);
+        meta.mode = self.mode.load(Ordering::Relaxed);
+    }
+}
+
+fn now() -> u64 {
+    hyperion_clock::get().nanosecond_now() as u64
+}
+
+//
+
 pub struct DirEntry<'a> {
     pub name: ArcOrRef<'a, str>,
     pub node: Node,
@@ -200,10 +410,22 @@ impl<T: FileDevice> FileDevice for &'static T {
         Err(Error::PERMISSION_DENIED)
     }
 
+    fn poll(&self) -> PollEvents {
+        (**self).poll()
+    }
+
+    fn listen(&self, listeners: &mut Listeners) {
+        (**self).listen(listeners)
+    }
+
     fn read(&self, offset: usize, buf: &mut [u8]) -> Result<usize> {
         (**self).read(offset, buf)
     }
 
+    fn try_read(&self, offset: usize, buf: &mut [u8]) -> Result<usize> {
+        (**self).try_read(offset, buf)
+    }
+
     fn write(&mut self, _: usize, _: &[u8]) -> Result<usize> {
         Err(Error::PERMISSION_DENIED)
     }
@@ -76,27 +76,39 @@ impl Path {
         self.0.starts_with('/')
     }
 
+    /// resolve `.` and `..` parts, relative paths are resolved from the `working_dir`
     pub fn to_absolute(&self, working_dir: &Path) -> Cow<'_, Path> {
-        if self.is_absolute() {
-            Cow::Borrowed(self)
+        if self.is_absolute() && self.iter().all(|part| part != "." && part != "..") {
+            // `/dir/` is `/dir`, but `/` stays
+            return match self.0.trim_end_matches('/') {
+                "" => Cow::Borrowed("/".as_ref()),
+                trimmed => Cow::Borrowed(trimmed.as_ref()),
+            };
+        }
+
+        let mut result = if self.is_absolute() {
+            PathBuf::new("/")
         } else {
-            let mut working_dir = working_dir.to_owned();
-
-            // relative path
-            for part in self.iter() {
-                match part {
-                    "." => {}
-                    ".." => {
-                        working_dir.pop();
-                    }
-                    other => {
-                        working_dir.join(other);
-                    }
+            working_dir.to_owned()
+        };
+
+        for part in self.iter() {
+            match part {
+                "." => {}
+                ".." => {
+                    result.pop();
+                }
+                other => {
+                    result.join(other);
                 }
             }
+        }
 
-            Cow::Owned(working_dir)
+        if result.0.len() > 1 && result.is_dir() {
+            result.0.pop();
         }
+
+        Cow::Owned(result)
     }
 }
 
@@ -138,7 +150,7 @@ impl PathBuf {
     }
 
     pub fn pop(&mut self) -> &mut PathBuf {
-        if let Some(split) = self.0.rfind('/') {
+        if let Some(split) = self.0.trim_end_matches('/').rfind('/') {
             self.0.truncate(split + 1);
         }
         self
@@ -218,4 +230,28 @@ mod tests {
         assert_eq!(parts.next(), Some("path"));
         assert_eq!(parts.next(), None);
     }
+
+    #[test]
+    fn path_to_absolute_test() {
+        let cwd: &Path = "/home/user".as_ref();
+
+        assert_eq!(
+            Path::from_str("file").to_absolute(cwd).as_str(),
+            "/home/user/file"
+        );
+        assert_eq!(
+            Path::from_str("./a/../b").to_absolute(cwd).as_str(),
+            "/home/user/b"
+        );
+        assert_eq!(Path::from_str("..").to_absolute(cwd).as_str(), "/home");
+        assert_eq!(Path::from_str("../../..").to_absolute(cwd).as_str(), "/");
+        assert_eq!(Path::from_str("/a/./b/..").to_absolute(cwd).as_str(), "/a");
+        assert_eq!(Path::from_str("/a/b").to_absolute(cwd).as_str(), "/a/b");
+        assert_eq!(Path::from_str("/a/b/").to_absolute(cwd).as_str(), "/a/b");
+        assert_eq!(
+            Path::from_str("b/").to_absolute(cwd).as_str(),
+            "/home/user/b"
+        );
+        assert_eq!(Path::from_str("//").to_absolute(cwd).as_str(), "/");
+    }
 }
@@ -13,11 +13,14 @@ use hyperion_mem::{
     vmm::{MapTarget, PageMapImpl},
 };
 use hyperion_scheduler::lock::Mutex;
-use hyperion_syscall::err::{Error, Result};
+use hyperion_syscall::{
+    err::{Error, Result},
+    fs::FileKind,
+};
 use x86_64::{structures::paging::PageTableFlags, VirtAddr};
 
 use crate::{
-    device::{ArcOrRef, DirEntry, DirectoryDevice, FileDevice},
+    device::{ArcOrRef, DirEntry, DirectoryDevice, FileDevice, Permissions, Timestamps},
     tree::{DirRef, FileRef, Node, WeakDirRef},
 };
 
@@ -27,6 +30,8 @@ pub struct File {
     // bytes: Vec<u8>,
     pages: Vec<PageFrame>,
     len: usize,
+    perms: Permissions,
+    times: Timestamps,
 }
 
 impl File {
@@ -35,6 +40,8 @@ impl File {
             Self {
                 pages: vec![],
                 len: 0,
+                perms: Permissions::new(0o644),
+                times: Timestamps::now(),
             }
         } else {
             let pages = bytes.len().div_ceil(0x1000);
@@ -44,6 +51,8 @@ impl File {
             Self {
                 pages: vec![pages],
                 len: bytes.len(),
+                perms: Permissions::new(0o644),
+                times: Timestamps::now(),
             }
         }
     }
@@ -52,6 +61,8 @@ impl File {
         Arc::new(Mutex::new(Self {
             pages: Vec::new(),
             len: 0,
+            perms: Permissions::new(0o644),
+            times: Timestamps::now(),
         })) as _
     }
 }
@@ -68,11 +79,23 @@ impl Drop for File {
 
 pub struct StaticRoFile {
     bytes: &'static [u8],
+    perms: Permissions,
 }
 
 impl StaticRoFile {
-    pub const fn new(bytes: &'static [u8]) -> Self {
-        Self { bytes }
+    pub fn new(bytes: &'static [u8]) -> Self {
+        Self {
+            bytes,
+            perms: Permissions::with_owner(0, 0, 0o444),
+        }
+    }
+
+    /// like [`Self::new`], but anyone can also execute it
+    pub fn executable(bytes: &'static [u8]) -> Self {
+        Self {
+            bytes,
+            perms: Permissions::with_owner(0, 0, 0o555),
+        }
     }
 }
 
@@ -80,6 +103,8 @@ pub struct Directory {
     pub name: Arc<str>,
     pub children: BTreeMap<Arc<str>, Node>,
     pub parent: Option<WeakDirRef>,
+    perms: Permissions,
+    times: Timestamps,
 
     nodes_cache: Option<Arc<[Arc<str>]>>,
 }
@@ -91,12 +116,25 @@ impl FileDevice for File {
         self
     }
 
+    fn kind(&self) -> FileKind {
+        FileKind::FILE
+    }
+
+    fn permissions(&self) -> Option<&Permissions> {
+        Some(&self.perms)
+    }
+
+    fn timestamps(&self) -> Option<&Timestamps> {
+        Some(&self.times)
+    }
+
     fn len(&self) -> usize {
         self.len
     }
 
     fn set_len(&mut self, len: usize) -> Result<()> {
         self.len = len;
+        self.times.modify();
         Ok(())
     }
 
@@ -104,9 +142,10 @@ impl FileDevice for File {
         &mut self,
         vmm: &PageMap,
         v_addr: Range<VirtAddr>,
+        offset: usize,
         flags: PageTableFlags,
     ) -> Result<usize> {
-        if !v_addr.start.is_aligned(0x1000u64) {
+        if !v_addr.start.is_aligned(0x1000u64) || offset & 0xfff != 0 {
             // FIXME: use the real abi error
             return Err(Error::PERMISSION_DENIED);
         }
@@ -114,15 +153,15 @@ impl FileDevice for File {
         let mut pos = v_addr.start;
 
         // grow the file
-        if (v_addr.end - v_addr.start) as usize > self.len {
-            self.set_len((v_addr.end - v_addr.start) as usize)?;
+        let needed = offset + (v_addr.end - v_addr.start) as usize;
+        if needed > self.len {
+            self.set_len(needed)?;
         }
 
         // TODO: use lazy mapping instead
         // lazy allocate more pages, since the file size is larger
         // than there are physical pages currently allocated
         let allocated = self.pages.iter().map(|p| p.byte_len()).sum::<usize>();
-        let needed = (v_addr.end - v_addr.start) as usize;
         if needed > allocated {
             let missing_pages = needed.abs_diff(allocated).div_ceil(0x1000);
 
@@ -133,24 +172,26 @@ impl FileDevice for File {
             }
         }
 
+        // skip the pages before the offset
+        let mut skip = offset;
         for pages in self.pages.iter() {
-            if pos + pages.byte_len() >= v_addr.end {
-                vmm.map(
-                    pos..v_addr.end,
-                    MapTarget::Borrowed(pages.physical_addr()),
-                    flags,
-                );
-                pos = v_addr.end;
+            if pos >= v_addr.end {
                 break;
             }
+            if skip >= pages.byte_len() {
+                skip -= pages.byte_len();
+                continue;
+            }
 
+            let end = (pos + (pages.byte_len() - skip) as u64).min(v_addr.end);
             vmm.map(
-                pos..pos + pages.byte_len(),
-                MapTarget::Borrowed(pages.physical_addr()),
+                pos..end,
+                MapTarget::Borrowed(pages.physical_addr() + skip as u64),
                 flags,
             );
 
-            pos += pages.byte_len();
+            pos = end;
+            skip = 0;
         }
 
         Ok((pos - v_addr.start) as usize)
@@ -164,6 +205,8 @@ impl FileDevice for File {
         // FIXME: this should really just temporarily map the pages
         // its simpler and its faster and its less buggy
 
+        self.times.access();
+
         if let Some(buf_limit) = self.len.checked_sub(offset) {
             let buf_limit = buf_limit.min(buf.len());
             buf = &mut buf[..buf_limit];
@@ -198,6 +241,7 @@ impl FileDevice for File {
 
     fn write(&mut self, offset: usize, mut buf: &[u8]) -> Result<usize> {
         self.len = self.len.max(offset + buf.len());
+        self.times.modify();
 
         let initial_len = buf.len();
 
@@ -240,6 +284,14 @@ impl FileDevice for StaticRoFile {
         self
     }
 
+    fn kind(&self) -> FileKind {
+        FileKind::FILE
+    }
+
+    fn permissions(&self) -> Option<&Permissions> {
+        Some(&self.perms)
+    }
+
     fn len(&self) -> usize {
         self.bytes.len()
     }
@@ -254,6 +306,14 @@ impl DirectoryDevice for Directory {
         "vfs"
     }
 
+    fn permissions(&self) -> Option<&Permissions> {
+        Some(&self.perms)
+    }
+
+    fn timestamps(&self) -> Option<&Timestamps> {
+        Some(&self.times)
+    }
+
     fn get_node(&mut self, name: &str) -> Result<Node> {
         if let Some(node) = self.children.get(name) {
             Ok(node.clone())
@@ -267,12 +327,38 @@ impl DirectoryDevice for Directory {
             Entry::Vacant(entry) => {
                 entry.insert(node);
                 self.nodes_cache = None;
+                self.times.modify();
                 Ok(())
             }
             Entry::Occupied(_) => Err(Error::ALREADY_EXISTS),
         }
     }
 
+    fn remove_node(&mut self, name: &str) -> Result<Node> {
+        let node = self.children.remove(name).ok_or(Error::NOT_FOUND)?;
+        self.nodes_cache = None;
+        self.times.modify();
+        Ok(node)
+    }
+
+    fn rename_node(&mut self, old_name: &str, new_name: &str) -> Result<()> {
+        if !self.children.contains_key(old_name) {
+            return Err(Error::NOT_FOUND);
+        }
+        if old_name == new_name {
+            return Ok(());
+        }
+        if self.children.contains_key(new_name) {
+            return Err(Error::ALREADY_EXISTS);
+        }
+
+        let node = self.children.remove(old_name).unwrap();
+        self.children.insert(new_name.into(), node);
+        self.nodes_cache = None;
+        self.times.modify();
+        Ok(())
+    }
+
     fn nodes(&mut self) -> Result<Box<dyn ExactSizeIterator<Item = DirEntry<'_>> + '_>> {
         Ok(Box::new(self.children.iter().map(|(name, node)| {
             DirEntry {
@@ -289,6 +375,8 @@ impl Directory {
             name: name.into(),
             children: BTreeMap::new(),
             parent: None,
+            perms: Permissions::new(0o755),
+            times: Timestamps::now(),
 
             nodes_cache: None,
         }
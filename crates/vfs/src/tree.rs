@@ -3,7 +3,10 @@ use core::fmt;
 
 use hyperion_log::*;
 use hyperion_scheduler::lock::Mutex;
-use hyperion_syscall::err::{Error, Result};
+use hyperion_syscall::{
+    err::{Error, Result},
+    fs::{FileKind, Metadata},
+};
 
 use crate::{
     device::DirectoryDevice,
@@ -86,8 +89,33 @@ impl Node {
             Node::Directory(d) => Ok(d.clone()),
         }
     }
+
+    /// a unique id of this node, like an inode number
+    pub fn id(&self) -> u64 {
+        match self {
+            Node::File(f) => f.lock().id(),
+            Node::Directory(d) => d.lock().id(),
+        }
+    }
+
+    pub fn kind(&self) -> FileKind {
+        match self {
+            Node::File(f) => f.lock().kind(),
+            Node::Directory(_) => FileKind::DIRECTORY,
+        }
+    }
+
+    /// node kind, mode, timestamps and the driver name
+    pub fn metadata(&self) -> Metadata {
+        match self {
+            Node::File(f) => f.lock().metadata(),
+            Node::Directory(d) => d.lock().metadata(),
+        }
+    }
 }
 
+//
+
 impl fmt::Debug for Node {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -228,6 +256,77 @@ impl Node {
             .create_node(target_name, node)
     }
 
+    /// remove a file, or an empty directory if `is_dir` is set
+    pub fn remove(&self, path: impl AsRef<Path>, is_dir: bool) -> Result<Node> {
+        let path = path.as_ref();
+        let (parent, name) = path.split();
+
+        if name.is_empty() {
+            // the root can't be removed
+            return Err(Error::INVALID_ARGUMENT);
+        }
+
+        let parent = self.find(parent, false)?.try_as_dir()?;
+        let mut parent = parent.lock();
+
+        match (parent.get_node(name)?, is_dir) {
+            (Node::File(_), true) => return Err(Error::NOT_A_DIRECTORY),
+            (Node::Directory(_), false) => return Err(Error::NOT_A_FILE),
+            (Node::Directory(dir), true) => {
+                if dir.lock().nodes()?.len() != 0 {
+                    return Err(Error::DIRECTORY_NOT_EMPTY);
+                }
+            }
+            (Node::File(_), false) => {}
+        }
+
+        parent.remove_node(name)
+    }
+
+    /// move a node to another path, fails if the target already exists
+    pub fn rename(&self, from: impl AsRef<Path>, to: impl AsRef<Path>) -> Result<()> {
+        let (from, to) = (from.as_ref(), to.as_ref());
+        let (from_parent, from_name) = from.split();
+        let (to_parent, to_name) = to.split();
+
+        if from_name.is_empty() || to_name.is_empty() {
+            // the root can't be moved
+            return Err(Error::INVALID_ARGUMENT);
+        }
+
+        // a directory can't be moved inside itself
+        if to.iter().count() > from.iter().count()
+            && from.iter().zip(to.iter()).all(|(a, b)| a == b)
+        {
+            return Err(Error::INVALID_ARGUMENT);
+        }
+
+        if from_parent.iter().eq(to_parent.iter()) {
+            return self
+                .find(from_parent, false)?
+                .try_as_dir()?
+                .lock()
+                .rename_node(from_name, to_name);
+        }
+
+        let from_parent = self.find(from_parent, false)?.try_as_dir()?;
+        let to_parent = self.find(to_parent, false)?.try_as_dir()?;
+
+        // both directories are never locked at the same time
+        if to_parent.lock().get_node(to_name).is_ok() {
+            return Err(Error::ALREADY_EXISTS);
+        }
+
+        let node = from_parent.lock().remove_node(from_name)?;
+        if let Err(err) = to_parent.lock().create_node(to_name, node.clone()) {
+            // put it back
+            _ = from_parent.lock().create_node(from_name, node);
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
     pub fn mount(&self, path: impl AsRef<Path>, dev: impl DirectoryDevice + 'static) {
         self.mount_ref(path, Arc::new(Mutex::new(dev)))
     }
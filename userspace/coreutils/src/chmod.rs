@@ -0,0 +1,24 @@
+use anyhow::{anyhow, Result};
+use libstd::sys::chmod;
+
+//
+
+pub fn cmd<'a>(mut args: impl Iterator<Item = &'a str>) -> Result<()> {
+    let mode = args
+        .next()
+        .ok_or_else(|| anyhow!("usage: chmod MODE file..."))?;
+    let mode =
+        usize::from_str_radix(mode, 8).map_err(|_| anyhow!("invalid octal mode `{mode}`"))?;
+
+    let mut files = 0;
+    for path in args {
+        chmod(path, mode).map_err(|err| anyhow!("cannot chmod `{path}`: {err}"))?;
+        files += 1;
+    }
+
+    if files == 0 {
+        return Err(anyhow!("usage: chmod MODE file..."));
+    }
+
+    Ok(())
+}
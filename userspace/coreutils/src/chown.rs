@@ -0,0 +1,37 @@
+use anyhow::{anyhow, Result};
+use libstd::sys::chown;
+
+//
+
+pub fn cmd<'a>(mut args: impl Iterator<Item = &'a str>) -> Result<()> {
+    let owner = args
+        .next()
+        .ok_or_else(|| anyhow!("usage: chown [UID][:GID] file..."))?;
+
+    // `UID`, `UID:GID` or `:GID`
+    let (uid, gid) = owner.split_once(':').unwrap_or((owner, ""));
+    let uid = parse_id(uid)?;
+    let gid = parse_id(gid)?;
+
+    let mut files = 0;
+    for path in args {
+        chown(path, uid, gid).map_err(|err| anyhow!("cannot chown `{path}`: {err}"))?;
+        files += 1;
+    }
+
+    if files == 0 {
+        return Err(anyhow!("usage: chown [UID][:GID] file..."));
+    }
+
+    Ok(())
+}
+
+fn parse_id(id: &str) -> Result<Option<usize>> {
+    if id.is_empty() {
+        return Ok(None);
+    }
+
+    id.parse::<usize>()
+        .map(Some)
+        .map_err(|_| anyhow!("invalid id `{id}`"))
+}
@@ -0,0 +1,19 @@
+use anyhow::Result;
+use libstd::{
+    println,
+    sys::{getgid, getuid},
+};
+
+//
+
+pub fn cmd<'a>(_: impl Iterator<Item = &'a str>) -> Result<()> {
+    let uid = getuid();
+    let gid = getgid();
+
+    // there is no user database, only root has a name
+    let name = |id: usize| if id == 0 { "(root)" } else { "" };
+
+    println!("uid={uid}{} gid={gid}{}", name(uid), name(gid));
+
+    Ok(())
+}
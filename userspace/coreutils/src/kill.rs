@@ -0,0 +1,28 @@
+use anyhow::{anyhow, Result};
+use libstd::sys::{kill, signal::Signal};
+
+//
+
+pub fn cmd<'a>(args: impl Iterator<Item = &'a str>) -> Result<()> {
+    let mut signal = Signal::TERM;
+    let mut pids = 0;
+
+    for arg in args {
+        if let Some(name) = arg.strip_prefix('-') {
+            signal = Signal::parse(name).ok_or_else(|| anyhow!("invalid signal `{name}`"))?;
+            continue;
+        }
+
+        let pid = arg
+            .parse::<usize>()
+            .map_err(|_| anyhow!("invalid pid `{arg}`"))?;
+        kill(pid, signal).map_err(|err| anyhow!("cannot signal `{pid}`: {err}"))?;
+        pids += 1;
+    }
+
+    if pids == 0 {
+        return Err(anyhow!("usage: kill [-SIGNAL] pid..."));
+    }
+
+    Ok(())
+}
@@ -1,49 +1,91 @@
-use alloc::{format, vec::Vec};
+use alloc::{format, string::String, vec::Vec};
 
 use anyhow::{anyhow, Result};
 use hyperion_num_postfix::NumberPostfix;
 use libstd::{
-    fs::{Dir, DirEntry},
-    print, println,
+    fs::{metadata, read_dir, DirEntry},
+    println,
+    sys::fs::Metadata,
 };
 
 //
 
-pub fn cmd<'a>(mut args: impl Iterator<Item = &'a str>) -> Result<()> {
-    let a1 = args.next().unwrap_or("/"); // TODO: cwd
-                                         // .ok_or_else(|| anyhow!("expected at least one argument"))?;
+pub fn cmd<'a>(args: impl Iterator<Item = &'a str>) -> Result<()> {
+    let mut long = false;
+    let mut dir = ".";
+    for arg in args {
+        match arg {
+            "-l" => long = true,
+            other => dir = other,
+        }
+    }
 
-    let mut entries: Vec<DirEntry> = Dir::open(a1).map_err(|err| anyhow!("{err}"))?.collect();
+    let mut entries: Vec<DirEntry> = read_dir(dir).map_err(|err| anyhow!("{err}"))?.collect();
     entries.sort_by(|a, b| {
         let cmp = (!a.is_dir).cmp(&(!b.is_dir));
         if cmp.is_ne() {
             return cmp;
         }
 
-        let cmp = a.file_name.cmp(&b.file_name);
-        if cmp.is_ne() {
-            return cmp;
-        }
-
-        a.size.cmp(&b.size)
+        a.file_name.cmp(&b.file_name)
     });
 
-    println!("mode size name");
+    if long {
+        println!(
+            "{: <10} {: >4} {: >4} {: >7} {: >10} {: <8} name",
+            "mode", "uid", "gid", "size", "modified", "driver"
+        );
+    } else {
+        println!("mode size name");
+    }
+
     for entry in entries {
-        let size = entry.size.postfix_binary();
-        let size_n = size.into_inner();
-        let size_scale = size.scale();
+        let path = format!("{}/{}", dir.trim_end_matches('/'), entry.file_name);
+        let meta = metadata(&path).map_err(|err| anyhow!("cannot stat `{path}`: {err}"))?;
 
-        let size = format!("{size_n}{size_scale}B");
+        let size = if meta.is_dir() {
+            "-".into()
+        } else {
+            format_size(meta.len)
+        };
 
-        if entry.is_dir {
-            print!("d       - ");
+        if long {
+            // seconds since boot
+            let modified = meta.modified / 1_000_000_000;
+
+            println!(
+                "{} {: >4} {: >4} {size: >7} {modified: >9}s {: <8} {}",
+                format_mode(&meta),
+                meta.uid,
+                meta.gid,
+                meta.driver(),
+                entry.file_name
+            );
         } else {
-            print!("f {size: >7} ");
+            println!("{} {size: >7} {}", entry.kind.as_char(), entry.file_name);
         }
-
-        println!("{}", entry.file_name);
     }
 
     Ok(())
 }
+
+fn format_size(size: usize) -> String {
+    let size = size.postfix_binary();
+    let size_n = size.into_inner();
+    let size_scale = size.scale();
+
+    format!("{size_n}{size_scale}B")
+}
+
+/// `drwxr-xr-x` style mode string
+fn format_mode(meta: &Metadata) -> String {
+    let mut mode = String::new();
+    mode.push(meta.kind.as_char());
+    for shift in [6, 3, 0] {
+        let bits = meta.mode >> shift;
+        mode.push(if bits & 0b100 != 0 { 'r' } else { '-' });
+        mode.push(if bits & 0b010 != 0 { 'w' } else { '-' });
+        mode.push(if bits & 0b001 != 0 { 'x' } else { '-' });
+    }
+    mode
+}
@@ -5,18 +5,28 @@
 extern crate alloc;
 
 mod cat;
+mod chmod;
+mod chown;
 mod cp;
 mod date;
 mod echo;
 mod hello;
+mod id;
+mod kill;
 mod ls;
 mod mem;
 mod mkdir;
+mod mv;
+mod nice;
 mod nproc;
 mod ps;
 mod random;
+mod renice;
+mod rm;
+mod rmdir;
 mod sleep;
 mod tail;
+mod timeout;
 mod top;
 mod touch;
 
@@ -37,19 +47,29 @@ fn main() {
 
     let result = match cmd {
         "cat" => cat::cmd(args),
+        "chmod" => chmod::cmd(args),
+        "chown" => chown::cmd(args),
         "coreutils" => crate::cmd(),
         "cp" => cp::cmd(args),
         "date" => date::cmd(args),
         "echo" => echo::cmd(args),
         "hello" => hello::cmd(args),
+        "id" => id::cmd(args),
+        "kill" => kill::cmd(args),
         "ls" => ls::cmd(args),
         "mem" => mem::cmd(args),
         "mkdir" => mkdir::cmd(args),
+        "mv" => mv::cmd(args),
+        "nice" => nice::cmd(args),
         "nproc" => nproc::cmd(args),
         "ps" => ps::cmd(args),
         "random" => random::cmd(args),
+        "renice" => renice::cmd(args),
+        "rm" => rm::cmd(args),
+        "rmdir" => rmdir::cmd(args),
         "sleep" => sleep::cmd(args),
         "tail" => tail::cmd(args),
+        "timeout" => timeout::cmd(args),
         "top" => top::cmd(args),
         "touch" => touch::cmd(args),
         _ => {
@@ -0,0 +1,39 @@
+use alloc::format;
+
+use anyhow::{anyhow, Result};
+use libstd::{
+    fs::{remove_file, rename, Dir},
+    sys::err::Error,
+};
+
+//
+
+pub fn cmd<'a>(mut args: impl Iterator<Item = &'a str>) -> Result<()> {
+    let from_path = args
+        .next()
+        .ok_or_else(|| anyhow!("expected at least two arguments"))?;
+
+    let to_path = args
+        .next()
+        .ok_or_else(|| anyhow!("expected at least two arguments"))?;
+
+    // moving into a directory keeps the file name
+    let to_path = if Dir::open(to_path).is_ok() {
+        let file_name = from_path.trim_end_matches('/').rsplit('/').next().unwrap();
+        format!("{}/{file_name}", to_path.trim_end_matches('/'))
+    } else {
+        to_path.into()
+    };
+
+    match rename(from_path, &to_path) {
+        Err(Error::ALREADY_EXISTS) => {
+            // overwrite the target file
+            remove_file(&to_path).map_err(|err| anyhow!("cannot overwrite `{to_path}`: {err}"))?;
+            rename(from_path, &to_path)
+        }
+        other => other,
+    }
+    .map_err(|err| anyhow!("cannot move `{from_path}` to `{to_path}`: {err}"))?;
+
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use alloc::{format, string::String, vec::Vec};
+
+use anyhow::{anyhow, Result};
+use libstd::{
+    println,
+    sys::{exec, get_priority, set_priority},
+};
+
+//
+
+/// `nice [-n N] [command [args...]]`
+///
+/// runs a command with the nice value raised by N (10 by default),
+/// prints the current nice value without a command
+pub fn cmd<'a>(args: impl Iterator<Item = &'a str>) -> Result<()> {
+    let mut args = args.peekable();
+
+    let current = get_priority(0).map_err(|err| anyhow!("cannot get the priority: {err}"))?;
+
+    let adjustment = if args.next_if_eq(&"-n").is_some() {
+        let n = args
+            .next()
+            .ok_or_else(|| anyhow!("option -n requires a value"))?;
+        n.parse::<i32>()
+            .map_err(|_| anyhow!("invalid adjustment `{n}`"))?
+    } else {
+        10
+    };
+
+    let Some(program) = args.next() else {
+        println!("{current}");
+        return Ok(());
+    };
+
+    set_priority(0, current.saturating_add(adjustment))
+        .map_err(|err| anyhow!("cannot set the priority: {err}"))?;
+
+    let path: String = if program.contains('/') {
+        program.into()
+    } else {
+        format!("/bin/{program}")
+    };
+    let args: Vec<&str> = args.collect();
+    let err = exec(&path, &args);
+
+    Err(anyhow!("{program}: {err}"))
+}
@@ -0,0 +1,39 @@
+use anyhow::{anyhow, Result};
+use libstd::{
+    println,
+    sys::{get_priority, set_priority},
+};
+
+//
+
+/// `renice [-n] N pid...`
+pub fn cmd<'a>(args: impl Iterator<Item = &'a str>) -> Result<()> {
+    let mut args = args.skip_while(|arg| *arg == "-n");
+
+    let Some(nice) = args.next() else {
+        return Err(anyhow!("usage: renice [-n] N pid..."));
+    };
+    let nice = nice
+        .parse::<i32>()
+        .map_err(|_| anyhow!("invalid nice value `{nice}`"))?;
+
+    let mut pids = 0;
+    for arg in args {
+        let pid = arg
+            .parse::<usize>()
+            .map_err(|_| anyhow!("invalid pid `{arg}`"))?;
+
+        let old = get_priority(pid).map_err(|err| anyhow!("cannot get `{pid}`: {err}"))?;
+        set_priority(pid, nice).map_err(|err| anyhow!("cannot renice `{pid}`: {err}"))?;
+        let new = get_priority(pid).map_err(|err| anyhow!("cannot get `{pid}`: {err}"))?;
+
+        println!("{pid}: old priority {old}, new priority {new}");
+        pids += 1;
+    }
+
+    if pids == 0 {
+        return Err(anyhow!("usage: renice [-n] N pid..."));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,12 @@
+use anyhow::{anyhow, Result};
+use libstd::fs::remove_file;
+
+//
+
+pub fn cmd<'a>(args: impl Iterator<Item = &'a str>) -> Result<()> {
+    for file in args {
+        remove_file(file).map_err(|err| anyhow!("cannot remove `{file}`: {err}"))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,12 @@
+use anyhow::{anyhow, Result};
+use libstd::fs::remove_dir;
+
+//
+
+pub fn cmd<'a>(args: impl Iterator<Item = &'a str>) -> Result<()> {
+    for dir in args {
+        remove_dir(dir).map_err(|err| anyhow!("cannot remove `{dir}`: {err}"))?;
+    }
+
+    Ok(())
+}
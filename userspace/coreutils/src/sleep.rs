@@ -8,6 +8,13 @@ pub fn cmd<'a>(mut args: impl Iterator<Item = &'a str>) -> Result<()> {
         .next()
         .ok_or_else(|| anyhow!("expected at least one argument"))?;
 
+    nanosleep(parse_duration(a1)?);
+
+    Ok(())
+}
+
+/// parse a time interval like `1.5`, `10s`, `2m`, `1h` or `1d` to nanoseconds
+pub fn parse_duration(a1: &str) -> Result<u64> {
     let s = a1.ends_with('s');
     let m = a1.ends_with('m');
     let h = a1.ends_with('h');
@@ -31,8 +38,5 @@ pub fn cmd<'a>(mut args: impl Iterator<Item = &'a str>) -> Result<()> {
         n *= 60.0 * 60.0 * 24.0;
     }
 
-    let nanos = (n * 1_000_000_000.0) as u64;
-    nanosleep(nanos);
-
-    Ok(())
+    Ok((n * 1_000_000_000.0) as u64)
 }
@@ -1,7 +1,10 @@
 use alloc::vec::Vec;
 
 use anyhow::{anyhow, Result};
-use libstd::println;
+use libstd::{
+    println,
+    sys::{signal::Signal, waitpid_with, WaitFlags},
+};
 
 //
 
@@ -29,8 +32,15 @@ pub fn cmd<'a>(args: impl Iterator<Item = &'a str>) -> Result<()> {
         return Err(anyhow!("PID should be a number"));
     };
 
-    let code = libstd::sys::waitpid(pid);
+    // children are reaped, other processes can only be polled until they are gone
+    if let Ok(Some((_, status))) = waitpid_with(pid, WaitFlags::empty()) {
+        println!("exit code {}", status.code());
+        return Ok(());
+    }
+    while libstd::sys::kill(pid, Signal(0)).is_ok() {
+        libstd::sys::nanosleep(100_000_000);
+    }
 
-    println!("exit code {code}");
+    println!("process {pid} exited");
     Ok(())
 }
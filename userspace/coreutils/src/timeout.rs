@@ -0,0 +1,44 @@
+use alloc::vec::Vec;
+
+use anyhow::{anyhow, Result};
+use libstd::{
+    process::Child,
+    sys::{nanosleep, timestamp},
+};
+
+use crate::sleep::parse_duration;
+
+//
+
+pub fn cmd<'a>(mut args: impl Iterator<Item = &'a str>) -> Result<()> {
+    let duration = args
+        .next()
+        .ok_or_else(|| anyhow!("expected a time interval and a command"))?;
+    let duration = parse_duration(duration)?;
+    let program = args.next().ok_or_else(|| anyhow!("expected a command"))?;
+    let args: Vec<&str> = args.collect();
+
+    let mut child = Child::spawn(program, &args).map_err(|err| anyhow!("{program}: {err}"))?;
+    let deadline = timestamp().map_err(|err| anyhow!("{err}"))? + duration as u128;
+
+    // poll the child until it exits or the time runs out
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(|err| anyhow!("{err}"))? {
+            break status;
+        }
+
+        if timestamp().map_err(|err| anyhow!("{err}"))? >= deadline {
+            child.kill().map_err(|err| anyhow!("{err}"))?;
+            let status = child.wait().map_err(|err| anyhow!("{err}"))?;
+            return Err(anyhow!("{program} timed out, {status}"));
+        }
+
+        nanosleep(10_000_000);
+    };
+
+    if !status.success() {
+        return Err(anyhow!("{program} failed, {status}"));
+    }
+
+    Ok(())
+}
@@ -1,5 +1,4 @@
 use std::{
-    fs::File,
     io::{self, BufReader},
     ptr::NonNull,
     sync::Arc,
@@ -7,7 +6,11 @@ use std::{
 };
 
 use crossbeam_channel::{unbounded, Receiver, Sender};
-use hyperion_syscall::{fs::FileDesc, map_file, unmap_file};
+use hyperion_syscall::{
+    close,
+    fs::{FileDesc, Metadata},
+    map_file, metadata, recvmsg, unmap_file,
+};
 
 use crate::{
     global::Region,
@@ -24,7 +27,7 @@ pub struct Connection {
 
 struct ConnectionInner {
     event_buf: Receiver<Event>,
-    pending_windows: Receiver<usize>,
+    pending_windows: Receiver<(usize, FileDesc)>,
 
     socket_w: Arc<LocalStream>,
 }
@@ -54,18 +57,12 @@ impl Connection {
     pub fn new_window(&self) -> Result<Window, ConnectionClosed> {
         self.send_request(Request::NewWindow)?;
 
-        let window_id = self.inner.pending_windows.recv().unwrap();
+        let (_window_id, fbo) = self.inner.pending_windows.recv().unwrap();
 
-        let path = format!("/run/wm.window.{window_id}");
-        let fbo = File::options()
-            .read(true)
-            .write(true)
-            .create(false)
-            .open(path)
-            .unwrap();
-        let size = fbo.metadata().unwrap().len() as usize;
+        let mut meta = Metadata::zeroed();
+        metadata(fbo, &mut meta).unwrap();
 
-        let fbo_ptr = map_file(FileDesc(fbo.as_raw_fd()), None, size, 0).unwrap();
+        let fbo_ptr = map_file(fbo, None, meta.len, 0).unwrap();
 
         Ok(Window {
             // conn: self.clone(),
@@ -93,7 +90,7 @@ pub struct Window {
     // TODO: make each window a its own stream?
     // conn: Connection,
     // window_id: usize,
-    fbo: File,
+    fbo: FileDesc,
     fbo_ptr: NonNull<()>, // TODO: volatile write
     pub width: usize,
     pub height: usize,
@@ -127,8 +124,8 @@ impl Window {
 
 impl Drop for Window {
     fn drop(&mut self) {
-        unmap_file(FileDesc(self.fbo.as_raw_fd()), self.fbo_ptr, 0)
-            .expect("failed to unmap the fb");
+        unmap_file(self.fbo, self.fbo_ptr, 0).expect("failed to unmap the fb");
+        _ = close(self.fbo);
     }
 }
 
@@ -137,8 +134,10 @@ impl Drop for Window {
 pub fn conn_handler(
     mut socket_r: BufReader<Arc<LocalStream>>,
     event_buf_tx: Sender<Event>,
-    pending_windows_tx: Sender<usize>,
+    pending_windows_tx: Sender<(usize, FileDesc)>,
 ) {
+    let socket = FileDesc(socket_r.get_ref().as_raw_fd());
+
     // let mut buf = [0u8; 256];
 
     loop {
@@ -163,7 +162,16 @@ pub fn conn_handler(
         };
 
         let is_err = match res {
-            Message::NewWindow { window_id } => pending_windows_tx.send(window_id).is_err(),
+            Message::NewWindow { window_id } => {
+                // the window framebuffer is sent before the message itself
+                let mut fbo = [FileDesc::NONE];
+                if recvmsg(socket, &mut [], &mut fbo).is_err() || fbo[0] == FileDesc::NONE {
+                    eprintln!("window framebuffer missing, closing the connection");
+                    break;
+                }
+
+                pending_windows_tx.send((window_id, fbo[0])).is_err()
+            }
             Message::Event(ev) => event_buf_tx.send(ev).is_err(),
         };
 
@@ -7,7 +7,7 @@ use std::{
 };
 
 use crossbeam_channel::{unbounded, Receiver, Sender};
-use hyperion_syscall::{fs::FileDesc, map_file};
+use hyperion_syscall::{fs::FileDesc, map_file, sendmsg, unlink};
 
 use crate::{
     os::{AsRawFd, LocalListener, LocalStream},
@@ -61,6 +61,14 @@ impl Connection {
         self.message_stream.send_message(res)
     }
 
+    pub fn send_message_with_file(
+        &self,
+        res: Message,
+        file: &File,
+    ) -> Result<(), ConnectionClosed> {
+        self.message_stream.send_message_with_file(res, file)
+    }
+
     pub fn clone_tx(&self) -> MessageStream {
         self.message_stream.clone()
     }
@@ -77,6 +85,18 @@ impl MessageStream {
     pub fn send_message(&self, msg: Message) -> Result<(), ConnectionClosed> {
         rmp_serde::encode::write(&mut &*self.conn, &msg).map_err(|_| ConnectionClosed)
     }
+
+    /// send a message and pass a file to the client as a file descriptor
+    pub fn send_message_with_file(
+        &self,
+        msg: Message,
+        file: &File,
+    ) -> Result<(), ConnectionClosed> {
+        let buf = rmp_serde::to_vec(&msg).map_err(|_| ConnectionClosed)?;
+        let fd = FileDesc(file.as_raw_fd());
+        sendmsg(FileDesc(self.conn.as_raw_fd()), &buf, &[fd]).map_err(|_| ConnectionClosed)?;
+        Ok(())
+    }
 }
 
 //
@@ -86,10 +106,12 @@ pub fn new_window_framebuffer(
     height: usize,
     window_id: usize,
 ) -> (File, NonNull<u32>) {
-    // TODO: anonymous file + pass the fd instead of making a file that any proc can read
     let path = format!("/run/wm.window.{window_id}");
     // TODO: create_new
     let mut window_file = File::create(path.as_str()).unwrap();
+    // the file is passed to the client as a file descriptor,
+    // so the path isn't needed and no other process can open it
+    unlink(path.as_str()).unwrap();
     // TODO: truncate
     window_file
         .seek(SeekFrom::Start((pitch * height * 4 - 4) as u64))
@@ -9,7 +9,10 @@ pub struct ConnectionClosed;
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum Message {
-    NewWindow { window_id: usize },
+    /// the window framebuffer file descriptor is sent along with this message
+    NewWindow {
+        window_id: usize,
+    },
     // ResizeWindow { window_id: usize },
     Event(Event),
 }
@@ -24,9 +27,6 @@ pub struct Window {
     pub width: usize,
     /// window visual pixel height
     pub height: usize,
-    /// FIXME: should be a file descriptor over socket (sendmsg)
-    /// a file id of the window fb file `/run/wm.window.<id>`
-    pub shmem_file: usize,
 }
 
 //
@@ -1,17 +1,41 @@
 use std::{
-    env::args,
+    env::{args, remove_var, set_var, var, vars},
     fs::File,
     io::{stdin, stdout, BufRead, BufReader, Read, Write},
-    iter,
-    process::{Command, Stdio},
-    str,
+    iter, str,
+    sync::Mutex,
 };
 
 use hyperion_escape::{
     decode::{DecodedPart, EscapeDecoder},
     encode::*,
 };
-use hyperion_syscall::exit;
+use hyperion_syscall::{
+    chdir, exec_with_env, exit, fork, getcwd, getpgid, getrlimit, killpg,
+    limit::{Limit, LimitValue, Resource},
+    setpgid, setrlimit, setsid, sigaction,
+    signal::{Signal, SignalHandler},
+    tcsetpgrp, waitpid_with, WaitFlags, WaitStatus,
+};
+
+//
+
+/// background and stopped jobs
+static JOBS: Mutex<Vec<Job>> = Mutex::new(Vec::new());
+
+/// signals from the terminal that only the foreground job should get
+const JOB_CONTROL_SIGNALS: [Signal; 3] = [Signal::INT, Signal::TSTP, Signal::TTOU];
+
+//
+
+/// a command running in its own process group
+#[derive(Debug)]
+struct Job {
+    id: usize,
+    pgid: usize,
+    cmd: String,
+    stopped: bool,
+}
 
 //
 
@@ -19,6 +43,8 @@ fn main() {
     let name = args().next().unwrap();
     let name = name.rsplit('/').next().unwrap();
 
+    init_env();
+
     if let Some(file) = args().nth(1) {
         if file.as_str() == "-c" {
             immediate();
@@ -37,6 +63,11 @@ fn run_line(line: &str) {
         return;
     }
 
+    let (line, background) = match line.strip_suffix('&') {
+        Some(line) => (line.trim(), true),
+        None => (line, false),
+    };
+
     let mut parts = line.split(' ').map(|s| s.trim()).filter(|s| !s.is_empty());
 
     let Some(cmd) = parts.next() else {
@@ -45,23 +76,280 @@ fn run_line(line: &str) {
 
     match cmd {
         "exit" => exit(0),
+        "export" => return parts.for_each(export),
+        "unset" => return parts.for_each(unset),
+        "env" => return env(),
+        "cd" => return cd(parts.next()),
+        "pwd" => return pwd(),
+        "jobs" => return jobs(),
+        "fg" => return fg(parts.next()),
+        "bg" => return bg(parts.next()),
+        "ulimit" => return ulimit(parts),
         "" => return,
         _ => {}
     }
 
-    let cli = format!("/bin/{cmd}");
+    let Some(cli) = find_program(cmd) else {
+        println!("{cmd}: command not found");
+        return;
+    };
+
+    let args: Vec<&str> = parts.collect();
+    let envs: Vec<String> = vars()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect();
+    let envs: Vec<&str> = envs.iter().map(String::as_str).collect();
+
+    let pid = fork();
+    if pid == 0 {
+        // the child process
+        _ = setpgid(0, 0);
+        for signal in JOB_CONTROL_SIGNALS {
+            _ = sigaction(signal, SignalHandler::Default);
+        }
+        let err = exec_with_env(cli.as_str(), &args, &envs);
+        eprintln!("{cmd}: {err}");
+        exit(127);
+    }
+
+    // set from both sides, so that the group exists before either one continues
+    _ = setpgid(pid, 0);
+
+    let job = Job {
+        id: next_job_id(),
+        pgid: pid,
+        cmd: line.to_string(),
+        stopped: false,
+    };
+
+    if background {
+        println!("[{}] {pid}", job.id);
+        JOBS.lock().unwrap().push(job);
+    } else {
+        foreground(job, false);
+    }
+}
+
+/// give the terminal to a job and wait until it exits or gets stopped
+fn foreground(mut job: Job, cont: bool) {
+    _ = tcsetpgrp(job.pgid);
+    if cont {
+        _ = killpg(job.pgid, Signal::CONT);
+    }
+
+    let status = waitpid_with(job.pgid, WaitFlags::UNTRACED);
+
+    // take the terminal back
+    if let Ok(pgid) = getpgid(0) {
+        _ = tcsetpgrp(pgid);
+    }
+
+    if let Ok(Some((_, WaitStatus::Stopped(_)))) = status {
+        println!();
+        println!("[{}]+ Stopped {}", job.id, job.cmd);
+        job.stopped = true;
+        JOBS.lock().unwrap().push(job);
+    } else {
+        println!();
+    }
+}
+
+fn next_job_id() -> usize {
+    JOBS.lock()
+        .unwrap()
+        .iter()
+        .map(|job| job.id)
+        .max()
+        .unwrap_or(0)
+        + 1
+}
+
+/// remove a job by its id or the latest job, `%` before the id is optional
+fn take_job(id: Option<&str>) -> Option<Job> {
+    let mut jobs = JOBS.lock().unwrap();
+    let i = match id {
+        Some(id) => {
+            let id = id.trim_start_matches('%').parse::<usize>().ok()?;
+            jobs.iter().position(|job| job.id == id)?
+        }
+        None => jobs.len().checked_sub(1)?,
+    };
+    Some(jobs.remove(i))
+}
+
+/// reap and forget the jobs that have exited
+fn reap_jobs() {
+    JOBS.lock().unwrap().retain(|job| {
+        let alive = matches!(waitpid_with(job.pgid, WaitFlags::NOHANG), Ok(None));
+        if !alive {
+            println!("[{}]  Done {}", job.id, job.cmd);
+        }
+        alive
+    });
+}
+
+fn jobs() {
+    reap_jobs();
+    for job in JOBS.lock().unwrap().iter() {
+        let state = if job.stopped { "Stopped" } else { "Running" };
+        println!("[{}]  {state} {}", job.id, job.cmd);
+    }
+}
+
+fn fg(id: Option<&str>) {
+    reap_jobs();
+    let Some(job) = take_job(id) else {
+        println!("fg: no such job");
+        return;
+    };
+
+    println!("{}", job.cmd);
+    foreground(job, true);
+}
+
+fn bg(id: Option<&str>) {
+    reap_jobs();
+    let Some(mut job) = take_job(id) else {
+        println!("bg: no such job");
+        return;
+    };
+
+    if let Err(err) = killpg(job.pgid, Signal::CONT) {
+        println!("bg: {err}");
+        return;
+    }
+
+    println!("[{}] {} &", job.id, job.cmd);
+    job.stopped = false;
+    JOBS.lock().unwrap().push(job);
+}
+
+/// `ulimit [-H] [-a|-v|-m|-n|-u|-t] [N|unlimited]`
+///
+/// sets the soft limit, or both limits with `-H`
+fn ulimit<'a>(args: impl Iterator<Item = &'a str>) {
+    let mut resource = Some(Resource::OPEN_FILES);
+    let mut hard = false;
+    let mut value = None;
+
+    for arg in args {
+        match arg {
+            "-H" => hard = true,
+            "-a" => resource = None,
+            "-v" => resource = Some(Resource::ADDRESS_SPACE),
+            "-m" => resource = Some(Resource::RESIDENT),
+            "-n" => resource = Some(Resource::OPEN_FILES),
+            "-u" => resource = Some(Resource::THREADS),
+            "-t" => resource = Some(Resource::CPU_TIME),
+            "unlimited" => value = Some(Limit::INFINITY),
+            _ => match arg.parse::<u64>() {
+                Ok(n) => value = Some(n),
+                Err(_) => {
+                    println!("ulimit: invalid argument `{arg}`");
+                    return;
+                }
+            },
+        }
+    }
+
+    let Some(resource) = resource else {
+        for resource in Resource::ALL {
+            if let Ok(limit) = getrlimit(resource) {
+                println!("{:<20} {}", resource.name(), LimitValue(limit.cur));
+            }
+        }
+        return;
+    };
+
+    let old = match getrlimit(resource) {
+        Ok(old) => old,
+        Err(err) => return println!("ulimit: {err}"),
+    };
+
+    let Some(value) = value else {
+        let limit = if hard { old.max } else { old.cur };
+        return println!("{}", LimitValue(limit));
+    };
+
+    let limit = if hard {
+        Limit {
+            cur: old.cur.min(value),
+            max: value,
+        }
+    } else {
+        Limit {
+            cur: value,
+            max: old.max,
+        }
+    };
+    if let Err(err) = setrlimit(resource, limit) {
+        println!("ulimit: {err}");
+    }
+}
+
+fn init_env() {
+    for (key, value) in [("PATH", "/bin"), ("HOME", "/"), ("SHELL", "/bin/hysh")] {
+        if var(key).is_err() {
+            set_var(key, value);
+        }
+    }
+}
+
+fn export(var: &str) {
+    let Some((key, value)) = var.split_once('=') else {
+        println!("export: expected KEY=VALUE");
+        return;
+    };
 
-    let mut cmd = Command::new(cli)
-        .stdin(Stdio::inherit())
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit())
-        .args(parts)
-        .spawn()
-        .unwrap();
+    set_var(key, value);
+}
 
-    cmd.wait().unwrap();
+fn unset(key: &str) {
+    remove_var(key);
+}
 
-    println!();
+fn env() {
+    for (key, value) in vars() {
+        println!("{key}={value}");
+    }
+}
+
+fn cd(dir: Option<&str>) {
+    let home = var("HOME").ok();
+    let dir = dir
+        .map(str::to_string)
+        .or(home)
+        .unwrap_or_else(|| "/".into());
+
+    if let Err(err) = chdir(dir.as_str()) {
+        println!("cd: {dir}: {err}");
+    }
+}
+
+fn pwd() {
+    let mut buf = vec![0u8; 64];
+    loop {
+        let len = getcwd(&mut buf).unwrap();
+        if len <= buf.len() {
+            println!("{}", str::from_utf8(&buf[..len]).unwrap());
+            return;
+        }
+        buf.resize(len, 0);
+    }
+}
+
+/// find the program from `PATH`
+fn find_program(cmd: &str) -> Option<String> {
+    if cmd.contains('/') {
+        return Some(cmd.to_string());
+    }
+
+    let path = var("PATH").ok()?;
+
+    path.split(':')
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| format!("{}/{cmd}", dir.trim_end_matches('/')))
+        .find(|cli| File::open(cli).is_ok())
 }
 
 fn immediate() {
@@ -88,6 +376,12 @@ fn script(file: String) {
 }
 
 fn interactive(name: &str) {
+    // the shell leads its own session and the terminal signals only the foreground job
+    _ = setsid();
+    for signal in JOB_CONTROL_SIGNALS {
+        _ = sigaction(signal, SignalHandler::Ignore);
+    }
+
     Shell::new(name).run();
 }
 
@@ -0,0 +1,118 @@
+#![no_std]
+#![no_main]
+#![allow(internal_features)]
+#![feature(lang_items)]
+
+//! a minimal dynamic linker
+//!
+//! the kernel loads the program and this interpreter (from `PT_INTERP`), then this loads the
+//! shared objects that the program needs from `/lib`, applies the relocations and jumps to the
+//! program entry point with the original cli args, environment and auxiliary vector
+
+//
+
+use core::{arch::asm, fmt, slice};
+
+use hyperion_syscall::{
+    auxv::{self, AuxEntry, AuxType},
+    fs::FileDesc,
+};
+
+use self::object::{Object, Objects, Phdr};
+
+//
+
+mod object;
+
+//
+
+/// shared objects are loaded from here
+const LIB_DIR: &str = "/lib/";
+
+//
+
+#[no_mangle]
+extern "C" fn _start(argv: usize, envp: usize, auxv: *const AuxEntry) -> ! {
+    let get = |ty: AuxType| {
+        unsafe { auxv::get(auxv, ty) }.unwrap_or_else(|| fail!("auxv entry {} missing", ty.0))
+    };
+
+    let phdrs =
+        unsafe { slice::from_raw_parts(get(AuxType::PHDR) as *const Phdr, get(AuxType::PHNUM)) };
+    let program = unsafe { Object::program(phdrs) };
+
+    let mut objects = Objects::new();
+    objects.push(program);
+
+    // breadth first, each shared object is loaded only once
+    let mut i = 0;
+    while let Some(object) = objects.get(i) {
+        for needed in object.needed() {
+            if !objects.contains(needed) {
+                objects.push(object::load(needed));
+            }
+        }
+        i += 1;
+    }
+
+    // shared objects first, the program might copy their relocated data
+    for object in objects.iter().rev() {
+        object.relocate(&objects);
+        object.protect();
+    }
+
+    unsafe { enter(get(AuxType::ENTRY), argv, envp, auxv) }
+}
+
+/// jump to the program entry point like the kernel does
+unsafe fn enter(entry: usize, argv: usize, envp: usize, auxv: *const AuxEntry) -> ! {
+    // the rest of this stack is not needed anymore,
+    // the entry point expects an aligned stack with a return address 0
+    unsafe {
+        asm!(
+            "and rsp, -16",
+            "push 0",
+            "jmp {entry}",
+            entry = in(reg) entry,
+            in("rdi") argv,
+            in("rsi") envp,
+            in("rdx") auxv,
+            options(noreturn),
+        )
+    }
+}
+
+//
+
+#[macro_export]
+macro_rules! fail {
+    ($($t:tt)*) => {
+        $crate::_fail(format_args!($($t)*))
+    };
+}
+
+#[doc(hidden)]
+pub fn _fail(args: fmt::Arguments) -> ! {
+    struct Stderr;
+
+    impl fmt::Write for Stderr {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            hyperion_syscall::write(FileDesc(2), s.as_bytes())
+                .map(|_| {})
+                .map_err(|_| fmt::Error)
+        }
+    }
+
+    _ = fmt::Write::write_fmt(&mut Stderr, format_args!("ld.so: {args}\n"));
+    hyperion_syscall::exit(127);
+}
+
+#[panic_handler]
+fn panic_handler(info: &core::panic::PanicInfo) -> ! {
+    fail!("{info}");
+}
+
+// to fix `cargo clippy` without a target
+#[cfg(any(clippy, not(target_os = "none")))]
+#[lang = "eh_personality"]
+fn eh_personality() {}
@@ -0,0 +1,502 @@
+use core::{ffi::CStr, mem, ptr::NonNull, slice, str};
+
+use elf::{
+    abi::{
+        DT_GNU_HASH, DT_HASH, DT_JMPREL, DT_NEEDED, DT_NULL, DT_PLTRELSZ, DT_RELA, DT_RELASZ,
+        DT_STRTAB, DT_SYMTAB, EI_CLASS, ELFCLASS64, ELFMAGIC, ET_DYN, PF_W, PF_X, PT_DYNAMIC,
+        PT_LOAD, PT_PHDR, PT_TLS, R_X86_64_64, R_X86_64_COPY, R_X86_64_GLOB_DAT,
+        R_X86_64_JUMP_SLOT, R_X86_64_NONE, R_X86_64_RELATIVE, SHN_UNDEF, STB_LOCAL, STB_WEAK,
+    },
+    dynamic::Elf64_Dyn as Dyn,
+    file::Elf64_Ehdr as Ehdr,
+    hash::{gnu_hash, sysv_hash},
+    relocation::Elf64_Rela as Rela,
+    segment::Elf64_Phdr,
+    symbol::Elf64_Sym as Sym,
+};
+use hyperion_syscall::{
+    fs::{FileDesc, FileOpenFlags, Metadata},
+    mem::{MapFlags, Prot},
+};
+
+use crate::{fail, LIB_DIR};
+
+//
+
+pub type Phdr = Elf64_Phdr;
+
+/// the program and all of its shared objects
+const MAX_OBJECTS: usize = 16;
+
+const PAGE_MASK: usize = 0xfff;
+
+//
+
+/// every loaded object, in load order, which is also the symbol lookup order
+pub struct Objects {
+    objects: [Option<Object>; MAX_OBJECTS],
+    len: usize,
+}
+
+impl Objects {
+    pub const fn new() -> Self {
+        Self {
+            objects: [None; MAX_OBJECTS],
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, object: Object) {
+        let Some(slot) = self.objects.get_mut(self.len) else {
+            fail!("too many shared objects");
+        };
+        *slot = Some(object);
+        self.len += 1;
+    }
+
+    pub fn get(&self, i: usize) -> Option<Object> {
+        self.objects.get(i).copied().flatten()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.iter().any(|object| object.name == name)
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = Object> + '_ {
+        self.objects[..self.len].iter().copied().flatten()
+    }
+}
+
+//
+
+/// a program or a shared object mapped into memory
+#[derive(Clone, Copy)]
+pub struct Object {
+    /// the `DT_NEEDED` name, empty for the program
+    name: &'static str,
+    /// the difference between the load address and the link address
+    base: usize,
+    phdrs: &'static [Phdr],
+    dynamic: *const Dyn,
+
+    strtab: usize,
+    symtab: usize,
+    hash: usize,
+    gnu_hash: usize,
+    rela: usize,
+    rela_size: usize,
+    jmprel: usize,
+    jmprel_size: usize,
+}
+
+impl Object {
+    /// the program, the kernel already loaded it
+    ///
+    /// # Safety
+    /// `phdrs` has to be the program headers from [`hyperion_syscall::auxv::AuxType::PHDR`]
+    pub unsafe fn program(phdrs: &'static [Phdr]) -> Self {
+        // programs without `PT_PHDR` are assumed to be position dependent
+        let base = phdrs
+            .iter()
+            .find(|phdr| phdr.p_type == PT_PHDR)
+            .map_or(0, |phdr| {
+                (phdrs.as_ptr() as usize).wrapping_sub(phdr.p_vaddr as usize)
+            });
+
+        unsafe { Self::new("", base, phdrs) }
+    }
+
+    /// # Safety
+    /// the object has to be mapped at `base`
+    unsafe fn new(name: &'static str, base: usize, phdrs: &'static [Phdr]) -> Self {
+        let mut object = Self {
+            name,
+            base,
+            phdrs,
+            dynamic: core::ptr::null(),
+            strtab: 0,
+            symtab: 0,
+            hash: 0,
+            gnu_hash: 0,
+            rela: 0,
+            rela_size: 0,
+            jmprel: 0,
+            jmprel_size: 0,
+        };
+
+        let Some(dynamic) = phdrs.iter().find(|phdr| phdr.p_type == PT_DYNAMIC) else {
+            // statically linked
+            return object;
+        };
+        object.dynamic = object.addr(dynamic.p_vaddr) as *const Dyn;
+
+        for entry in object.dynamic_entries() {
+            let addr = object.addr(entry.d_un);
+            let val = entry.d_un as usize;
+            match entry.d_tag {
+                DT_STRTAB => object.strtab = addr,
+                DT_SYMTAB => object.symtab = addr,
+                DT_HASH => object.hash = addr,
+                DT_GNU_HASH => object.gnu_hash = addr,
+                DT_RELA => object.rela = addr,
+                DT_RELASZ => object.rela_size = val,
+                DT_JMPREL => object.jmprel = addr,
+                DT_PLTRELSZ => object.jmprel_size = val,
+                _ => {}
+            }
+        }
+
+        object
+    }
+
+    /// the names of the shared objects that this object needs
+    pub fn needed(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.dynamic_entries()
+            .filter(|entry| entry.d_tag == DT_NEEDED)
+            .map(|entry| self.str(entry.d_un as _))
+    }
+
+    /// apply all relocations of this object, the objects it depends on have to be relocated first
+    pub fn relocate(&self, objects: &Objects) {
+        if self.rela_size == 0 && self.jmprel_size == 0 {
+            return;
+        }
+
+        // the segments might be read-only already
+        self.protect_with(|_| Prot::READ | Prot::WRITE);
+
+        let rela = unsafe { table::<Rela>(self.rela, self.rela_size) };
+        let jmprel = unsafe { table::<Rela>(self.jmprel, self.jmprel_size) };
+        for rela in rela.iter().chain(jmprel) {
+            self.apply(objects, rela);
+        }
+    }
+
+    /// set the final page protections of all segments
+    pub fn protect(&self) {
+        self.protect_with(|p_flags| {
+            // READ is always enabled, like the kernel does
+            let mut prot = Prot::READ;
+            if p_flags & PF_W != 0 {
+                prot |= Prot::WRITE;
+            }
+            if p_flags & PF_X != 0 {
+                prot |= Prot::EXEC;
+            }
+            prot
+        });
+    }
+
+    fn protect_with(&self, mut f: impl FnMut(u32) -> Prot) {
+        for phdr in self.phdrs.iter().filter(|phdr| phdr.p_type == PT_LOAD) {
+            let start = self.addr(phdr.p_vaddr) & !PAGE_MASK;
+            let end = (self.addr(phdr.p_vaddr) + phdr.p_memsz as usize + PAGE_MASK) & !PAGE_MASK;
+            let Some(at) = NonNull::new(start as *mut ()) else {
+                continue;
+            };
+
+            if let Err(err) = hyperion_syscall::mprotect(at, end - start, f(phdr.p_flags)) {
+                fail!("failed to protect segment at {start:#x}: {err}");
+            }
+        }
+    }
+
+    fn apply(&self, objects: &Objects, rela: &Rela) {
+        let ty = rela.r_info as u32;
+        let sym = (rela.r_info >> 32) as usize;
+        let target = self.addr(rela.r_offset) as *mut usize;
+        let addend = rela.r_addend as usize;
+
+        let value = match ty {
+            R_X86_64_NONE => return,
+            R_X86_64_RELATIVE => self.base.wrapping_add(addend),
+            R_X86_64_64 => self.resolve(objects, sym, false).wrapping_add(addend),
+            R_X86_64_GLOB_DAT | R_X86_64_JUMP_SLOT => self.resolve(objects, sym, false),
+            R_X86_64_COPY => {
+                // the program has its own copy of the shared object data
+                let size = self.sym(sym).st_size as usize;
+                let from = self.resolve(objects, sym, true);
+                unsafe { core::ptr::copy_nonoverlapping(from as *const u8, target.cast(), size) };
+                return;
+            }
+            _ => fail!(
+                "unsupported relocation type {ty} in `{}`",
+                self.display_name()
+            ),
+        };
+
+        unsafe { target.write_unaligned(value) };
+    }
+
+    /// the address of a symbol, local symbols are always from this object
+    /// and the rest are looked up from all objects in load order
+    fn resolve(&self, objects: &Objects, sym: usize, skip_self: bool) -> usize {
+        let symbol = self.sym(sym);
+        if symbol.st_info >> 4 == STB_LOCAL {
+            return self.addr(symbol.st_value);
+        }
+
+        let name = self.str(symbol.st_name as _);
+        let found = objects
+            .iter()
+            .filter(|object| !skip_self || object.base != self.base)
+            .find_map(|object| Some(object.addr(object.lookup(name)?.st_value)));
+        if let Some(addr) = found {
+            return addr;
+        }
+
+        if symbol.st_shndx != SHN_UNDEF && !skip_self {
+            self.addr(symbol.st_value)
+        } else if symbol.st_info >> 4 == STB_WEAK {
+            0
+        } else {
+            fail!("undefined symbol `{name}` in `{}`", self.display_name());
+        }
+    }
+
+    /// find a global symbol defined in this object
+    fn lookup(&self, name: &str) -> Option<&'static Sym> {
+        let defined = |sym: &&Sym| {
+            sym.st_shndx != SHN_UNDEF
+                && sym.st_info >> 4 != STB_LOCAL
+                && self.str(sym.st_name as _) == name
+        };
+
+        if self.gnu_hash != 0 {
+            self.gnu_lookup(name).filter(defined)
+        } else if self.hash != 0 {
+            self.sysv_lookup(name, defined)
+        } else {
+            None
+        }
+    }
+
+    fn gnu_lookup(&self, name: &str) -> Option<&'static Sym> {
+        let header = unsafe { slice::from_raw_parts(self.gnu_hash as *const u32, 4) };
+        let (bucket_count, sym_offset, bloom_size) = (header[0], header[1], header[2]);
+        if bucket_count == 0 {
+            return None;
+        }
+
+        // the bloom filter is skipped
+        let buckets = self.gnu_hash + 16 + bloom_size as usize * mem::size_of::<u64>();
+        let chains = buckets + bucket_count as usize * mem::size_of::<u32>();
+
+        let hash = gnu_hash(name.as_bytes());
+        let mut i = unsafe { *(buckets as *const u32).add((hash % bucket_count) as usize) };
+        if i < sym_offset {
+            return None;
+        }
+
+        loop {
+            let chain_hash = unsafe { *(chains as *const u32).add((i - sym_offset) as usize) };
+            if hash | 1 == chain_hash | 1 {
+                let sym = self.sym(i as usize);
+                if self.str(sym.st_name as _) == name {
+                    return Some(sym);
+                }
+            }
+            if chain_hash & 1 != 0 {
+                return None;
+            }
+            i += 1;
+        }
+    }
+
+    fn sysv_lookup(&self, name: &str, defined: impl Fn(&&Sym) -> bool) -> Option<&'static Sym> {
+        let header = unsafe { slice::from_raw_parts(self.hash as *const u32, 2) };
+        let (bucket_count, chain_count) = (header[0] as usize, header[1] as usize);
+        if bucket_count == 0 {
+            return None;
+        }
+
+        let table = unsafe {
+            slice::from_raw_parts(self.hash as *const u32, 2 + bucket_count + chain_count)
+        };
+        let (buckets, chains) = table[2..].split_at(bucket_count);
+
+        let mut i = buckets[sysv_hash(name.as_bytes()) as usize % bucket_count] as usize;
+        while i != 0 {
+            let sym = self.sym(i);
+            if defined(&sym) {
+                return Some(sym);
+            }
+            i = *chains.get(i)? as usize;
+        }
+        None
+    }
+
+    fn dynamic_entries(&self) -> impl Iterator<Item = &'static Dyn> {
+        let dynamic = self.dynamic;
+        (0..)
+            .take_while(move |_| !dynamic.is_null())
+            .map(move |i| unsafe { &*dynamic.add(i) })
+            .take_while(|entry| entry.d_tag != DT_NULL)
+    }
+
+    fn sym(&self, i: usize) -> &'static Sym {
+        unsafe { &*(self.symtab as *const Sym).add(i) }
+    }
+
+    fn str(&self, offset: usize) -> &'static str {
+        let s = unsafe { CStr::from_ptr((self.strtab + offset) as *const _) };
+        s.to_str()
+            .unwrap_or_else(|_| fail!("invalid string in `{}`", self.display_name()))
+    }
+
+    fn addr(&self, link: u64) -> usize {
+        self.base.wrapping_add(link as usize)
+    }
+
+    fn display_name(&self) -> &'static str {
+        if self.name.is_empty() {
+            "<program>"
+        } else {
+            self.name
+        }
+    }
+}
+
+//
+
+/// load a shared object from [`LIB_DIR`]
+pub fn load(name: &'static str) -> Object {
+    let mut buf = [0u8; 256];
+    let path = LIB_DIR.len() + name.len();
+    if path > buf.len() {
+        fail!("shared object name `{name}` is too long");
+    }
+    buf[..LIB_DIR.len()].copy_from_slice(LIB_DIR.as_bytes());
+    buf[LIB_DIR.len()..path].copy_from_slice(name.as_bytes());
+    let path = unsafe { str::from_utf8_unchecked(&buf[..path]) };
+
+    let file = read_file(path);
+    let (file_ptr, file_len) = (NonNull::from(&mut *file).cast(), file.len());
+    let object = unsafe { map_object(name, file) };
+    _ = hyperion_syscall::munmap(file_ptr, (file_len + PAGE_MASK) & !PAGE_MASK);
+    object
+}
+
+/// read the whole file into temporary anonymous memory
+fn read_file(path: &str) -> &'static mut [u8] {
+    let file = hyperion_syscall::open(path, FileOpenFlags::READ, 0)
+        .unwrap_or_else(|err| fail!("cannot open `{path}`: {err}"));
+
+    let mut meta = Metadata::zeroed();
+    if let Err(err) = hyperion_syscall::metadata(file, &mut meta) {
+        fail!("cannot read `{path}`: {err}");
+    }
+    if meta.len == 0 {
+        fail!("`{path}` is empty");
+    }
+
+    let bytes = anonymous(meta.len);
+    let mut read = 0;
+    while read < bytes.len() {
+        match hyperion_syscall::read(file, &mut bytes[read..]) {
+            Ok(0) => fail!("`{path}` is truncated"),
+            Ok(n) => read += n,
+            Err(err) => fail!("cannot read `{path}`: {err}"),
+        }
+    }
+
+    _ = hyperion_syscall::close(file);
+    bytes
+}
+
+/// map the `PT_LOAD` segments of a shared object at some free address
+///
+/// # Safety
+/// the returned object lives forever
+unsafe fn map_object(name: &'static str, file: &[u8]) -> Object {
+    if file.len() < mem::size_of::<Ehdr>() {
+        fail!("`{name}` is not an ELF file");
+    }
+    let ehdr = unsafe { &*(file.as_ptr() as *const Ehdr) };
+    if ehdr.e_ident[..4] != ELFMAGIC || ehdr.e_ident[EI_CLASS] != ELFCLASS64 {
+        fail!("`{name}` is not a 64 bit ELF file");
+    }
+    if ehdr.e_type != ET_DYN {
+        fail!("`{name}` is not a shared object");
+    }
+
+    let phoff = ehdr.e_phoff as usize;
+    let phnum = ehdr.e_phnum as usize;
+    if phoff & (mem::align_of::<Phdr>() - 1) != 0
+        || phoff
+            .checked_add(phnum * mem::size_of::<Phdr>())
+            .is_none_or(|end| end > file.len())
+    {
+        fail!("`{name}` has invalid program headers");
+    }
+    let file_phdrs =
+        unsafe { slice::from_raw_parts(file.as_ptr().add(phoff) as *const Phdr, phnum) };
+
+    let loads = || file_phdrs.iter().filter(|phdr| phdr.p_type == PT_LOAD);
+    let lo = loads().map(|phdr| phdr.p_vaddr as usize).min().unwrap_or(0) & !PAGE_MASK;
+    let hi = loads()
+        .map(|phdr| (phdr.p_vaddr + phdr.p_memsz) as usize)
+        .max()
+        .unwrap_or(0);
+    let hi = (hi + PAGE_MASK) & !PAGE_MASK;
+    if hi <= lo {
+        fail!("`{name}` has nothing to load");
+    }
+    if file_phdrs.iter().any(|phdr| phdr.p_type == PT_TLS) {
+        fail!("`{name}` uses thread local storage, which is only supported in the program");
+    }
+
+    // the whole object is reserved at once, so the segments keep their relative positions
+    let region = anonymous(hi - lo);
+    let base = (region.as_mut_ptr() as usize).wrapping_sub(lo);
+
+    for phdr in loads() {
+        let (offset, size) = (phdr.p_offset as usize, phdr.p_filesz as usize);
+        let Some(data) = file.get(offset..offset + size) else {
+            fail!("`{name}` is truncated");
+        };
+        let at = phdr.p_vaddr as usize - lo;
+        region[at..at + size].copy_from_slice(data);
+    }
+
+    // the program headers have to stay readable after the file is unmapped
+    let phdrs = file_phdrs
+        .iter()
+        .find(|phdr| phdr.p_type == PT_PHDR)
+        .map(|phdr| phdr.p_vaddr as usize)
+        .or_else(|| {
+            loads()
+                .find(|phdr| {
+                    (phdr.p_offset..phdr.p_offset + phdr.p_filesz).contains(&(phoff as u64))
+                })
+                .map(|phdr| phdr.p_vaddr as usize + phoff - phdr.p_offset as usize)
+        })
+        .unwrap_or_else(|| fail!("`{name}` does not load its program headers"));
+    let phdrs = unsafe { slice::from_raw_parts(base.wrapping_add(phdrs) as *const Phdr, phnum) };
+
+    unsafe { Object::new(name, base, phdrs) }
+}
+
+/// zeroed read-write memory that is never freed
+fn anonymous(size: usize) -> &'static mut [u8] {
+    let ptr = hyperion_syscall::mmap(
+        None,
+        size,
+        Prot::READ | Prot::WRITE,
+        MapFlags::PRIVATE | MapFlags::ANONYMOUS,
+        FileDesc::NONE,
+        0,
+    )
+    .unwrap_or_else(|err| fail!("out of memory: {err}"));
+
+    unsafe { slice::from_raw_parts_mut(ptr.as_ptr().cast(), size) }
+}
+
+/// # Safety
+/// `addr` has to point to `size` bytes of `T`s
+unsafe fn table<T>(addr: usize, size: usize) -> &'static [T] {
+    if addr == 0 {
+        return &[];
+    }
+    unsafe { slice::from_raw_parts(addr as *const T, size / mem::size_of::<T>()) }
+}
@@ -1,3 +1,11 @@
 fn main() {
-    println!("cargo:rustc-link-arg=-no-pie");
+    // the `pie` binary uses the default static position independent executable
+    println!("cargo:rustc-link-arg-bin=sample-elf=-no-pie");
+
+    // the `dynamic` binary is linked against `libsample.so` and `/lib/ld.so` loads it,
+    // `-Bdynamic` because the target links everything statically by default
+    let lib = std::env::var("CARGO_BIN_FILE_SAMPLE_SO").unwrap();
+    println!("cargo:rustc-link-arg-bin=dynamic=--dynamic-linker=/lib/ld.so");
+    println!("cargo:rustc-link-arg-bin=dynamic=-Bdynamic");
+    println!("cargo:rustc-link-arg-bin=dynamic={lib}");
 }
@@ -0,0 +1,14 @@
+#![no_std]
+
+//
+
+use libstd::{env, println};
+
+//
+
+/// print the cli args, one per line
+pub fn main() {
+    for arg in env::args() {
+        println!("{arg}");
+    }
+}
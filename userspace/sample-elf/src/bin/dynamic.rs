@@ -0,0 +1,22 @@
+#![no_std]
+
+//
+
+use core::str::from_utf8;
+
+use libstd::println;
+
+//
+
+// from `/lib/libsample.so`, `/lib/ld.so` resolves these when the program starts
+extern "C" {
+    static SAMPLE_NAME: [u8; 12];
+
+    fn sample_add(a: usize, b: usize) -> usize;
+}
+
+pub fn main() {
+    let name = from_utf8(unsafe { &SAMPLE_NAME }).unwrap();
+    let sum = unsafe { sample_add(1, 2) };
+    println!("1 + 2 = {sum} from {name}");
+}
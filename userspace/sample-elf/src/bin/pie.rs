@@ -0,0 +1,29 @@
+#![no_std]
+
+//
+
+use core::hint::black_box;
+
+use libstd::println;
+
+//
+
+type Term = (&'static str, fn() -> usize);
+
+/// string slices and function pointers in statics need `R_X86_64_RELATIVE` relocations
+static TERMS: [Term; 2] = [("first", first), ("second", second)];
+
+fn first() -> usize {
+    1
+}
+
+fn second() -> usize {
+    2
+}
+
+pub fn main() {
+    let terms = black_box(&TERMS);
+
+    let sum: usize = terms.iter().map(|(_, term)| term()).sum();
+    println!("{} + {} = {sum}", terms[0].0, terms[1].0);
+}
@@ -0,0 +1,7 @@
+fn main() {
+    // `x86_64-unknown-none` has no `cdylib` crate type,
+    // so the linker makes this bin a shared object instead of a static position independent executable
+    println!("cargo:rustc-link-arg-bins=-no-pie");
+    println!("cargo:rustc-link-arg-bins=-shared");
+    println!("cargo:rustc-link-arg-bins=-soname=libsample.so");
+}
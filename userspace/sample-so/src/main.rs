@@ -0,0 +1,42 @@
+#![no_std]
+#![no_main]
+#![allow(internal_features)]
+#![feature(lang_items)]
+
+//! a small shared object for testing `/lib/ld.so`, installed as `/lib/libsample.so`
+
+//
+
+use core::hint::black_box;
+
+//
+
+/// the program reads this through a `R_X86_64_GLOB_DAT` relocation
+#[no_mangle]
+pub static SAMPLE_NAME: [u8; 12] = *b"libsample.so";
+
+/// function pointers in statics need `R_X86_64_RELATIVE` relocations in the shared object too
+static ADD: fn(usize, usize) -> usize = add;
+
+fn add(a: usize, b: usize) -> usize {
+    a + b
+}
+
+#[no_mangle]
+pub extern "C" fn sample_add(a: usize, b: usize) -> usize {
+    black_box(ADD)(a, b)
+}
+
+//
+
+#[panic_handler]
+fn panic_handler(_: &core::panic::PanicInfo) -> ! {
+    loop {
+        core::hint::spin_loop();
+    }
+}
+
+// to fix `cargo clippy` without a target
+#[cfg(any(clippy, not(target_os = "none")))]
+#[lang = "eh_personality"]
+fn eh_personality() {}
@@ -7,6 +7,7 @@ use std::{
 };
 
 use hyperion_escape::encode::{CursorDown, CursorLeft, CursorRight, CursorUp};
+use hyperion_syscall::{killpg, signal::Signal, tcgetpgrp};
 use hyperion_windowing::{
     client::Connection,
     shared::{ElementState, Event},
@@ -63,6 +64,9 @@ fn main() {
         .spawn()
         .unwrap();
 
+    // the shell is the session leader, the terminal signals its foreground job
+    let session = shell.id() as usize;
+
     let mut stdin = shell.stdin.take().unwrap();
     let mut stdout = shell.stdout.take().unwrap();
     let mut stderr = shell.stderr.take().unwrap();
@@ -88,6 +92,8 @@ fn main() {
                     code: 103,
                     state: ElementState::Pressed,
                 } => stdin.write_fmt(format_args!("{}", CursorRight(1))).unwrap(),
+                Event::Text { ch: '\u{3}' } => signal_foreground(session, Signal::INT),
+                Event::Text { ch: '\u{1a}' } => signal_foreground(session, Signal::TSTP),
                 Event::Text { ch } => {
                     let mut buf = [0u8; 4];
                     let str = ch.encode_utf8(&mut buf);
@@ -134,3 +140,10 @@ fn main() {
     let ec = shell.wait().unwrap().code().unwrap_or(0);
     exit(ec);
 }
+
+/// send a signal to the foreground process group of a session, like Ctrl-C does
+fn signal_foreground(session: usize, signal: Signal) {
+    if let Ok(pgid) = tcgetpgrp(session) {
+        _ = killpg(pgid, signal);
+    }
+}
@@ -18,7 +18,7 @@ pub fn keyboard() {
     let mut keyboard = Keyboard::new(
         ScancodeSet1::new(),
         AnyLayout::Us104Key(Us104Key),
-        HandleControl::Ignore,
+        HandleControl::MapLettersToUnicode,
     );
 
     loop {
@@ -257,6 +257,14 @@ fn handle_client(client: Connection) {
 
                 let (window_file, shmem_ptr) = new_window_framebuffer(400, 400, id);
 
+                // the client gets the framebuffer as a file descriptor
+                if client
+                    .send_message_with_file(Message::NewWindow { window_id: id }, &window_file)
+                    .is_err()
+                {
+                    break;
+                }
+
                 let mut windows = WINDOWS.lock().unwrap();
                 windows.push(Window {
                     info: WindowInfo {
@@ -274,13 +282,6 @@ fn handle_client(client: Connection) {
                 });
                 drop(windows);
                 own_windows.insert(id);
-
-                if client
-                    .send_message(Message::NewWindow { window_id: id })
-                    .is_err()
-                {
-                    break;
-                }
             }
             Request::CloseConnection => {
                 let mut windows = WINDOWS.lock().unwrap();